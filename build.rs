@@ -0,0 +1,15 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/rfind.proto");
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("No vendored protoc available");
+        std::env::set_var("PROTOC", protoc);
+        // Only the server is needed; the generated client code assumes the 2021 prelude
+        tonic_build::configure()
+            .build_client(false)
+            .compile_protos(&["proto/rfind.proto"], &["proto"])
+            .expect("Failed to compile protos");
+    }
+}
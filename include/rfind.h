@@ -0,0 +1,89 @@
+#ifndef RFIND_H
+#define RFIND_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit. */
+
+#include <stdint.h>
+
+// The search ran to completion
+#define RFIND_OK 0
+
+// The search was stopped by the callback or a cancel handle
+#define RFIND_CANCELLED 1
+
+// A required argument was null or not valid UTF-8
+#define RFIND_ERR_INVALID_ARGUMENT -1
+
+// The pattern is not a valid glob
+#define RFIND_ERR_INVALID_PATTERN -2
+
+// The root is not a directory that can be read
+#define RFIND_ERR_ROOT -3
+
+// The search failed unexpectedly
+#define RFIND_ERR_INTERNAL -4
+
+// Stops running searches from any thread, see [`rfind_cancel_new`]
+typedef struct RfindCancel RfindCancel;
+
+// What to search for, mirroring the command line options of the same names
+typedef struct RfindOptions {
+  // Glob or substring matched against entry names (NUL-terminated UTF-8)
+  const char *pattern;
+  // Directory to search (NUL-terminated UTF-8)
+  const char *root;
+  // Maximum depth below the root, or 0 for the default
+  uint32_t max_depth;
+  // Number of scanner threads, or 0 for one per CPU core
+  uint32_t threads;
+  // Nonzero to follow all symlinks, like `-L`
+  int32_t follow_symlinks;
+} RfindOptions;
+
+// Receives each match as a NUL-terminated path, which is only valid for the
+// duration of the call. Returning nonzero stops the search.
+typedef int32_t (*RfindCallback)(const char *path, void *user_data);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Create a handle for cancelling a search, to be freed with
+// [`rfind_cancel_free`]. A handle can be shared by several searches.
+RfindCancel *rfind_cancel_new(void);
+
+// Stop the searches using `cancel`, including ones not yet started
+//
+// # Safety
+//
+// `cancel` must be null or a handle from [`rfind_cancel_new`] that has not
+// been freed.
+void rfind_cancel_trigger(const RfindCancel *cancel);
+
+// Free a handle from [`rfind_cancel_new`]
+//
+// # Safety
+//
+// `cancel` must be null or a handle from [`rfind_cancel_new`] that is not
+// freed yet and not used by a running search.
+void rfind_cancel_free(RfindCancel *cancel);
+
+// Search below `options->root`, calling `callback` with each match on the
+// calling thread. Returns [`RFIND_OK`] once the whole tree has been
+// walked, [`RFIND_CANCELLED`] if the callback or `cancel` stopped the
+// search, or a negative error code.
+//
+// # Safety
+//
+// `options` must point to valid options whose strings are NUL-terminated,
+// and `cancel` must be null or a live handle from [`rfind_cancel_new`].
+int32_t rfind_search(const RfindOptions *options,
+                     RfindCallback callback,
+                     void *user_data,
+                     const RfindCancel *cancel);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* RFIND_H */
@@ -0,0 +1,37 @@
+# rfind shell integration for bash
+# Add to ~/.bashrc:  eval "$(rfind init bash)"
+# Picking uses fzf (https://github.com/junegunn/fzf).
+
+# rcd [PATTERN]: pick a directory below the current one and cd into it
+rcd() {
+    local dir
+    dir="$(command rfind "${1:-*}" -d . -t d 2>/dev/null | fzf --height 40% --reverse)" &&
+        cd -- "$dir" &&
+        __rfind_visited "$PWD"
+}
+
+# rj KEYWORD...: cd into the best match among directories visited with rcd
+rj() {
+    local dir
+    dir="$(command rfind jump "$@")" && cd -- "$dir" && __rfind_visited "$PWD"
+}
+
+# Record a visit in rfind's jump database, and in zoxide's if it is installed
+__rfind_visited() {
+    command rfind jump --add "$1"
+    if command -v zoxide >/dev/null 2>&1; then
+        zoxide add -- "$1"
+    fi
+}
+
+# Ctrl-X f: insert a picked path at the cursor
+__rfind_insert_path() {
+    local path
+    path="$(command rfind '*' -d . 2>/dev/null | fzf --height 40% --reverse)" || return
+    path="$(printf '%q' "$path")"
+    READLINE_LINE="${READLINE_LINE:0:READLINE_POINT}${path}${READLINE_LINE:READLINE_POINT}"
+    READLINE_POINT=$((READLINE_POINT + ${#path}))
+}
+if [[ $- == *i* ]]; then
+    bind -x '"\C-xf": __rfind_insert_path'
+fi
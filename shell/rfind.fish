@@ -0,0 +1,37 @@
+# rfind shell integration for fish
+# Add to ~/.config/fish/config.fish:  rfind init fish | source
+# Picking uses fzf (https://github.com/junegunn/fzf).
+
+# rcd [PATTERN]: pick a directory below the current one and cd into it
+function rcd
+    set -l pattern '*'
+    if set -q argv[1]
+        set pattern $argv[1]
+    end
+    set -l dir (command rfind $pattern -d . -t d 2>/dev/null | fzf --height 40% --reverse)
+    and cd -- $dir
+    and __rfind_visited $PWD
+end
+
+# rj KEYWORD...: cd into the best match among directories visited with rcd
+function rj
+    set -l dir (command rfind jump $argv)
+    and cd -- $dir
+    and __rfind_visited $PWD
+end
+
+# Record a visit in rfind's jump database, and in zoxide's if it is installed
+function __rfind_visited
+    command rfind jump --add $argv[1]
+    if type -q zoxide
+        zoxide add -- $argv[1]
+    end
+end
+
+# Ctrl-X f: insert a picked path at the cursor
+function __rfind_insert_path
+    set -l path (command rfind '*' -d . 2>/dev/null | fzf --height 40% --reverse)
+    and commandline --insert -- (string escape -- $path)
+    commandline --function repaint
+end
+bind \cxf __rfind_insert_path
@@ -0,0 +1,531 @@
+//! Actions applied to matched paths (`--exec`, `--rename`, `--move-to`, `--delete`,
+//! `--delete --trash`).
+//!
+//! Every action first turns a path into an [`Operation`] describing exactly what
+//! will happen to it. The [`Executor`] then either performs the operation or, in
+//! dry-run mode, prints it, so all actions share the same dry-run behaviour.
+
+use crate::journal::{Journal, JournalEntry};
+use crossbeam_channel::{bounded, Sender};
+use parking_lot::Mutex;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread::{self, JoinHandle};
+
+/// Commands treated as mutating when run through `--exec`
+const DESTRUCTIVE_COMMANDS: &[&str] = &[
+    "chgrp", "chmod", "chown", "cp", "dd", "ln", "mv", "rm", "rmdir", "shred", "srm", "truncate",
+    "unlink",
+];
+
+/// Whether `program` (a path or bare command name) is a known destructive command
+fn is_destructive_command(program: &OsStr) -> bool {
+    Path::new(program)
+        .file_name()
+        .is_some_and(|name| DESTRUCTIVE_COMMANDS.iter().any(|command| name == *command))
+}
+
+/// Something to do with every match
+#[derive(Debug, Clone)]
+pub enum Action {
+    /// Run a command, replacing `{}` in its arguments with the path
+    /// (the path is appended if no argument contains `{}`)
+    Exec(Vec<String>),
+    /// Run a command on many paths at once (`--exec ... {} +`), appending
+    /// them after its arguments
+    ExecBatch(Vec<String>),
+    /// Replace the first occurrence of `from` in the file name with `to`
+    Rename { from: String, to: String },
+    /// Move into a directory, keeping the file name
+    MoveTo(PathBuf),
+    /// Remove files, symlinks and empty directories
+    Delete,
+    /// Move to the platform trash (Freedesktop trash, macOS Trash, Windows Recycle Bin)
+    Trash,
+}
+
+impl Action {
+    /// Parse a `--rename` argument of the form `FROM=TO`
+    pub fn parse_rename(s: &str) -> Result<Self, String> {
+        match s.split_once('=') {
+            Some((from, to)) if !from.is_empty() => Ok(Action::Rename {
+                from: from.to_string(),
+                to: to.to_string(),
+            }),
+            _ => Err(format!("Invalid rename '{}': expected FROM=TO", s)),
+        }
+    }
+
+    /// Whether this action changes the filesystem
+    pub fn is_mutating(&self) -> bool {
+        match self {
+            Action::Exec(command) | Action::ExecBatch(command) => command
+                .first()
+                .is_some_and(|program| is_destructive_command(program.as_ref())),
+            _ => true,
+        }
+    }
+
+    /// Describe what this action would do to `path`
+    pub fn plan(&self, path: &Path) -> Operation {
+        match self {
+            Action::Exec(command) => {
+                let mut argv: Vec<OsString> = Vec::with_capacity(command.len() + 1);
+                let mut substituted = false;
+                for arg in command {
+                    if arg.contains("{}") {
+                        substituted = true;
+                        argv.push(substitute_path(arg, path));
+                    } else {
+                        argv.push(arg.into());
+                    }
+                }
+                if !substituted {
+                    argv.push(path.into());
+                }
+                Operation::Exec(argv)
+            }
+            Action::Rename { from, to } => {
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().replacen(from.as_str(), to, 1))
+                    .unwrap_or_default();
+                Operation::Move {
+                    from: path.to_path_buf(),
+                    to: path.with_file_name(name),
+                }
+            }
+            Action::MoveTo(dir) => Operation::Move {
+                from: path.to_path_buf(),
+                to: dir.join(path.file_name().unwrap_or_default()),
+            },
+            Action::ExecBatch(command) => batch_operation(command, &[path.to_path_buf()]),
+            Action::Delete => Operation::Delete(path.to_path_buf()),
+            Action::Trash => Operation::Trash(path.to_path_buf()),
+        }
+    }
+
+    /// The `--exec` action for a command given on the command line, batched
+    /// if it ends with `{} +` like with find
+    pub fn exec(command: &[String]) -> Result<Self, String> {
+        let plus = command
+            .windows(2)
+            .position(|pair| pair[0] == "{}" && pair[1] == "+");
+        match plus {
+            // Otherwise the first match would be run as the program
+            Some(0) => Err("'{} +' needs a command before it in --exec".to_string()),
+            Some(at) if at + 2 == command.len() => Ok(Action::ExecBatch(command[..at].to_vec())),
+            // Anything after it was taken as part of the command
+            Some(_) => Err(format!(
+                "'{{}} +' must end --exec; end it with ';' to give more options after it: {}",
+                command.join(" ")
+            )),
+            None => Ok(Action::Exec(command.to_vec())),
+        }
+    }
+}
+
+/// `arg` with each `{}` replaced by `path`, which is kept as it is even when
+/// it is not valid UTF-8
+fn substitute_path(arg: &str, path: &Path) -> OsString {
+    let mut substituted = OsString::new();
+    for (i, part) in arg.split("{}").enumerate() {
+        if i > 0 {
+            substituted.push(path);
+        }
+        substituted.push(part);
+    }
+    substituted
+}
+
+/// `command` run with `paths` as its last arguments
+fn batch_operation(command: &[String], paths: &[PathBuf]) -> Operation {
+    let argv = command
+        .iter()
+        .map(OsString::from)
+        .chain(paths.iter().map(OsString::from))
+        .collect();
+    Operation::Exec(argv)
+}
+
+/// A single planned change (or command) for one path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    Exec(Vec<OsString>),
+    Move { from: PathBuf, to: PathBuf },
+    Delete(PathBuf),
+    Trash(PathBuf),
+}
+
+impl Operation {
+    /// Whether performing this operation changes the filesystem
+    pub fn is_mutating(&self) -> bool {
+        match self {
+            Operation::Exec(argv) => argv
+                .first()
+                .is_some_and(|program| is_destructive_command(program)),
+            Operation::Move { from, to } => from != to,
+            Operation::Delete(_) | Operation::Trash(_) => true,
+        }
+    }
+
+    /// Whether `--ok` asks before performing this operation
+    fn needs_confirmation(&self) -> bool {
+        matches!(
+            self,
+            Operation::Exec(_) | Operation::Delete(_) | Operation::Trash(_)
+        )
+    }
+
+    /// Carry out the operation
+    pub fn perform(&self) -> io::Result<()> {
+        match self {
+            Operation::Exec(argv) => {
+                let status = Command::new(&argv[0]).args(&argv[1..]).status()?;
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(io::Error::other(format!("command exited with {}", status)))
+                }
+            }
+            Operation::Move { from, to } => {
+                if from == to {
+                    return Ok(());
+                }
+                // Never silently replace an existing file
+                if fs::symlink_metadata(to).is_ok() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("{} already exists", to.display()),
+                    ));
+                }
+                match fs::rename(from, to) {
+                    Ok(()) => Ok(()),
+                    // Renaming fails across filesystems; fall back to copying regular files
+                    Err(e) if fs::symlink_metadata(from).is_ok_and(|m| m.is_file()) => {
+                        fs::copy(from, to).map_err(|_| e)?;
+                        fs::remove_file(from)
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            Operation::Delete(path) => {
+                if fs::symlink_metadata(path)?.is_dir() {
+                    fs::remove_dir(path)
+                } else {
+                    fs::remove_file(path)
+                }
+            }
+            Operation::Trash(path) => trash::delete(path).map_err(|e| match e {
+                #[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios")))]
+                trash::Error::FileSystem { source, .. } => source,
+                e => io::Error::other(e.to_string()),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operation::Exec(argv) => {
+                f.write_str("exec")?;
+                for arg in argv {
+                    write!(f, " {}", shell_quote(&arg.to_string_lossy()))?;
+                }
+                Ok(())
+            }
+            Operation::Move { from, to } => write!(
+                f,
+                "move {} -> {}",
+                shell_quote(&from.to_string_lossy()),
+                shell_quote(&to.to_string_lossy())
+            ),
+            Operation::Delete(path) => write!(f, "delete {}", shell_quote(&path.to_string_lossy())),
+            Operation::Trash(path) => write!(f, "trash {}", shell_quote(&path.to_string_lossy())),
+        }
+    }
+}
+
+/// Quote an argument for display if it would otherwise be ambiguous
+pub fn shell_quote(s: &str) -> String {
+    let plain = !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_alphanumeric() || "/._-+:,@%=".contains(c));
+    if plain {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', "'\\''"))
+    }
+}
+
+/// Applies a chain of actions to each match
+pub struct Executor {
+    actions: Vec<Action>,
+    dry_run: bool,
+    journal: Option<Journal>,
+    /// Ask on stderr before each command or deletion (`--ok`)
+    ask: bool,
+    batches: Option<Batches>,
+}
+
+impl Executor {
+    /// In dry-run mode mutating operations are printed to stdout instead of performed
+    pub fn new(actions: Vec<Action>, dry_run: bool) -> Self {
+        let batches = actions.iter().find_map(|action| match action {
+            Action::ExecBatch(command) => Some(Batches::new(command.clone())),
+            _ => None,
+        });
+        Executor {
+            actions,
+            dry_run,
+            journal: None,
+            ask: false,
+            batches,
+        }
+    }
+
+    /// Run up to `jobs` batched commands at once (1 by default)
+    pub fn with_exec_jobs(mut self, jobs: usize) -> Self {
+        if let Some(batches) = &mut self.batches {
+            batches.jobs = jobs.max(1);
+        }
+        self
+    }
+
+    /// Ask for a y/n answer on stdin before running each command or deleting
+    /// each entry
+    pub fn with_confirmation(mut self) -> Self {
+        self.ask = true;
+        self
+    }
+
+    /// Record every change made into `journal` so it can be undone later
+    pub fn with_journal(mut self, journal: Journal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    pub fn journal(&self) -> Option<&Journal> {
+        self.journal.as_ref()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Whether any of the actions changes the filesystem
+    pub fn is_destructive(&self) -> bool {
+        self.actions.iter().any(Action::is_mutating)
+    }
+
+    /// Plan every action for `path` without performing anything. Batched
+    /// commands are left out, since they only run once enough paths are
+    /// collected.
+    pub fn plan(&self, path: &Path) -> Vec<Operation> {
+        let mut path = path.to_path_buf();
+        let mut operations = Vec::with_capacity(self.actions.len());
+        for action in &self.actions {
+            if let Action::ExecBatch(_) = action {
+                continue;
+            }
+            let operation = action.plan(&path);
+            // Later actions see the path where an earlier one moved the entry
+            if let Operation::Move { to, .. } = &operation {
+                path = to.clone();
+            }
+            operations.push(operation);
+        }
+        operations
+    }
+
+    /// Run the actions in order, stopping at the first failure or at the
+    /// first operation the user declines. A batched command gets the path
+    /// where the other actions left the entry.
+    pub fn apply(&self, path: &Path) -> io::Result<()> {
+        let mut final_path = path.to_path_buf();
+        for operation in self.plan(path) {
+            if self.dry_run && operation.is_mutating() {
+                println!("would {}", operation);
+            } else if self.ask && operation.needs_confirmation() && !confirm(&operation)? {
+                return Ok(());
+            } else if let (Some(journal), true) = (&self.journal, operation.is_mutating()) {
+                let entry = JournalEntry::before(&operation, path);
+                operation.perform()?;
+                journal.record(&entry)?;
+            } else {
+                operation.perform()?;
+            }
+            if let Operation::Move { to, .. } = operation {
+                final_path = to;
+            }
+        }
+        if let Some(batches) = &self.batches {
+            if let Some(operation) = batches.push(final_path) {
+                self.run_batch(batches, operation)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the batched command on the paths still waiting for it and wait
+    /// for every batch to finish. Returns how many batches failed, which
+    /// have been reported on stderr.
+    pub fn finish(&self) -> io::Result<usize> {
+        let batches = match &self.batches {
+            Some(batches) => batches,
+            None => return Ok(0),
+        };
+        if let Some(operation) = batches.take() {
+            self.run_batch(batches, operation)?;
+        }
+        Ok(batches.wait())
+    }
+
+    fn run_batch(&self, batches: &Batches, operation: Operation) -> io::Result<()> {
+        if self.dry_run && operation.is_mutating() {
+            println!("would {}", operation);
+        } else if !self.ask || confirm(&operation)? {
+            batches.run(operation);
+        }
+        Ok(())
+    }
+}
+
+/// Upper limit on the size of the paths passed to one batched command, well
+/// below the command line limits of common platforms
+const MAX_BATCH_BYTES: usize = 128 * 1024;
+
+/// Paths collected for a batched `--exec`, and the threads running it
+struct Batches {
+    command: Vec<String>,
+    jobs: usize,
+    pending: Mutex<(Vec<PathBuf>, usize)>,
+    /// Hands batches to the runner threads, started with the first batch
+    queue: Mutex<Option<Sender<Operation>>>,
+    runners: Mutex<Vec<JoinHandle<usize>>>,
+}
+
+impl Batches {
+    fn new(command: Vec<String>) -> Self {
+        Batches {
+            command,
+            jobs: 1,
+            pending: Mutex::new((Vec::new(), 0)),
+            queue: Mutex::new(None),
+            runners: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Add `path` to the next batch, returning the batch once it is full
+    fn push(&self, path: PathBuf) -> Option<Operation> {
+        let mut pending = self.pending.lock();
+        let (paths, bytes) = &mut *pending;
+        *bytes += path.as_os_str().len() + 1;
+        paths.push(path);
+        if *bytes < MAX_BATCH_BYTES {
+            return None;
+        }
+        *bytes = 0;
+        Some(batch_operation(&self.command, &std::mem::take(paths)))
+    }
+
+    /// The batch of whatever paths are waiting, if any
+    fn take(&self) -> Option<Operation> {
+        let mut pending = self.pending.lock();
+        let (paths, bytes) = &mut *pending;
+        *bytes = 0;
+        if paths.is_empty() {
+            return None;
+        }
+        Some(batch_operation(&self.command, &std::mem::take(paths)))
+    }
+
+    /// Queue `operation` for the next free runner thread
+    fn run(&self, operation: Operation) {
+        let mut queue = self.queue.lock();
+        let sender = queue.get_or_insert_with(|| {
+            // Matches wait while every runner is busy
+            let (sender, receiver) = bounded::<Operation>(0);
+            let mut runners = self.runners.lock();
+            for _ in 0..self.jobs {
+                let receiver = receiver.clone();
+                runners.push(thread::spawn(move || {
+                    let mut failures = 0;
+                    for operation in receiver {
+                        if let Err(e) = operation.perform() {
+                            eprintln!("Failed to run {}: {}", operation, e);
+                            failures += 1;
+                        }
+                    }
+                    failures
+                }));
+            }
+            sender
+        });
+        // Runners only stop once the queue is closed
+        let _ = sender.send(operation);
+    }
+
+    /// Close the queue and wait for the runners, returning the number of
+    /// failed batches
+    fn wait(&self) -> usize {
+        self.queue.lock().take();
+        self.runners
+            .lock()
+            .drain(..)
+            .map(|runner| runner.join().unwrap_or(1))
+            .sum()
+    }
+}
+
+/// Ask whether to go ahead with `operation`; anything but yes, including the
+/// end of stdin, declines
+fn confirm(operation: &Operation) -> io::Result<bool> {
+    // Earlier results must not end up after the prompt
+    io::stdout().flush()?;
+    eprint!("{}? [y/N] ", operation);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Number and total size of the entries a batch of actions would touch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Impact {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+impl Impact {
+    pub fn measure<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> Self {
+        let mut impact = Impact::default();
+        for path in paths {
+            impact.entries += 1;
+            if let Ok(metadata) = fs::symlink_metadata(path) {
+                if metadata.is_file() {
+                    impact.bytes += metadata.len();
+                }
+            }
+        }
+        impact
+    }
+}
+
+/// Limits above which destructive actions need explicit confirmation
+#[derive(Debug, Clone, Copy)]
+pub struct SafetyLimits {
+    pub max_entries: usize,
+    pub max_bytes: u64,
+}
+
+impl SafetyLimits {
+    pub fn allows(&self, impact: &Impact) -> bool {
+        impact.entries <= self.max_entries && impact.bytes <= self.max_bytes
+    }
+}
@@ -0,0 +1,149 @@
+//! Resolution of macOS Finder alias files for `--resolve-aliases`.
+//!
+//! Finder aliases are regular files holding bookmark data rather than symlinks,
+//! so the filesystem never follows them. On macOS they are resolved through
+//! CoreFoundation; on other platforms nothing is ever resolved.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Alias files written by Finder start with this bookmark header
+const BOOKMARK_MAGIC: &[u8; 16] = b"book\0\0\0\0mark\0\0\0\0";
+
+/// Cheap check of the file header, done before asking the OS to resolve it
+pub fn is_alias_file(path: &Path) -> bool {
+    let mut header = [0; 16];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|()| &header == BOOKMARK_MAGIC)
+}
+
+/// The target of a Finder alias, if `path` is one and it can be resolved
+/// without user interaction or mounting volumes
+#[cfg(target_os = "macos")]
+pub fn resolve(path: &Path) -> Option<PathBuf> {
+    use core_foundation::base::{kCFAllocatorDefault, TCFType};
+    use core_foundation::data::CFData;
+    use core_foundation::url::CFURL;
+    use core_foundation_sys::url::{
+        kCFURLBookmarkResolutionWithoutMountingMask, kCFURLBookmarkResolutionWithoutUIMask,
+        CFURLCreateBookmarkDataFromFile, CFURLCreateByResolvingBookmarkData,
+    };
+    use std::ptr;
+
+    if !is_alias_file(path) {
+        return None;
+    }
+    let url = CFURL::from_path(path, false)?;
+
+    // SAFETY: every pointer passed is either valid for the duration of the call
+    // or null where CoreFoundation allows it, and returned objects follow the
+    // create rule, so wrapping them transfers ownership for release on drop.
+    unsafe {
+        let data = CFURLCreateBookmarkDataFromFile(
+            kCFAllocatorDefault,
+            url.as_concrete_TypeRef(),
+            ptr::null_mut(),
+        );
+        if data.is_null() {
+            return None;
+        }
+        let data = CFData::wrap_under_create_rule(data);
+
+        let mut is_stale = 0;
+        let resolved = CFURLCreateByResolvingBookmarkData(
+            kCFAllocatorDefault,
+            data.as_concrete_TypeRef(),
+            kCFURLBookmarkResolutionWithoutUIMask | kCFURLBookmarkResolutionWithoutMountingMask,
+            ptr::null(),
+            ptr::null(),
+            &mut is_stale,
+            ptr::null_mut(),
+        );
+        if resolved.is_null() {
+            return None;
+        }
+        CFURL::wrap_under_create_rule(resolved).to_path()
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn resolve(_path: &Path) -> Option<PathBuf> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_is_alias_file() {
+        let dir = TempDir::new().unwrap();
+        let alias = dir.path().join("alias");
+        let mut bookmark = BOOKMARK_MAGIC.to_vec();
+        bookmark.extend_from_slice(&[0; 48]);
+        fs::write(&alias, &bookmark).unwrap();
+        assert!(is_alias_file(&alias));
+
+        let plain = dir.path().join("plain.txt");
+        fs::write(&plain, "book mark, but not a bookmark").unwrap();
+        assert!(!is_alias_file(&plain));
+        // Too short to hold the header
+        fs::write(&plain, &BOOKMARK_MAGIC[..8]).unwrap();
+        assert!(!is_alias_file(&plain));
+        assert!(!is_alias_file(dir.path()));
+        assert!(!is_alias_file(&dir.path().join("missing")));
+
+        #[cfg(not(target_os = "macos"))]
+        assert_eq!(resolve(&alias), None);
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_resolve_alias() {
+        use core_foundation::base::{kCFAllocatorDefault, TCFType};
+        use core_foundation::data::CFData;
+        use core_foundation::url::CFURL;
+        use core_foundation_sys::url::{
+            kCFURLBookmarkCreationSuitableForBookmarkFile, CFURLCreateBookmarkData,
+            CFURLWriteBookmarkDataToFile,
+        };
+        use std::ptr;
+
+        let dir = TempDir::new().unwrap();
+        // Aliases resolve to canonical paths, and the temp dir is under a symlink
+        let target = dir.path().canonicalize().unwrap().join("target");
+        fs::create_dir(&target).unwrap();
+        let alias = dir.path().join("alias");
+
+        // What Finder does when making an alias
+        let target_url = CFURL::from_path(&target, true).unwrap();
+        let alias_url = CFURL::from_path(&alias, false).unwrap();
+        unsafe {
+            let data = CFURLCreateBookmarkData(
+                kCFAllocatorDefault,
+                target_url.as_concrete_TypeRef(),
+                kCFURLBookmarkCreationSuitableForBookmarkFile,
+                ptr::null(),
+                ptr::null(),
+                ptr::null_mut(),
+            );
+            assert!(!data.is_null());
+            let data = CFData::wrap_under_create_rule(data);
+            let written = CFURLWriteBookmarkDataToFile(
+                data.as_concrete_TypeRef(),
+                alias_url.as_concrete_TypeRef(),
+                0,
+                ptr::null_mut(),
+            );
+            assert!(written != 0);
+        }
+        assert!(is_alias_file(&alias));
+        assert_eq!(resolve(&alias), Some(target.clone()));
+        // Anything else is left alone
+        assert_eq!(resolve(&target), None);
+    }
+}
@@ -0,0 +1,72 @@
+//! Listing the members of zip and tar archives for `--search-archives`.
+//!
+//! Only the zip central directory and tar headers are read; nothing is extracted.
+
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+/// Separates the archive path from the member path in reported results
+pub const MEMBER_SEPARATOR: &str = "!/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+fn archive_kind(path: &Path) -> Option<ArchiveKind> {
+    let name = path.file_name()?.to_str()?.to_lowercase();
+    if name.ends_with(".zip") {
+        Some(ArchiveKind::Zip)
+    } else if name.ends_with(".tar") {
+        Some(ArchiveKind::Tar)
+    } else if name.ends_with(".tgz") || name.ends_with(".tar.gz") {
+        Some(ArchiveKind::TarGz)
+    } else {
+        None
+    }
+}
+
+/// Whether `path` looks like an archive whose members can be listed
+pub fn is_archive(path: &Path) -> bool {
+    archive_kind(path).is_some()
+}
+
+/// Paths of the regular files inside an archive (directories are left out)
+pub fn member_paths(path: &Path) -> io::Result<Vec<String>> {
+    let kind = archive_kind(path)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Not an archive"))?;
+    let file = File::open(path)?;
+    match kind {
+        ArchiveKind::Zip => {
+            let archive = zip::ZipArchive::new(BufReader::new(file)).map_err(io::Error::other)?;
+            let mut members = Vec::with_capacity(archive.len());
+            for name in archive.file_names() {
+                let name = name.map_err(io::Error::other)?;
+                if !name.ends_with('/') {
+                    members.push(name.into_owned());
+                }
+            }
+            Ok(members)
+        }
+        // Seeking skips over member contents instead of reading them
+        ArchiveKind::Tar => tar_members(tar::Archive::new(file).entries_with_seek()?),
+        ArchiveKind::TarGz => {
+            tar_members(tar::Archive::new(GzDecoder::new(BufReader::new(file))).entries()?)
+        }
+    }
+}
+
+fn tar_members<R: io::Read>(entries: tar::Entries<R>) -> io::Result<Vec<String>> {
+    let mut members = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.header().entry_type().is_file() {
+            members.push(entry.path()?.to_string_lossy().into_owned());
+        }
+    }
+    Ok(members)
+}
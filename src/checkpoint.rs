@@ -0,0 +1,87 @@
+//! Saved progress of a search, used by `--checkpoint` and `--resume`.
+//!
+//! A checkpoint holds the directories that were still queued or being scanned,
+//! every result already handed to the caller, and results that had been found
+//! but not yet handed out. Resuming scans the saved directories again and skips
+//! results that were already emitted, so nothing is lost or reported twice.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Bumped whenever the file format changes incompatibly
+const CHECKPOINT_VERSION: u32 = 1;
+
+/// A directory still to be scanned
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingDir {
+    pub path: PathBuf,
+    pub depth: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub version: u32,
+    /// Search root and pattern, so a checkpoint is not resumed by a different search
+    pub root: PathBuf,
+    pub pattern: String,
+    pub pending: Vec<PendingDir>,
+    /// Results already handed to the caller
+    pub emitted: Vec<PathBuf>,
+    /// Results found but not yet handed to the caller
+    pub queued: Vec<PathBuf>,
+}
+
+impl Checkpoint {
+    pub fn new(root: &Path, pattern: &str) -> Self {
+        Checkpoint {
+            version: CHECKPOINT_VERSION,
+            root: root.to_path_buf(),
+            pattern: pattern.to_string(),
+            ..Checkpoint::default()
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let checkpoint: Checkpoint =
+            serde_json::from_reader(BufReader::new(file)).map_err(|e| e.to_string())?;
+        if checkpoint.version != CHECKPOINT_VERSION {
+            return Err(format!(
+                "Unsupported checkpoint version {}",
+                checkpoint.version
+            ));
+        }
+        Ok(checkpoint)
+    }
+
+    /// Write the checkpoint atomically, so an interruption while saving never
+    /// leaves a truncated file behind
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        serde_json::to_writer(&mut writer, self).map_err(io::Error::other)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        drop(writer);
+        fs::rename(&tmp, path)
+    }
+
+    /// Check that this checkpoint was written by a search for `pattern` under `root`
+    pub fn check_compatible(&self, root: &Path, pattern: &str) -> Result<(), String> {
+        if self.root != root || self.pattern != pattern {
+            return Err(format!(
+                "Checkpoint is for pattern '{}' in {}, not '{}' in {}",
+                self.pattern,
+                self.root.display(),
+                pattern,
+                root.display()
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+//! Cleanup chores built on top of a search: `--delete-empty-dirs`.
+
+use std::collections::{BinaryHeap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Order in which to remove the empty directories `dirs`, deepest first, along
+/// with every directory below `root` that would be left empty by those
+/// removals. `root` itself is never included.
+///
+/// Nothing is removed here: a directory counts as empty when each of its
+/// entries is already on the list, so the result is the same for a dry run.
+pub fn empty_dir_removal_order(dirs: Vec<PathBuf>, root: &Path) -> Vec<PathBuf> {
+    let mut pending: BinaryHeap<(usize, PathBuf)> = dirs
+        .into_iter()
+        .map(|dir| (dir.components().count(), dir))
+        .collect();
+    let mut checked = HashSet::new();
+    let mut removed = HashSet::new();
+    let mut order = Vec::new();
+
+    // Children are always deeper than their parent, so by the time a parent
+    // comes up every one of its removable children has been handled
+    while let Some((depth, dir)) = pending.pop() {
+        if dir.as_path() == root || !checked.insert(dir.clone()) {
+            continue;
+        }
+        if !is_empty_after(&dir, &removed) {
+            continue;
+        }
+        if let Some(parent) = dir.parent() {
+            if parent.starts_with(root) && parent != root {
+                pending.push((depth - 1, parent.to_path_buf()));
+            }
+        }
+        removed.insert(dir.clone());
+        order.push(dir);
+    }
+    order
+}
+
+/// Whether `dir` is a directory holding nothing but entries in `removed`
+fn is_empty_after(dir: &Path, removed: &HashSet<PathBuf>) -> bool {
+    if !fs::symlink_metadata(dir).is_ok_and(|metadata| metadata.is_dir()) {
+        return false;
+    }
+    match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .map(|entry| entry.map(|entry| dir.join(entry.file_name())))
+            .all(|path| path.is_ok_and(|path| removed.contains(&path))),
+        Err(_) => false,
+    }
+}
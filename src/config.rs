@@ -0,0 +1,106 @@
+//! The rfind config file, `~/.rfind/config.toml` (or the file named by
+//! `RFIND_CONFIG`, e.g. one shared by a team).
+//!
+//! It holds named option profiles for `--profile`, and can replace the
+//! directory names pruned by `--no-dev-clutter` and the extension groups of
+//! `--preset` (or add new groups):
+//!
+//! ```toml
+//! dev_clutter = ["node_modules", "target", "vendor"]
+//!
+//! [profiles]
+//! media = ["--type", "f", "--size", "+10M", "--where", 'ext == "mp4" || ext == "mkv"']
+//!
+//! [presets]
+//! images = ["jpg", "png", "cr3"]
+//! notes = ["md", "org"]
+//! ```
+
+use crate::presets;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming a config file to use instead of the default one
+pub const CONFIG_ENV: &str = "RFIND_CONFIG";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Argument lists by profile name
+    pub profiles: BTreeMap<String, Vec<String>>,
+    /// Directory names to prune with `--no-dev-clutter`, instead of
+    /// [`presets::DEV_CLUTTER`]
+    pub dev_clutter: Option<Vec<String>>,
+    /// Extension groups for `--preset`, replacing built-in groups of the same name
+    pub presets: BTreeMap<String, Vec<String>>,
+}
+
+/// Location of the config file
+pub fn config_file() -> io::Result<PathBuf> {
+    if let Some(path) = std::env::var_os(CONFIG_ENV).filter(|path| !path.is_empty()) {
+        return Ok(PathBuf::from(path));
+    }
+    directories_next::BaseDirs::new()
+        .map(|dirs| dirs.home_dir().join(".rfind").join("config.toml"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No home directory"))
+}
+
+impl Config {
+    /// Read a config file; a missing file is an empty config
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents)
+                .map_err(|e| format!("Invalid config file {}: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Directory names pruned by `--no-dev-clutter`
+    pub fn dev_clutter(&self) -> Vec<String> {
+        match &self.dev_clutter {
+            Some(dirs) => dirs.clone(),
+            None => presets::DEV_CLUTTER.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+
+    /// The extensions of the `--preset` group `name`
+    pub fn preset_extensions(&self, name: &str) -> Result<Vec<String>, String> {
+        if let Some(extensions) = self.presets.get(name) {
+            return Ok(extensions.clone());
+        }
+        match presets::extension_group(name) {
+            Some(extensions) => Ok(extensions.iter().map(|e| e.to_string()).collect()),
+            None => {
+                let mut names: Vec<&str> = presets::EXTENSION_GROUPS
+                    .iter()
+                    .map(|(group, _)| *group)
+                    .chain(std::iter::once(presets::SECURITY))
+                    .chain(self.presets.keys().map(String::as_str))
+                    .collect();
+                names.sort_unstable();
+                names.dedup();
+                Err(format!(
+                    "No preset named '{}'. Available: {}",
+                    name,
+                    names.join(", ")
+                ))
+            }
+        }
+    }
+
+    /// The arguments of each named profile, in order
+    pub fn profile_args(&self, names: &[String]) -> Result<Vec<String>, String> {
+        let mut args = Vec::new();
+        for name in names {
+            match self.profiles.get(name) {
+                Some(profile) => args.extend_from_slice(profile),
+                None => return Err(format!("No profile named '{}'", name)),
+            }
+        }
+        Ok(args)
+    }
+}
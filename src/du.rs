@@ -0,0 +1,111 @@
+//! Cumulative directory sizes for `rfind du`.
+//!
+//! The parallel walker hands every entry to [`DirSizes::add`], which only
+//! keeps a running total for each directory down to the requested depth.
+//! Totals are rolled up into their ancestors once the walk is done, so the
+//! walker threads never contend over more than one counter per entry.
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+#[cfg(unix)]
+use std::collections::HashSet;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+
+/// Apparent sizes of the files below each directory of a tree. Directories
+/// themselves add nothing, and files with several hard links are counted
+/// once.
+#[derive(Debug)]
+pub struct DirSizes {
+    root: PathBuf,
+    depth: usize,
+    /// Sizes of the files directly below each directory, relative to the
+    /// root; anything deeper than `depth` counts towards its ancestor there
+    totals: Mutex<HashMap<PathBuf, u64>>,
+    /// Device and inode of the hard-linked files counted so far
+    #[cfg(unix)]
+    linked: Mutex<HashSet<(u64, u64)>>,
+}
+
+impl DirSizes {
+    /// Sizes of the directories below `root`, down to `depth` levels (all of
+    /// them with `None`)
+    pub fn new(root: impl Into<PathBuf>, depth: Option<usize>) -> Self {
+        DirSizes {
+            root: root.into(),
+            depth: depth.unwrap_or(usize::MAX),
+            totals: Mutex::new(HashMap::new()),
+            #[cfg(unix)]
+            linked: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Count the entry at `path`, which must be below the root
+    pub fn add(&self, path: &Path, metadata: &Metadata) {
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(relative) => relative,
+            Err(_) => return,
+        };
+        if metadata.is_dir() {
+            // Listed even if nothing below it has a size
+            if relative.components().count() <= self.depth {
+                self.totals
+                    .lock()
+                    .entry(relative.to_path_buf())
+                    .or_default();
+            }
+            return;
+        }
+        if !self.first_link(metadata) {
+            return;
+        }
+        let dir: PathBuf = relative
+            .parent()
+            .unwrap_or(relative)
+            .components()
+            .take(self.depth)
+            .collect();
+        *self.totals.lock().entry(dir).or_default() += metadata.len();
+    }
+
+    /// Whether this is the first of the links to a file to be counted
+    #[cfg(unix)]
+    fn first_link(&self, metadata: &Metadata) -> bool {
+        use std::os::unix::fs::MetadataExt;
+        metadata.nlink() <= 1 || self.linked.lock().insert((metadata.dev(), metadata.ino()))
+    }
+
+    #[cfg(not(unix))]
+    fn first_link(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    /// Every directory with the total size of everything below it, largest
+    /// first, starting with the root itself
+    pub fn rows(&self) -> Vec<(PathBuf, u64)> {
+        let mut cumulative: HashMap<PathBuf, u64> = HashMap::new();
+        cumulative.insert(PathBuf::new(), 0);
+        for (dir, bytes) in self.totals.lock().iter() {
+            let mut ancestor = PathBuf::new();
+            *cumulative.entry(ancestor.clone()).or_default() += bytes;
+            for component in dir.components() {
+                ancestor.push(component);
+                *cumulative.entry(ancestor.clone()).or_default() += bytes;
+            }
+        }
+
+        let mut rows: Vec<(PathBuf, u64)> = cumulative
+            .into_iter()
+            .map(|(dir, bytes)| {
+                // Joining an empty path would add a trailing separator
+                if dir.as_os_str().is_empty() {
+                    (self.root.clone(), bytes)
+                } else {
+                    (self.root.join(dir), bytes)
+                }
+            })
+            .collect();
+        rows.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        rows
+    }
+}
@@ -0,0 +1,204 @@
+//! C interface to the search engine, enabled by the `ffi` feature, so that
+//! file managers and other non-Rust tools can embed rfind's parallel search.
+//! `cargo build --release --features ffi` builds the shared library, and
+//! `include/rfind.h` is generated from this file with cbindgen (see
+//! `cbindgen.toml`).
+//!
+//! Matches are passed to a callback on the calling thread while the scanner
+//! threads keep walking, and a search can be stopped from the callback or
+//! from another thread through an [`RfindCancel`] handle.
+
+use crate::scanner::{CancelToken, Search, SearchOptions, SymlinkMode};
+use parking_lot::Mutex;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+/// The search ran to completion
+pub const RFIND_OK: i32 = 0;
+/// The search was stopped by the callback or a cancel handle
+pub const RFIND_CANCELLED: i32 = 1;
+/// A required argument was null or not valid UTF-8
+pub const RFIND_ERR_INVALID_ARGUMENT: i32 = -1;
+/// The pattern is not a valid glob
+pub const RFIND_ERR_INVALID_PATTERN: i32 = -2;
+/// The root is not a directory that can be read
+pub const RFIND_ERR_ROOT: i32 = -3;
+/// The search failed unexpectedly
+pub const RFIND_ERR_INTERNAL: i32 = -4;
+
+/// What to search for, mirroring the command line options of the same names
+#[repr(C)]
+pub struct RfindOptions {
+    /// Glob or substring matched against entry names (NUL-terminated UTF-8)
+    pub pattern: *const c_char,
+    /// Directory to search (NUL-terminated UTF-8)
+    pub root: *const c_char,
+    /// Maximum depth below the root, or 0 for the default
+    pub max_depth: u32,
+    /// Number of scanner threads, or 0 for one per CPU core
+    pub threads: u32,
+    /// Nonzero to follow all symlinks, like `-L`
+    pub follow_symlinks: i32,
+}
+
+/// Receives each match as a NUL-terminated path, which is only valid for the
+/// duration of the call. Returning nonzero stops the search.
+pub type RfindCallback = extern "C" fn(path: *const c_char, user_data: *mut c_void) -> i32;
+
+/// Stops running searches from any thread, see [`rfind_cancel_new`]
+#[derive(Default)]
+pub struct RfindCancel(Mutex<CancelState>);
+
+#[derive(Default)]
+struct CancelState {
+    cancelled: bool,
+    next_id: u64,
+    /// The searches using the handle, by an ID to remove them when they end
+    running: Vec<(u64, CancelToken)>,
+}
+
+impl RfindCancel {
+    fn cancel(&self) {
+        let mut state = self.0.lock();
+        state.cancelled = true;
+        for (_, token) in &state.running {
+            token.cancel();
+        }
+    }
+
+    /// Tie a search to the handle, cancelling it right away if the handle
+    /// was triggered before the search started. Returns the ID to
+    /// [`RfindCancel::detach`] it with.
+    fn attach(&self, token: CancelToken) -> u64 {
+        let mut state = self.0.lock();
+        if state.cancelled {
+            token.cancel();
+        }
+        let id = state.next_id;
+        state.next_id += 1;
+        state.running.push((id, token));
+        id
+    }
+
+    fn detach(&self, id: u64) {
+        self.0.lock().running.retain(|(running, _)| *running != id);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.0.lock().cancelled
+    }
+}
+
+/// Create a handle for cancelling a search, to be freed with
+/// [`rfind_cancel_free`]. A handle can be shared by several searches.
+#[no_mangle]
+pub extern "C" fn rfind_cancel_new() -> *mut RfindCancel {
+    Box::into_raw(Box::default())
+}
+
+/// Stop the searches using `cancel`, including ones not yet started
+///
+/// # Safety
+///
+/// `cancel` must be null or a handle from [`rfind_cancel_new`] that has not
+/// been freed.
+#[no_mangle]
+pub unsafe extern "C" fn rfind_cancel_trigger(cancel: *const RfindCancel) {
+    if let Some(cancel) = cancel.as_ref() {
+        cancel.cancel();
+    }
+}
+
+/// Free a handle from [`rfind_cancel_new`]
+///
+/// # Safety
+///
+/// `cancel` must be null or a handle from [`rfind_cancel_new`] that is not
+/// freed yet and not used by a running search.
+#[no_mangle]
+pub unsafe extern "C" fn rfind_cancel_free(cancel: *mut RfindCancel) {
+    if !cancel.is_null() {
+        drop(Box::from_raw(cancel));
+    }
+}
+
+/// Search below `options->root`, calling `callback` with each match on the
+/// calling thread. Returns [`RFIND_OK`] once the whole tree has been
+/// walked, [`RFIND_CANCELLED`] if the callback or `cancel` stopped the
+/// search, or a negative error code.
+///
+/// # Safety
+///
+/// `options` must point to valid options whose strings are NUL-terminated,
+/// and `cancel` must be null or a live handle from [`rfind_cancel_new`].
+#[no_mangle]
+pub unsafe extern "C" fn rfind_search(
+    options: *const RfindOptions,
+    callback: Option<RfindCallback>,
+    user_data: *mut c_void,
+    cancel: *const RfindCancel,
+) -> i32 {
+    let (options, callback) = match (options.as_ref(), callback) {
+        (Some(options), Some(callback)) => (options, callback),
+        _ => return RFIND_ERR_INVALID_ARGUMENT,
+    };
+    let (pattern, root) = match (c_str(options.pattern), c_str(options.root)) {
+        (Some(pattern), Some(root)) => (pattern, root),
+        _ => return RFIND_ERR_INVALID_ARGUMENT,
+    };
+    if !Path::new(root).is_dir() {
+        return RFIND_ERR_ROOT;
+    }
+
+    let mut search_options = SearchOptions::new(pattern, root);
+    if options.max_depth > 0 {
+        search_options.max_depth = options.max_depth as usize;
+    }
+    if options.threads > 0 {
+        search_options.threads = options.threads as usize;
+    }
+    if options.follow_symlinks != 0 {
+        search_options.symlink_mode = SymlinkMode::Always;
+    }
+    let cancel = cancel.as_ref();
+
+    // Unwinding into C is undefined behavior
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut search = Search::start(search_options).ok()?;
+        let token = search.cancel_token();
+        let attached = cancel.map(|cancel| cancel.attach(token.clone()));
+        let mut stopped = false;
+        for path in search.by_ref() {
+            let path = match CString::new(path.into_os_string().as_encoded_bytes()) {
+                Ok(path) => path,
+                Err(_) => continue,
+            };
+            if callback(path.as_ptr(), user_data) != 0 {
+                stopped = true;
+                token.cancel();
+                break;
+            }
+        }
+        stopped |= token.is_cancelled() || cancel.is_some_and(RfindCancel::is_cancelled);
+        if let (Some(cancel), Some(id)) = (cancel, attached) {
+            cancel.detach(id);
+        }
+        search.join();
+        Some(stopped)
+    }));
+    match outcome {
+        Ok(Some(false)) => RFIND_OK,
+        Ok(Some(true)) => RFIND_CANCELLED,
+        Ok(None) => RFIND_ERR_INVALID_PATTERN,
+        Err(_) => RFIND_ERR_INTERNAL,
+    }
+}
+
+/// The UTF-8 string at `ptr`, if any
+unsafe fn c_str<'a>(ptr: *const c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        return None;
+    }
+    CStr::from_ptr(ptr).to_str().ok()
+}
@@ -0,0 +1,190 @@
+//! Fields selectable with `--fields` for the structured output modes
+//! (`--json` and `--csv`).
+//!
+//! Only what the selected fields need is looked up: the path-derived fields
+//! cost nothing, and the entry is only stat'ed if a field needs its metadata.
+//! Sizes and times are rendered like in every other output mode, and stay
+//! JSON numbers when shown as bytes and epoch seconds.
+
+use crate::format::{format_size, format_time, FieldFormat, SizeFormat, TimeFormat};
+use crate::permissions;
+use serde_json::{json, Map, Value};
+use std::fs::{self, Metadata};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A field of a match in structured output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Path,
+    Name,
+    Ext,
+    /// file, dir, symlink or other
+    Type,
+    /// Size, in the `--size-format`
+    Size,
+    /// Modification time, in the `--time-style` or `--time-format`
+    Mtime,
+    /// Name of the owning user (its ID if it has no account)
+    Owner,
+    /// Permission bits in octal, e.g. 0644
+    Perm,
+    /// Where a symlink points
+    Target,
+}
+
+impl Field {
+    pub const ALL: [Field; 9] = [
+        Field::Path,
+        Field::Name,
+        Field::Ext,
+        Field::Type,
+        Field::Size,
+        Field::Mtime,
+        Field::Owner,
+        Field::Perm,
+        Field::Target,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Path => "path",
+            Field::Name => "name",
+            Field::Ext => "ext",
+            Field::Type => "type",
+            Field::Size => "size",
+            Field::Mtime => "mtime",
+            Field::Owner => "owner",
+            Field::Perm => "perm",
+            Field::Target => "target",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        Field::ALL
+            .iter()
+            .copied()
+            .find(|field| field.name() == s.trim())
+            .ok_or_else(|| {
+                let names: Vec<&str> = Field::ALL.iter().map(|field| field.name()).collect();
+                format!("Unknown field '{}'. Use {}", s, names.join(", "))
+            })
+    }
+
+    fn needs_metadata(self) -> bool {
+        matches!(
+            self,
+            Field::Type | Field::Size | Field::Mtime | Field::Owner | Field::Perm
+        )
+    }
+
+    /// The value of this field for the entry at `path`, shown as `shown`.
+    /// Unavailable values are null.
+    fn value(
+        self,
+        path: &Path,
+        shown: &str,
+        metadata: Option<&Metadata>,
+        format: &FieldFormat,
+        now: SystemTime,
+    ) -> Value {
+        let text = |s: Option<&std::ffi::OsStr>| json!(s.map(|s| s.to_string_lossy()));
+        match self {
+            Field::Path => json!(shown),
+            Field::Name => text(path.file_name()),
+            Field::Ext => text(path.extension()),
+            Field::Target => json!(fs::read_link(path)
+                .ok()
+                .map(|target| target.to_string_lossy().into_owned())),
+            Field::Type => json!(metadata.map(|metadata| {
+                let file_type = metadata.file_type();
+                if file_type.is_file() {
+                    "file"
+                } else if file_type.is_dir() {
+                    "dir"
+                } else if file_type.is_symlink() {
+                    "symlink"
+                } else {
+                    "other"
+                }
+            })),
+            Field::Size => match (metadata, format.size) {
+                (Some(metadata), SizeFormat::Bytes) => json!(metadata.len()),
+                (Some(metadata), size_format) => json!(format_size(metadata.len(), size_format)),
+                (None, _) => Value::Null,
+            },
+            Field::Mtime => match (metadata.and_then(|m| m.modified().ok()), &format.time) {
+                (Some(mtime), TimeFormat::Epoch) => json!(mtime
+                    .duration_since(UNIX_EPOCH)
+                    .ok()
+                    .map(|since_epoch| since_epoch.as_secs())),
+                (Some(mtime), time_format) => json!(format_time(mtime, now, time_format)),
+                (None, _) => Value::Null,
+            },
+            Field::Owner => json!(metadata.and_then(permissions::owner)),
+            Field::Perm => json!(metadata.and_then(permissions::octal_mode)),
+        }
+    }
+}
+
+/// The values of `fields` for the entry at `path`, in order
+pub fn values(fields: &[Field], path: &Path, shown: &str, format: &FieldFormat) -> Vec<Value> {
+    let metadata = if fields.iter().any(|field| field.needs_metadata()) {
+        // Archive members have no metadata of their own
+        fs::symlink_metadata(path).ok()
+    } else {
+        None
+    };
+    let now = SystemTime::now();
+    fields
+        .iter()
+        .map(|field| field.value(path, shown, metadata.as_ref(), format, now))
+        .collect()
+}
+
+/// The selected fields as a JSON object
+pub fn json_object(fields: &[Field], path: &Path, shown: &str, format: &FieldFormat) -> Value {
+    let object: Map<String, Value> = fields
+        .iter()
+        .map(|field| field.name().to_string())
+        .zip(values(fields, path, shown, format))
+        .collect();
+    Value::Object(object)
+}
+
+/// The header line of CSV output
+pub fn csv_header(fields: &[Field]) -> String {
+    let names: Vec<String> = fields
+        .iter()
+        .map(|field| field.name().to_string())
+        .collect();
+    csv_line(&names)
+}
+
+/// The selected fields as a CSV line; unavailable values are left empty
+pub fn csv_record(fields: &[Field], path: &Path, shown: &str, format: &FieldFormat) -> String {
+    let cells: Vec<String> = values(fields, path, shown, format)
+        .into_iter()
+        .map(|value| match value {
+            Value::Null => String::new(),
+            Value::String(s) => s,
+            other => other.to_string(),
+        })
+        .collect();
+    csv_line(&cells)
+}
+
+/// Join cells with commas, quoting those that need it (RFC 4180)
+pub fn csv_line(cells: &[String]) -> String {
+    cells
+        .iter()
+        .map(|cell| {
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
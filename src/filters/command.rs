@@ -0,0 +1,68 @@
+use super::{EntryFilter, EntryInfo};
+use crate::actions::{Action, Operation};
+use parking_lot::{Condvar, Mutex};
+use std::process::{Command, Stdio};
+
+/// Keeps entries for which an external command exits successfully
+/// (`--filter-cmd`). `{}` in the command is replaced by the entry's path, which
+/// is appended if no argument contains `{}`.
+///
+/// Scanner threads share a fixed number of slots, so no more than `jobs`
+/// commands run at once however many threads are scanning.
+pub struct CommandFilter {
+    command: Action,
+    jobs: usize,
+    running: Mutex<usize>,
+    slot_freed: Condvar,
+}
+
+impl CommandFilter {
+    pub fn new(command: Vec<String>, jobs: usize) -> Self {
+        CommandFilter {
+            command: Action::Exec(command),
+            jobs: jobs.max(1),
+            running: Mutex::new(0),
+            slot_freed: Condvar::new(),
+        }
+    }
+
+    /// Split a shell-style command line such as `ffprobe -v error {}`
+    pub fn parse(command: &str, jobs: usize) -> Result<Self, String> {
+        let argv = shell_words::split(command).map_err(|e| e.to_string())?;
+        if argv.is_empty() {
+            return Err("Empty command".to_string());
+        }
+        Ok(CommandFilter::new(argv, jobs))
+    }
+
+    fn acquire(&self) {
+        let mut running = self.running.lock();
+        while *running >= self.jobs {
+            self.slot_freed.wait(&mut running);
+        }
+        *running += 1;
+    }
+
+    fn release(&self) {
+        *self.running.lock() -= 1;
+        self.slot_freed.notify_one();
+    }
+}
+
+impl EntryFilter for CommandFilter {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        let argv = match self.command.plan(entry.disk_path) {
+            Operation::Exec(argv) => argv,
+            _ => unreachable!("the command is always an exec action"),
+        };
+        self.acquire();
+        // Commands that cannot be started reject the entry like a failure would
+        let status = Command::new(&argv[0])
+            .args(&argv[1..])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .status();
+        self.release();
+        status.is_ok_and(|status| status.success())
+    }
+}
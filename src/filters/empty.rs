@@ -0,0 +1,19 @@
+use super::{EntryFilter, EntryInfo};
+
+/// Keeps zero-byte regular files and directories without any entries, like
+/// `find -empty`. Symlinks and other special files never match.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EmptyFilter;
+
+impl EntryFilter for EmptyFilter {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        let file_type = entry.metadata.file_type();
+        if file_type.is_file() {
+            entry.metadata.len() == 0
+        } else if file_type.is_dir() {
+            std::fs::read_dir(entry.disk_path).is_ok_and(|mut dir| dir.next().is_none())
+        } else {
+            false
+        }
+    }
+}
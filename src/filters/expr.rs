@@ -0,0 +1,108 @@
+use super::{EntryFilter, EntryInfo, SizeUnit};
+use glob::{MatchOptions, Pattern};
+use rhai::{Engine, Scope, AST};
+use std::time::{Duration, SystemTime};
+
+/// Upper bound on the work a single expression evaluation may do
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// Filters entries with a user supplied expression evaluated by an embedded
+/// Rhai engine, e.g. `size > 10*MB && name.ends_with(".log") && age_days > 30`.
+///
+/// Available variables: `name`, `path`, `ext`, `size`, `mtime`, `now`, `age_secs`,
+/// `age_days`, `is_file`, `is_dir`, `is_symlink`, and the size units of
+/// `--size` as constants: `kB` (also `KB`), `MB` and `GB` are powers of 1000,
+/// `KiB`, `MiB` and `GiB` powers of 1024. `mtime` and `now` are seconds since
+/// the Unix epoch. The function
+/// `like(text, pattern)` does case-insensitive SQL LIKE matching.
+pub struct WhereFilter {
+    engine: Engine,
+    ast: AST,
+}
+
+impl WhereFilter {
+    /// Compile an expression, reporting syntax errors up front
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.register_fn("like", like);
+        let ast = engine.compile_expression(s).map_err(|e| e.to_string())?;
+        Ok(WhereFilter { engine, ast })
+    }
+
+    fn scope_for(entry: &EntryInfo) -> Scope<'static> {
+        let metadata = entry.metadata;
+        let file_type = metadata.file_type();
+        let modified = metadata.modified().unwrap_or(entry.now);
+        let age = entry.now.duration_since(modified).unwrap_or(Duration::ZERO);
+        let epoch_secs = |t: SystemTime| {
+            t.duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs() as i64)
+        };
+
+        let mut scope = Scope::new();
+        for (name, unit) in [
+            ("kB", SizeUnit::SiKilobytes),
+            ("KB", SizeUnit::SiKilobytes),
+            ("MB", SizeUnit::SiMegabytes),
+            ("GB", SizeUnit::SiGigabytes),
+            ("KiB", SizeUnit::Kilobytes),
+            ("MiB", SizeUnit::Megabytes),
+            ("GiB", SizeUnit::Gigabytes),
+        ] {
+            scope.push_constant(name, unit.bytes() as i64);
+        }
+        scope.push_constant(
+            "name",
+            entry
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        );
+        scope.push_constant("path", entry.path.to_string_lossy().into_owned());
+        scope.push_constant(
+            "ext",
+            entry
+                .path
+                .extension()
+                .map(|e| e.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        );
+        scope.push_constant("size", metadata.len() as i64);
+        scope.push_constant("mtime", epoch_secs(modified));
+        scope.push_constant("now", epoch_secs(entry.now));
+        scope.push_constant("age_secs", age.as_secs() as i64);
+        scope.push_constant("age_days", (age.as_secs() / (24 * 60 * 60)) as i64);
+        scope.push_constant("is_file", file_type.is_file());
+        scope.push_constant("is_dir", file_type.is_dir());
+        scope.push_constant("is_symlink", file_type.is_symlink());
+        scope
+    }
+}
+
+/// SQL LIKE: `%` matches any run of characters and `_` a single character
+fn like(text: &str, pattern: &str) -> bool {
+    let glob = Pattern::escape(pattern).replace('%', "*").replace('_', "?");
+    let options = MatchOptions {
+        case_sensitive: false,
+        ..MatchOptions::new()
+    };
+    Pattern::new(&glob).is_ok_and(|p| p.matches_with(text, options))
+}
+
+impl EntryFilter for WhereFilter {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        let mut scope = Self::scope_for(entry);
+        match self
+            .engine
+            .eval_ast_with_scope::<bool>(&mut scope, &self.ast)
+        {
+            Ok(keep) => keep,
+            Err(e) => {
+                log::debug!("--where failed on {:?}: {}", entry.path, e);
+                false
+            }
+        }
+    }
+}
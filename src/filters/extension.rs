@@ -0,0 +1,33 @@
+use super::{EntryFilter, EntryInfo};
+use std::collections::HashSet;
+
+/// Keeps entries whose extension is one of a set, ignoring case, e.g. the
+/// extension groups of `--preset images`
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionFilter {
+    extensions: HashSet<String>,
+}
+
+impl ExtensionFilter {
+    pub fn new<I, S>(extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        ExtensionFilter {
+            extensions: extensions
+                .into_iter()
+                .map(|ext| ext.as_ref().trim_start_matches('.').to_lowercase())
+                .collect(),
+        }
+    }
+}
+
+impl EntryFilter for ExtensionFilter {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        entry.path.extension().is_some_and(|ext| {
+            self.extensions
+                .contains(&ext.to_string_lossy().to_lowercase())
+        })
+    }
+}
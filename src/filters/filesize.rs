@@ -10,22 +10,71 @@ pub enum SizeComparison {
 #[derive(Debug, Clone, Copy)]
 pub enum SizeUnit {
     Bytes,     // c
-    Kilobytes, // k
-    Megabytes, // M
-    Gigabytes, // G
+    Blocks,    // b, 512 bytes as in find -size
+    Kilobytes, // k, KiB
+    Megabytes, // M, MiB
+    Gigabytes, // G, GiB
+    /// Decimal (SI) units, powers of 1000
+    SiKilobytes, // kB
+    SiMegabytes, // MB
+    SiGigabytes, // GB
+}
+
+/// Unit suffixes, checked in order so that "kB" is not read as "k"
+const UNITS: [(&str, SizeUnit); 11] = [
+    ("KiB", SizeUnit::Kilobytes),
+    ("MiB", SizeUnit::Megabytes),
+    ("GiB", SizeUnit::Gigabytes),
+    ("kB", SizeUnit::SiKilobytes),
+    ("MB", SizeUnit::SiMegabytes),
+    ("GB", SizeUnit::SiGigabytes),
+    ("c", SizeUnit::Bytes),
+    ("b", SizeUnit::Blocks),
+    ("k", SizeUnit::Kilobytes),
+    ("M", SizeUnit::Megabytes),
+    ("G", SizeUnit::Gigabytes),
+];
+
+impl SizeUnit {
+    /// The unit written as `suffix`, e.g. `k` or `MB`
+    pub fn from_suffix(suffix: &str) -> Option<SizeUnit> {
+        UNITS
+            .iter()
+            .find(|(name, _)| *name == suffix)
+            .map(|(_, unit)| *unit)
+    }
+
+    /// Number of bytes in one unit
+    pub fn bytes(self) -> u64 {
+        match self {
+            SizeUnit::Bytes => 1,
+            SizeUnit::Blocks => 512,
+            SizeUnit::Kilobytes => 1024,
+            SizeUnit::Megabytes => 1024 * 1024,
+            SizeUnit::Gigabytes => 1024 * 1024 * 1024,
+            SizeUnit::SiKilobytes => 1000,
+            SizeUnit::SiMegabytes => 1000 * 1000,
+            SizeUnit::SiGigabytes => 1000 * 1000 * 1000,
+        }
+    }
 }
 
 /// Holds size-based filter configuration
 #[derive(Debug, Clone)]
 pub struct SizeFilter {
     comparison: SizeComparison,
-    value: u64,
+    value: f64,
     unit: SizeUnit,
+    /// Compare whole units rounded up, like find -size
+    round_up: bool,
 }
 
 impl SizeFilter {
-    /// Parse a size filter string in the format: [+-]N[ckmG]
-    /// Examples: "+1M" (more than 1 MiB), "-500k" (less than 500 KiB), "1G" (about 1 GiB)
+    /// Parse a size filter string in the format: [+-]N[c|b|k|M|G|kB|MB|GB]
+    /// k, M and G are binary (KiB, MiB, GiB); kB, MB and GB are decimal (SI).
+    /// N may have a fractional part.
+    /// Examples: "+1M" (more than 1 MiB), "-500k" (less than 500 KiB), "1G" (about 1 GiB),
+    /// "+1.5GB" (more than 1.5 billion bytes)
     pub fn parse(s: &str) -> Result<Self, String> {
         let (comparison, rest) = match s.chars().next() {
             Some('+') => (SizeComparison::Greater, &s[1..]),
@@ -34,53 +83,66 @@ impl SizeFilter {
             None => return Err("Empty size filter".to_string()),
         };
 
-        let unit = match rest.chars().last() {
-            Some('c') => SizeUnit::Bytes,
-            Some('k') => SizeUnit::Kilobytes,
-            Some('M') => SizeUnit::Megabytes,
-            Some('G') => SizeUnit::Gigabytes,
-            _ => {
-                return Err(
-                    "Invalid size unit. Use c (bytes), k (KB), M (MB), or G (GB)".to_string(),
-                )
-            }
-        };
+        let (value_str, unit) = UNITS
+            .iter()
+            .find_map(|(suffix, unit)| rest.strip_suffix(suffix).map(|value| (value, *unit)))
+            .ok_or_else(|| {
+                "Invalid size unit. Use c (bytes), b (512-byte blocks), k/M/G (KiB/MiB/GiB), or kB/MB/GB (powers of 1000)"
+                    .to_string()
+            })?;
 
-        let value_str = &rest[..rest.len() - 1];
         let value = value_str
-            .parse::<u64>()
-            .map_err(|_| "Invalid number in size filter".to_string())?;
+            .parse::<f64>()
+            .ok()
+            .filter(|value| {
+                value.is_finite() && *value >= 0.0 && value_str.as_bytes()[0].is_ascii_digit()
+            })
+            .ok_or_else(|| "Invalid number in size filter".to_string())?;
 
         Ok(SizeFilter {
             comparison,
             value,
             unit,
+            round_up: false,
+        })
+    }
+
+    /// Parse a size the way find -size does: a number without a unit counts
+    /// 512-byte blocks, and file sizes are rounded up to whole units before
+    /// comparing, so "-1M" only matches empty files
+    pub fn parse_find_compat(s: &str) -> Result<Self, String> {
+        let filter = if s.ends_with(|c: char| c.is_ascii_digit()) {
+            Self::parse(&format!("{}b", s))?
+        } else {
+            Self::parse(s)?
+        };
+        Ok(SizeFilter {
+            round_up: true,
+            ..filter
         })
     }
 
     /// Convert the size filter value to bytes
     pub fn to_bytes(&self) -> u64 {
-        match self.unit {
-            SizeUnit::Bytes => self.value,
-            SizeUnit::Kilobytes => self.value * 1024,
-            SizeUnit::Megabytes => self.value * 1024 * 1024,
-            SizeUnit::Gigabytes => self.value * 1024 * 1024 * 1024,
-        }
+        (self.value * self.unit.bytes() as f64).round() as u64
     }
 
     /// Check if a file's size matches the filter
     pub fn matches(&self, file_size: u64) -> bool {
+        if self.round_up {
+            let units = file_size.div_ceil(self.unit.bytes()) as f64;
+            return match self.comparison {
+                SizeComparison::Exactly => units == self.value,
+                SizeComparison::Lesser => units < self.value,
+                SizeComparison::Greater => units > self.value,
+            };
+        }
         let target_size = self.to_bytes();
 
         match self.comparison {
             SizeComparison::Exactly => {
-                // For exact matches, we'll allow a small tolerance based on the unit
-                let tolerance = match self.unit {
-                    SizeUnit::Bytes => 0,
-                    SizeUnit::Kilobytes => 512,         // ±0.5KB
-                    SizeUnit::Megabytes => 524_288,     // ±0.5MB
-                    SizeUnit::Gigabytes => 536_870_912, // ±0.5GB
-                };
+                // For exact matches, allow half a unit either way (none for bytes)
+                let tolerance = self.unit.bytes() / 2;
 
                 let lower = target_size.saturating_sub(tolerance);
                 let upper = target_size.saturating_add(tolerance);
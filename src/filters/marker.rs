@@ -0,0 +1,36 @@
+use super::{EntryFilter, EntryInfo};
+
+/// Keeps directories that directly contain every one of the given marker
+/// entries, e.g. `Cargo.toml` or `.git`, to find project roots.
+///
+/// A marker may be a file, a directory or a symlink; anything that is not a
+/// directory never matches.
+#[derive(Debug, Clone)]
+pub struct HasFileFilter {
+    markers: Vec<String>,
+}
+
+impl HasFileFilter {
+    pub fn new(markers: Vec<String>) -> Result<Self, String> {
+        if let Some(marker) = markers
+            .iter()
+            .find(|m| m.is_empty() || m.contains('/') || m.contains(std::path::MAIN_SEPARATOR))
+        {
+            return Err(format!(
+                "Invalid marker '{}'. Give a file name, not a path",
+                marker
+            ));
+        }
+        Ok(HasFileFilter { markers })
+    }
+}
+
+impl EntryFilter for HasFileFilter {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        entry.metadata.is_dir()
+            && self
+                .markers
+                .iter()
+                .all(|marker| entry.disk_path.join(marker).symlink_metadata().is_ok())
+    }
+}
@@ -1,7 +1,27 @@
+mod command;
+mod empty;
+mod expr;
+mod extension;
 mod filesize;
 mod filetype;
+mod marker;
+mod newer;
+mod placeholder;
+mod predicate;
+mod symlink;
 mod time;
 
-pub use filesize::SizeFilter;
+pub use command::CommandFilter;
+pub use empty::EmptyFilter;
+pub use expr::WhereFilter;
+pub use extension::ExtensionFilter;
+pub use filesize::{SizeFilter, SizeUnit};
 pub use filetype::TypeFilter;
+pub use marker::HasFileFilter;
+pub use newer::{parse_timestamp, reference_time, NewerFilter};
+pub use placeholder::CloudFilter;
+pub use predicate::{
+    from_fn, AllOf, And, EntryFilter, EntryInfo, FnFilter, Not, Or, TimeField, TimestampFilter,
+};
+pub use symlink::{BrokenSymlinkFilter, LinkTargetFilter};
 pub use time::TimeFilter;
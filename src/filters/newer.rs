@@ -0,0 +1,97 @@
+use super::{EntryFilter, EntryInfo, TimeField};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Keeps entries whose timestamp is later than a reference time, like find's
+/// `-newerXY`
+#[derive(Debug, Clone, Copy)]
+pub struct NewerFilter {
+    pub field: TimeField,
+    pub reference: SystemTime,
+}
+
+impl NewerFilter {
+    pub fn new(field: TimeField, reference: SystemTime) -> Self {
+        NewerFilter { field, reference }
+    }
+
+    /// Build the filter from find's `XY` letters and a reference. X picks the
+    /// entry's timestamp (a, B, c or m); Y picks that of the reference file,
+    /// or is t when the reference is a timestamp itself.
+    /// Examples: ("mm", "build.stamp"), ("cB", "notes.txt"), ("mt", "2024-06-01")
+    pub fn parse(xy: &str, reference: &str) -> Result<Self, String> {
+        let letters: Vec<char> = xy.chars().collect();
+        let (x, y) = match letters[..] {
+            [x, y] => (x, y),
+            _ => return Err(format!("Expected two letters (XY), got '{}'", xy)),
+        };
+        let field = TimeField::from_letter(x)
+            .ok_or_else(|| format!("Invalid time '{}': use a, B, c or m", x))?;
+        let reference = if y == 't' {
+            parse_timestamp(reference)?
+        } else {
+            let reference_field = TimeField::from_letter(y)
+                .ok_or_else(|| format!("Invalid reference time '{}': use a, B, c, m or t", y))?;
+            reference_time(Path::new(reference), reference_field)?
+        };
+        Ok(NewerFilter::new(field, reference))
+    }
+}
+
+/// The `field` timestamp of the file at `path`, following symlinks
+pub fn reference_time(path: &Path, field: TimeField) -> Result<SystemTime, String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    field
+        .read(&metadata)
+        .ok_or_else(|| format!("{}: this timestamp is not available", path.display()))
+}
+
+/// Parse a point in time: `@` followed by seconds since the Unix epoch, an
+/// RFC 3339 timestamp, or a local date with an optional time
+/// Examples: "2024-06-01", "2024-06-01 13:30", "2024-06-01T13:30:00+02:00", "@1717200000"
+pub fn parse_timestamp(s: &str) -> Result<SystemTime, String> {
+    let s = s.trim();
+    if let Some(seconds) = s.strip_prefix('@') {
+        let seconds: u64 = seconds
+            .parse()
+            .map_err(|_| format!("Invalid epoch timestamp '{}'", s))?;
+        return Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds));
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(time.into());
+    }
+    let local = [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+    })
+    .ok_or_else(|| {
+        format!(
+            "Invalid timestamp '{}': use YYYY-MM-DD, YYYY-MM-DD HH:MM[:SS], RFC 3339 or @SECONDS",
+            s
+        )
+    })?;
+    Local
+        .from_local_datetime(&local)
+        .earliest()
+        .map(SystemTime::from)
+        .ok_or_else(|| format!("'{}' does not exist in the local time zone", s))
+}
+
+impl EntryFilter for NewerFilter {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        self.field
+            .read(entry.metadata)
+            .is_some_and(|time| time > self.reference)
+    }
+}
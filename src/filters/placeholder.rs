@@ -0,0 +1,16 @@
+use super::{EntryFilter, EntryInfo};
+use crate::placeholder::is_placeholder_at;
+
+/// Keeps either the entries stored locally or the cloud placeholders whose
+/// contents have not been downloaded (`--only-local` / `--only-cloud`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloudFilter {
+    Local,
+    Cloud,
+}
+
+impl EntryFilter for CloudFilter {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        is_placeholder_at(entry.path, entry.metadata) == (*self == CloudFilter::Cloud)
+    }
+}
@@ -0,0 +1,221 @@
+use super::{SizeFilter, TimeFilter, TypeFilter};
+use std::fs::Metadata;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Information about a single directory entry that filters are evaluated against
+#[derive(Debug, Clone, Copy)]
+pub struct EntryInfo<'a> {
+    /// Path of the entry as it will be reported
+    pub path: &'a Path,
+    /// Where the entry is on disk, to be used by filters that read it. Only
+    /// differs from `path` for iCloud stubs, which are reported under the
+    /// name of the file they stand for.
+    pub disk_path: &'a Path,
+    /// Metadata of the entry itself (symlinks are not followed)
+    pub metadata: &'a Metadata,
+    /// Reference time used by time-based filters
+    pub now: SystemTime,
+}
+
+/// A predicate over directory entries.
+///
+/// Filters can be combined with [`EntryFilter::and`], [`EntryFilter::or`] and
+/// [`EntryFilter::not`] to build arbitrary expressions.
+pub trait EntryFilter: Send + Sync {
+    /// Returns true if the entry should be kept
+    fn matches(&self, entry: &EntryInfo) -> bool;
+
+    /// Keep entries matched by both `self` and `other`
+    fn and<F: EntryFilter>(self, other: F) -> And<Self, F>
+    where
+        Self: Sized,
+    {
+        And(self, other)
+    }
+
+    /// Keep entries matched by either `self` or `other`
+    fn or<F: EntryFilter>(self, other: F) -> Or<Self, F>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
+
+    /// Keep entries not matched by `self`
+    fn not(self) -> Not<Self>
+    where
+        Self: Sized,
+    {
+        Not(self)
+    }
+}
+
+impl<F: EntryFilter + ?Sized> EntryFilter for Box<F> {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        (**self).matches(entry)
+    }
+}
+
+impl<F: EntryFilter + ?Sized> EntryFilter for std::sync::Arc<F> {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        (**self).matches(entry)
+    }
+}
+
+/// Matches when both inner filters match
+#[derive(Debug, Clone)]
+pub struct And<A, B>(pub A, pub B);
+
+impl<A: EntryFilter, B: EntryFilter> EntryFilter for And<A, B> {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        self.0.matches(entry) && self.1.matches(entry)
+    }
+}
+
+/// Matches when either inner filter matches
+#[derive(Debug, Clone)]
+pub struct Or<A, B>(pub A, pub B);
+
+impl<A: EntryFilter, B: EntryFilter> EntryFilter for Or<A, B> {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        self.0.matches(entry) || self.1.matches(entry)
+    }
+}
+
+/// Inverts the inner filter
+#[derive(Debug, Clone)]
+pub struct Not<A>(pub A);
+
+impl<A: EntryFilter> EntryFilter for Not<A> {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        !self.0.matches(entry)
+    }
+}
+
+/// Matches when every filter in the list matches (an empty list matches everything)
+#[derive(Default)]
+pub struct AllOf(pub Vec<Box<dyn EntryFilter>>);
+
+impl AllOf {
+    /// Append a filter to the conjunction
+    pub fn push<F: EntryFilter + 'static>(&mut self, filter: F) {
+        self.0.push(Box::new(filter));
+    }
+}
+
+impl EntryFilter for AllOf {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        self.0.iter().all(|f| f.matches(entry))
+    }
+}
+
+/// Adapter that turns a closure into an [`EntryFilter`]
+pub struct FnFilter<F>(pub F);
+
+impl<F> EntryFilter for FnFilter<F>
+where
+    F: Fn(&EntryInfo) -> bool + Send + Sync,
+{
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        (self.0)(entry)
+    }
+}
+
+/// Create a filter from a closure
+pub fn from_fn<F>(f: F) -> FnFilter<F>
+where
+    F: Fn(&EntryInfo) -> bool + Send + Sync,
+{
+    FnFilter(f)
+}
+
+impl EntryFilter for TypeFilter {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        let file_type = entry.metadata.file_type();
+        match self {
+            TypeFilter::Any => true,
+            TypeFilter::File => file_type.is_file(),
+            TypeFilter::Dir => file_type.is_dir(),
+            TypeFilter::Symlink => file_type.is_symlink(),
+        }
+    }
+}
+
+impl EntryFilter for SizeFilter {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        SizeFilter::matches(self, entry.metadata.len())
+    }
+}
+
+/// Which timestamp of an entry a [`TimestampFilter`] inspects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeField {
+    Modified,
+    Accessed,
+    Changed,
+    /// Birth time, where the platform and filesystem record one
+    Created,
+}
+
+impl TimeField {
+    /// The field named by a letter of find's `-newerXY`: a, B, c or m
+    pub fn from_letter(letter: char) -> Option<Self> {
+        match letter {
+            'a' => Some(TimeField::Accessed),
+            'B' => Some(TimeField::Created),
+            'c' => Some(TimeField::Changed),
+            'm' => Some(TimeField::Modified),
+            _ => None,
+        }
+    }
+
+    /// Read this timestamp from the metadata, if the platform provides it
+    pub fn read(self, metadata: &Metadata) -> Option<SystemTime> {
+        match self {
+            TimeField::Modified => metadata.modified().ok(),
+            TimeField::Accessed => metadata.accessed().ok(),
+            TimeField::Created => metadata.created().ok(),
+            TimeField::Changed => {
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::MetadataExt;
+                    Some(
+                        SystemTime::UNIX_EPOCH
+                            + Duration::new(metadata.ctime() as u64, metadata.ctime_nsec() as u32),
+                    )
+                }
+                #[cfg(not(unix))]
+                {
+                    // Fall back to mtime on non-Unix systems
+                    metadata.modified().ok()
+                }
+            }
+        }
+    }
+
+    /// Read this timestamp from the metadata, falling back to `now` if unavailable
+    pub fn get(self, metadata: &Metadata, now: SystemTime) -> SystemTime {
+        self.read(metadata).unwrap_or(now)
+    }
+}
+
+/// Applies a [`TimeFilter`] to one of the entry's timestamps
+#[derive(Debug, Clone)]
+pub struct TimestampFilter {
+    pub field: TimeField,
+    pub filter: TimeFilter,
+}
+
+impl TimestampFilter {
+    pub fn new(field: TimeField, filter: TimeFilter) -> Self {
+        TimestampFilter { field, filter }
+    }
+}
+
+impl EntryFilter for TimestampFilter {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        let time = self.field.get(entry.metadata, entry.now);
+        self.filter.matches(time, entry.now)
+    }
+}
@@ -0,0 +1,38 @@
+use super::{EntryFilter, EntryInfo};
+use glob::Pattern;
+
+/// Keeps symlinks whose target cannot be reached, because it was removed or
+/// moved, or because the link is part of a loop
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BrokenSymlinkFilter;
+
+impl EntryFilter for BrokenSymlinkFilter {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        entry.metadata.file_type().is_symlink() && std::fs::metadata(entry.disk_path).is_err()
+    }
+}
+
+/// Keeps symlinks whose target, as stored in the link, matches a glob, like
+/// `find -lname`. `*` also matches `/`, so `/old/storage/*` matches links
+/// anywhere below that directory.
+#[derive(Debug, Clone)]
+pub struct LinkTargetFilter {
+    pattern: Pattern,
+}
+
+impl LinkTargetFilter {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        Pattern::new(pattern)
+            .map(|pattern| LinkTargetFilter { pattern })
+            .map_err(|e| format!("'{}': {}", pattern, e))
+    }
+}
+
+impl EntryFilter for LinkTargetFilter {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        // Only links are read, so other entries cost nothing extra
+        entry.metadata.file_type().is_symlink()
+            && std::fs::read_link(entry.disk_path)
+                .is_ok_and(|target| self.pattern.matches(&target.to_string_lossy()))
+    }
+}
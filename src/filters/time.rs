@@ -1,3 +1,4 @@
+use chrono::{DateTime, Local};
 use std::time::{Duration, SystemTime};
 /// Represents a time comparison operation
 #[derive(Debug, Clone, Copy)]
@@ -14,6 +15,9 @@ pub enum TimeUnit {
     Minutes,
     Hours,
     Days,
+    Weeks,
+    Months, // 30 days
+    Years,  // 365 days
 }
 
 /// Holds time-based filter configuration
@@ -22,11 +26,14 @@ pub struct TimeFilter {
     comparison: TimeComparison,
     value: i64,
     unit: TimeUnit,
+    /// Count days as calendar days before today, like `find -daystart`
+    daystart: bool,
 }
 
 impl TimeFilter {
-    /// Parse a time filter string in the format: [+-]N[smhd]
-    /// Examples: "+1h" (more than 1 hour), "-2m" (less than 2 minutes), "3d" (about 3 days back)
+    /// Parse a time filter string in the format: [+-]N[smhdwMy]
+    /// Examples: "+1h" (more than 1 hour), "-2m" (less than 2 minutes), "3d" (about 3 days back),
+    /// "+6M" (more than 6 months)
     pub fn parse(s: &str) -> Result<Self, String> {
         let (comparison, rest) = match s.chars().next() {
             Some('+') => (TimeComparison::Greater, &s[1..]),
@@ -40,7 +47,14 @@ impl TimeFilter {
             Some('m') => TimeUnit::Minutes,
             Some('d') => TimeUnit::Days,
             Some('h') => TimeUnit::Hours,
-            _ => return Err("Invalid time unit. Use 'm' for minutes or 'd' for days".to_string()),
+            Some('w') => TimeUnit::Weeks,
+            Some('M') => TimeUnit::Months,
+            Some('y') => TimeUnit::Years,
+            _ => {
+                return Err(
+                    "Invalid time unit. Use s, m (minutes), h, d, w, M (months) or y".to_string(),
+                )
+            }
         };
 
         let value_str = &rest[..rest.len() - 1];
@@ -52,9 +66,19 @@ impl TimeFilter {
             comparison,
             value,
             unit,
+            daystart: false,
         })
     }
 
+    /// Measure day units from the start of today instead of in 24-hour steps
+    /// back from now, like `find -daystart`: `1d` is then anything modified
+    /// yesterday, `-1d` today and `+0d` before today, whatever the time of day.
+    /// Other units are not affected.
+    pub fn measured_from_daystart(mut self) -> Self {
+        self.daystart = true;
+        self
+    }
+
     /// Convert the time filter value to a Duration
     pub fn to_duration(&self) -> Duration {
         match self.unit {
@@ -62,11 +86,17 @@ impl TimeFilter {
             TimeUnit::Minutes => Duration::from_secs(self.value.unsigned_abs() * 60),
             TimeUnit::Hours => Duration::from_secs(self.value.unsigned_abs() * 60 * 60),
             TimeUnit::Days => Duration::from_secs(self.value.unsigned_abs() * 24 * 60 * 60),
+            TimeUnit::Weeks => Duration::from_secs(self.value.unsigned_abs() * 7 * 24 * 60 * 60),
+            TimeUnit::Months => Duration::from_secs(self.value.unsigned_abs() * 30 * 24 * 60 * 60),
+            TimeUnit::Years => Duration::from_secs(self.value.unsigned_abs() * 365 * 24 * 60 * 60),
         }
     }
 
     /// Check if a file's modification time matches the filter
     pub fn matches(&self, file_time: SystemTime, now: SystemTime) -> bool {
+        if self.daystart && matches!(self.unit, TimeUnit::Days) {
+            return self.matches_calendar_days(file_time, now);
+        }
         let duration = self.to_duration();
         let age = now.duration_since(file_time).unwrap_or(Duration::ZERO);
 
@@ -77,6 +107,9 @@ impl TimeFilter {
                     TimeUnit::Minutes => Duration::from_secs(30), // ±30 seconds
                     TimeUnit::Hours => Duration::from_secs(60 * 30), // ±30 minutes
                     TimeUnit::Days => Duration::from_secs(60 * 60 * 12), // ±12 hours
+                    TimeUnit::Weeks => Duration::from_secs(60 * 60 * 84), // ±3.5 days
+                    TimeUnit::Months => Duration::from_secs(60 * 60 * 24 * 15), // ±15 days
+                    TimeUnit::Years => Duration::from_secs(60 * 60 * 24 * 182), // ±182 days
                 };
                 let lower = duration.saturating_sub(tolerance);
                 let upper = duration.saturating_add(tolerance);
@@ -86,4 +119,16 @@ impl TimeFilter {
             TimeComparison::Greater => age > duration,
         }
     }
+
+    /// Compare the number of local calendar days between the two times
+    fn matches_calendar_days(&self, file_time: SystemTime, now: SystemTime) -> bool {
+        let day = |time: SystemTime| DateTime::<Local>::from(time).date_naive();
+        // Times in the future count as today
+        let days_ago = (day(now) - day(file_time)).num_days().max(0);
+        match self.comparison {
+            TimeComparison::Exactly => days_ago == self.value.abs(),
+            TimeComparison::Lesser => days_ago < self.value.abs(),
+            TimeComparison::Greater => days_ago > self.value.abs(),
+        }
+    }
 }
@@ -0,0 +1,152 @@
+//! Rendering of entry fields (sizes and times) shared by every output mode.
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, SecondsFormat};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How every field of an entry is rendered
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldFormat {
+    pub size: SizeFormat,
+    pub time: TimeFormat,
+}
+
+/// How sizes are rendered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SizeFormat {
+    /// Exact byte count
+    #[default]
+    Bytes,
+    /// Powers of 1024 with a one-letter unit, like `ls -h` (e.g. 1.4G)
+    Human,
+    /// Powers of 1000, like `ls --si` (e.g. 1.5G)
+    Si,
+}
+
+impl FromStr for SizeFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bytes" => Ok(SizeFormat::Bytes),
+            "human" => Ok(SizeFormat::Human),
+            "si" => Ok(SizeFormat::Si),
+            other => Err(format!(
+                "Unknown size format '{}'. Use bytes, human or si",
+                other
+            )),
+        }
+    }
+}
+
+/// Render `bytes` in the given format. Human and SI sizes keep one decimal
+/// below 10 and are rounded to whole units above.
+pub fn format_size(bytes: u64, format: SizeFormat) -> String {
+    let (base, units) = match format {
+        SizeFormat::Bytes => return bytes.to_string(),
+        SizeFormat::Human => (1024.0, ["K", "M", "G", "T", "P", "E"]),
+        SizeFormat::Si => (1000.0, ["k", "M", "G", "T", "P", "E"]),
+    };
+    if (bytes as f64) < base {
+        return bytes.to_string();
+    }
+
+    let mut value = bytes as f64 / base;
+    let mut unit = 0;
+    while value >= base && unit < units.len() - 1 {
+        value /= base;
+        unit += 1;
+    }
+    if value < 9.95 {
+        format!("{:.1}{}", value, units[unit])
+    } else if value.round() < base || unit == units.len() - 1 {
+        format!("{:.0}{}", value, units[unit])
+    } else {
+        // e.g. 1023.7K rounds up to the next unit
+        format!("1.0{}", units[unit + 1])
+    }
+}
+
+/// How timestamps are rendered
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TimeFormat {
+    /// Seconds since the Unix epoch
+    #[default]
+    Epoch,
+    /// RFC 3339 in the local time zone, e.g. 2026-10-15T18:03:24+02:00
+    Iso,
+    /// Time since now, e.g. "3 days ago"
+    Relative,
+    /// A strftime format in the local time zone, e.g. "%Y-%m-%d %H:%M"
+    Strftime(String),
+}
+
+impl TimeFormat {
+    /// A strftime format, checked up front so rendering cannot fail later
+    pub fn strftime(format: &str) -> Result<Self, String> {
+        if StrftimeItems::new(format).any(|item| item == Item::Error) {
+            return Err(format!("Invalid time format '{}'", format));
+        }
+        Ok(TimeFormat::Strftime(format.to_string()))
+    }
+}
+
+impl FromStr for TimeFormat {
+    type Err = String;
+
+    /// Parse a `--time-style`: epoch, iso or relative
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "epoch" => Ok(TimeFormat::Epoch),
+            "iso" => Ok(TimeFormat::Iso),
+            "relative" => Ok(TimeFormat::Relative),
+            other => Err(format!(
+                "Unknown time style '{}'. Use epoch, iso or relative",
+                other
+            )),
+        }
+    }
+}
+
+/// Render `time` in the given format, relative times being measured from `now`
+pub fn format_time(time: SystemTime, now: SystemTime, format: &TimeFormat) -> String {
+    match format {
+        TimeFormat::Epoch => match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => since.as_secs().to_string(),
+            Err(e) => format!("-{}", e.duration().as_secs()),
+        },
+        TimeFormat::Iso => {
+            DateTime::<Local>::from(time).to_rfc3339_opts(SecondsFormat::Secs, false)
+        }
+        TimeFormat::Relative => match now.duration_since(time) {
+            Ok(age) => format!("{} ago", describe_duration(age)),
+            Err(e) => format!("in {}", describe_duration(e.duration())),
+        },
+        TimeFormat::Strftime(format) => DateTime::<Local>::from(time).format(format).to_string(),
+    }
+}
+
+/// The duration in its largest whole unit, e.g. "3 days"
+fn describe_duration(duration: Duration) -> String {
+    const UNITS: [(u64, &str); 6] = [
+        (365 * 24 * 60 * 60, "year"),
+        (30 * 24 * 60 * 60, "month"),
+        (7 * 24 * 60 * 60, "week"),
+        (24 * 60 * 60, "day"),
+        (60 * 60, "hour"),
+        (60, "minute"),
+    ];
+    let secs = duration.as_secs();
+    let (count, unit) = UNITS
+        .iter()
+        .find(|(unit_secs, _)| secs >= *unit_secs)
+        .map_or((secs, "second"), |(unit_secs, unit)| {
+            (secs / unit_secs, *unit)
+        });
+    if count == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", count, unit)
+    }
+}
@@ -0,0 +1,143 @@
+//! gRPC search service (see `proto/rfind.proto`), enabled by the `grpc` feature.
+
+use crate::scanner::{Search, SearchOptions};
+use crate::server::{request_filter, ServerOptions};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
+
+/// Generated protobuf types and service definitions
+pub mod proto {
+    tonic::include_proto!("rfind.v1");
+}
+
+use proto::finder_server::{Finder, FinderServer};
+use proto::{SearchRequest, SearchResult, StatusRequest, StatusResponse};
+
+/// Number of results buffered per stream before the scanner waits for the client
+const STREAM_BUFFER: usize = 256;
+
+/// Implementation of the `Finder` service
+pub struct FinderService {
+    options: ServerOptions,
+    started: Instant,
+    active_searches: Arc<AtomicU64>,
+    total_searches: AtomicU64,
+}
+
+impl FinderService {
+    pub fn new(options: ServerOptions) -> Self {
+        FinderService {
+            options,
+            started: Instant::now(),
+            active_searches: Arc::new(AtomicU64::new(0)),
+            total_searches: AtomicU64::new(0),
+        }
+    }
+
+    fn search_options(&self, request: &SearchRequest) -> Result<SearchOptions, String> {
+        let root = if request.dir.is_empty() {
+            self.options.root.clone()
+        } else {
+            PathBuf::from(&request.dir)
+        };
+
+        let mut options = SearchOptions::new(request.pattern.as_str(), root);
+        options.threads = self.options.threads;
+        if request.max_depth > 0 {
+            options.max_depth = request.max_depth as usize;
+        }
+        options.filter = Arc::new(request_filter(
+            non_empty(&request.r#type),
+            non_empty(&request.size),
+            non_empty(&request.mtime),
+        )?);
+        Ok(options)
+    }
+}
+
+/// Protobuf strings are empty when unset
+fn non_empty(s: &str) -> Option<&str> {
+    if s.is_empty() {
+        None
+    } else {
+        Some(s)
+    }
+}
+
+#[tonic::async_trait]
+impl Finder for FinderService {
+    type SearchStream = ReceiverStream<Result<SearchResult, Status>>;
+
+    async fn search(
+        &self,
+        request: Request<SearchRequest>,
+    ) -> Result<Response<Self::SearchStream>, Status> {
+        let request = request.into_inner();
+        let options = self
+            .search_options(&request)
+            .map_err(Status::invalid_argument)?;
+        let limit = match request.limit {
+            0 => usize::MAX,
+            limit => limit as usize,
+        };
+        let search = Search::start(options).map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        self.total_searches.fetch_add(1, Ordering::Relaxed);
+        let active_searches = Arc::clone(&self.active_searches);
+        active_searches.fetch_add(1, Ordering::Relaxed);
+
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        tokio::task::spawn_blocking(move || {
+            for path in search.take(limit) {
+                let result = SearchResult {
+                    path: path.to_string_lossy().into_owned(),
+                };
+                // Stop once the client has gone away
+                if tx.blocking_send(Ok(result)).is_err() {
+                    break;
+                }
+            }
+            active_searches.fetch_sub(1, Ordering::Relaxed);
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn status(
+        &self,
+        _request: Request<StatusRequest>,
+    ) -> Result<Response<StatusResponse>, Status> {
+        Ok(Response::new(StatusResponse {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            uptime_secs: self.started.elapsed().as_secs(),
+            active_searches: self.active_searches.load(Ordering::Relaxed),
+            total_searches: self.total_searches.load(Ordering::Relaxed),
+        }))
+    }
+}
+
+/// Run the gRPC server on `listener` until it fails
+pub fn serve(
+    listener: TcpListener,
+    options: ServerOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        listener.set_nonblocking(true)?;
+        let listener = tokio::net::TcpListener::from_std(listener)?;
+        let incoming = TcpIncoming::from_listener(listener, true, None)
+            .map_err(|e| e as Box<dyn std::error::Error>)?;
+        tonic::transport::Server::builder()
+            .add_service(FinderServer::new(FinderService::new(options)))
+            .serve_with_incoming(incoming)
+            .await?;
+        Ok(())
+    })
+}
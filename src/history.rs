@@ -0,0 +1,78 @@
+//! Search history for `rfind history` and `rfind rerun`.
+//!
+//! Recording is opt-in: only when `RFIND_HISTORY` is set to a non-empty value
+//! other than `0` is each search appended to `~/.rfind/history`, one JSON object
+//! per line. Entries are numbered from 1, oldest first.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Environment variable that turns on history recording
+pub const HISTORY_ENV: &str = "RFIND_HISTORY";
+
+/// One recorded search
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    /// Working directory the search was run from
+    pub cwd: PathBuf,
+    /// Arguments after the program name
+    pub args: Vec<String>,
+    /// Number of matches
+    pub results: u64,
+    pub duration_ms: u64,
+}
+
+impl HistoryEntry {
+    pub fn new(args: Vec<String>, results: u64, duration: Duration) -> Self {
+        HistoryEntry {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            cwd: std::env::current_dir().unwrap_or_default(),
+            args,
+            results,
+            duration_ms: duration.as_millis() as u64,
+        }
+    }
+}
+
+/// Whether searches should be recorded
+pub fn is_enabled() -> bool {
+    std::env::var_os(HISTORY_ENV).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// File holding the history (`~/.rfind/history`)
+pub fn history_file() -> io::Result<PathBuf> {
+    directories_next::BaseDirs::new()
+        .map(|dirs| dirs.home_dir().join(".rfind").join("history"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No home directory"))
+}
+
+/// Read every recorded search, oldest first; no file means an empty history
+pub fn load(path: &Path) -> io::Result<Vec<HistoryEntry>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    BufReader::new(file)
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| serde_json::from_str(&line?).map_err(io::Error::other))
+        .collect()
+}
+
+pub fn append(path: &Path, entry: &HistoryEntry) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let line = serde_json::to_string(entry).map_err(io::Error::other)?;
+    writeln!(file, "{}", line)
+}
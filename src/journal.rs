@@ -0,0 +1,233 @@
+//! Undo journal for mutating actions.
+//!
+//! Every change made by `--rename`, `--move-to`, `--delete` (with or without
+//! `--trash`), `--delete-empty-dirs` and destructive `--exec` commands is appended to
+//! `~/.rfind/journal/<run-id>.jsonl`, one JSON object per line. `rfind undo <run-id>`
+//! walks a journal backwards and reverses whatever can be reversed.
+
+use crate::actions::Operation;
+use crate::checksum::sha256_file;
+use crate::placeholder::is_placeholder;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Files up to this size get a checksum, so undo can tell if they changed since
+const CHECKSUM_LIMIT: u64 = 1024 * 1024;
+
+/// Kind of change recorded in the journal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JournalOperation {
+    Move,
+    Delete,
+    Trash,
+    Exec,
+}
+
+/// One recorded change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub operation: JournalOperation,
+    /// Absolute path of the entry before the change
+    pub path: PathBuf,
+    /// Where the entry was moved to, for moves and renames
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination: Option<PathBuf>,
+    /// The command line that was run, for exec
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<Vec<String>>,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    /// SHA-256 of the file contents, for small regular files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+}
+
+impl JournalEntry {
+    /// Describe `operation` on the match `subject`; must be called before the
+    /// operation is performed
+    pub fn before(operation: &Operation, subject: &Path) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let (operation, path, destination, command) = match operation {
+            Operation::Move { from, to } => (
+                JournalOperation::Move,
+                from.clone(),
+                Some(absolute(to)),
+                None,
+            ),
+            Operation::Delete(path) => (JournalOperation::Delete, path.clone(), None, None),
+            Operation::Trash(path) => (JournalOperation::Trash, path.clone(), None, None),
+            Operation::Exec(argv) => (
+                JournalOperation::Exec,
+                subject.to_path_buf(),
+                None,
+                Some(
+                    argv.iter()
+                        .map(|arg| arg.to_string_lossy().into_owned())
+                        .collect(),
+                ),
+            ),
+        };
+        let checksum = small_file_checksum(&path);
+        JournalEntry {
+            operation,
+            path: absolute(&path),
+            destination,
+            command,
+            timestamp,
+            checksum,
+        }
+    }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn small_file_checksum(path: &Path) -> Option<String> {
+    let metadata = fs::symlink_metadata(path).ok()?;
+    if metadata.is_file() && metadata.len() <= CHECKSUM_LIMIT && !is_placeholder(&metadata) {
+        sha256_file(path).ok()
+    } else {
+        None
+    }
+}
+
+/// Directory holding the journals (`~/.rfind/journal`)
+pub fn journal_dir() -> io::Result<PathBuf> {
+    directories_next::BaseDirs::new()
+        .map(|dirs| dirs.home_dir().join(".rfind").join("journal"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No home directory"))
+}
+
+/// Journal of the changes made by one run; the file is only created once
+/// something is recorded
+pub struct Journal {
+    run_id: String,
+    path: PathBuf,
+    file: Mutex<Option<LineWriter<File>>>,
+}
+
+impl Journal {
+    /// Start a journal for a new run in [`journal_dir`]
+    pub fn create() -> io::Result<Self> {
+        Ok(Self::in_dir(&journal_dir()?))
+    }
+
+    pub fn in_dir(dir: &Path) -> Self {
+        // e.g. 20261015T174312Z-4242
+        let timestamp = humantime::format_rfc3339_seconds(SystemTime::now())
+            .to_string()
+            .replace(['-', ':'], "");
+        let run_id = format!("{}-{}", timestamp, std::process::id());
+        Journal {
+            path: dir.join(format!("{}.jsonl", run_id)),
+            run_id,
+            file: Mutex::new(None),
+        }
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Whether anything has been recorded yet
+    pub fn is_empty(&self) -> bool {
+        self.file.lock().is_none()
+    }
+
+    pub fn record(&self, entry: &JournalEntry) -> io::Result<()> {
+        let mut file = self.file.lock();
+        if file.is_none() {
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let created = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            *file = Some(LineWriter::new(created));
+        }
+        let line = serde_json::to_string(entry).map_err(io::Error::other)?;
+        match file.as_mut() {
+            Some(file) => writeln!(file, "{}", line),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Read the entries recorded for `run_id`, oldest first
+pub fn load(dir: &Path, run_id: &str) -> io::Result<Vec<JournalEntry>> {
+    let file = File::open(dir.join(format!("{}.jsonl", run_id)))?;
+    BufReader::new(file)
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| serde_json::from_str(&line?).map_err(io::Error::other))
+        .collect()
+}
+
+/// Reverse a single recorded change
+pub fn undo(entry: &JournalEntry) -> Result<(), String> {
+    match entry.operation {
+        JournalOperation::Move => {
+            let destination = entry
+                .destination
+                .as_ref()
+                .ok_or("Journal entry has no destination")?;
+            if let Some(expected) = &entry.checksum {
+                let actual = sha256_file(destination).map_err(|e| e.to_string())?;
+                if &actual != expected {
+                    return Err(format!("{} has changed since", destination.display()));
+                }
+            }
+            Operation::Move {
+                from: destination.clone(),
+                to: entry.path.clone(),
+            }
+            .perform()
+            .map_err(|e| e.to_string())
+        }
+        JournalOperation::Trash => restore_from_trash(&entry.path),
+        JournalOperation::Delete => Err("Permanently deleted".to_string()),
+        JournalOperation::Exec => Err("Commands cannot be undone".to_string()),
+    }
+}
+
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+fn restore_from_trash(path: &Path) -> Result<(), String> {
+    let item = trash::os_limited::list()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|item| item.original_path() == path)
+        .max_by_key(|item| item.time_deleted)
+        .ok_or("No longer in the trash")?;
+    trash::os_limited::restore_all([item]).map_err(|e| e.to_string())
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+fn restore_from_trash(_path: &Path) -> Result<(), String> {
+    Err("Restoring from the trash is not supported on this platform".to_string())
+}
@@ -0,0 +1,141 @@
+//! Jump database for `rfind jump`.
+//!
+//! Directories picked through the shell integration are recorded in
+//! `~/.rfind/jump.json` with a visit count and the time of the last visit.
+//! Queries rank the matching directories by frecency, the same way zoxide
+//! does: the visit count weighted by how recently the directory was visited.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Least-visited directories are forgotten beyond this many entries
+const MAX_ENTRIES: usize = 1000;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Visits {
+    pub count: f64,
+    /// Seconds since the Unix epoch
+    pub last_visit: u64,
+}
+
+impl Visits {
+    /// Visit count weighted by the time since the last visit
+    pub fn frecency(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last_visit);
+        let weight = if age < HOUR {
+            4.0
+        } else if age < DAY {
+            2.0
+        } else if age < WEEK {
+            0.5
+        } else {
+            0.25
+        };
+        self.count * weight
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct JumpDatabase {
+    pub dirs: BTreeMap<PathBuf, Visits>,
+}
+
+/// File holding the jump database (`~/.rfind/jump.json`)
+pub fn database_file() -> io::Result<PathBuf> {
+    directories_next::BaseDirs::new()
+        .map(|dirs| dirs.home_dir().join(".rfind").join("jump.json"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No home directory"))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl JumpDatabase {
+    /// Read the database; a missing file is an empty database
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| format!("Invalid jump database {}: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(JumpDatabase::default()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = serde_json::to_string(self).map_err(io::Error::other)?;
+        fs::write(path, contents)
+    }
+
+    /// Record a visit to `dir`, which should be absolute
+    pub fn add(&mut self, dir: &Path) {
+        let visits = self.dirs.entry(dir.to_path_buf()).or_insert(Visits {
+            count: 0.0,
+            last_visit: 0,
+        });
+        visits.count += 1.0;
+        visits.last_visit = now();
+
+        if self.dirs.len() > MAX_ENTRIES {
+            let now = now();
+            let mut ranked: Vec<(PathBuf, f64)> = self
+                .dirs
+                .iter()
+                .map(|(dir, visits)| (dir.clone(), visits.frecency(now)))
+                .collect();
+            ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+            for (dir, _) in ranked.into_iter().skip(MAX_ENTRIES) {
+                self.dirs.remove(&dir);
+            }
+        }
+    }
+
+    /// Existing directories matching every keyword, best first. Keywords are
+    /// matched case-insensitively and in order, and the last one must appear
+    /// in the final path component.
+    pub fn query(&self, keywords: &[String]) -> Vec<&Path> {
+        let now = now();
+        let keywords: Vec<String> = keywords.iter().map(|k| k.to_lowercase()).collect();
+        let mut matches: Vec<(&Path, f64)> = self
+            .dirs
+            .iter()
+            .filter(|(dir, _)| matches_keywords(dir, &keywords) && dir.is_dir())
+            .map(|(dir, visits)| (dir.as_path(), visits.frecency(now)))
+            .collect();
+        matches.sort_by(|a, b| b.1.total_cmp(&a.1));
+        matches.into_iter().map(|(dir, _)| dir).collect()
+    }
+}
+
+fn matches_keywords(dir: &Path, keywords: &[String]) -> bool {
+    let path = dir.to_string_lossy().to_lowercase();
+    let mut rest = path.as_str();
+    for keyword in keywords {
+        match rest.find(keyword.as_str()) {
+            Some(index) => rest = &rest[index + keyword.len()..],
+            None => return false,
+        }
+    }
+    match keywords.last() {
+        Some(last) => dir.file_name().is_some_and(|name| {
+            name.to_string_lossy()
+                .to_lowercase()
+                .contains(last.as_str())
+        }),
+        None => true,
+    }
+}
@@ -1 +1,39 @@
+pub mod actions;
+pub mod alias;
+pub mod archive;
+pub mod checkpoint;
+pub mod checksum;
+pub mod cleanup;
+pub mod config;
+pub mod du;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fields;
 pub mod filters;
+pub mod format;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod history;
+pub mod journal;
+pub mod jump;
+pub mod manifest;
+pub mod memory;
+pub mod permissions;
+pub mod placeholder;
+pub mod plugin;
+pub mod presets;
+pub mod query;
+pub mod rank;
+pub mod reparse;
+pub mod rpc;
+pub mod saved;
+pub mod scanner;
+pub mod server;
+pub mod summary;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use scanner::{
+    CancelToken, ErrorHandler, PatternError, PatternSyntax, ScanError, ScanOperation, ScanStats,
+    Search, SearchOptions, SearchResults, SymlinkMode,
+};
@@ -1,92 +1,115 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use colored::*;
-use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
-use glob::Pattern;
-use log::debug;
-use memchr::memmem::FinderBuilder; // Uses Boyer-Moore-Horspool algorithm for substring search
+use env_logger::Env;
 use parking_lot::Mutex;
-use pathdiff::diff_paths;
-use std::error::Error;
-use std::io::Write;
-use std::path::Path;
-use std::sync::{
-    atomic::{AtomicUsize, Ordering},
-    Arc,
-};
-use std::thread;
-use std::time::{Duration, SystemTime};
-use std::{collections::HashSet, path::PathBuf};
-mod filters;
-
-#[derive(Default, Debug, Clone, Copy)]
-enum SymlinkMode {
-    #[default]
-    Never, // -P: Never follow symlinks
-    Command, // -H: Follow symlinks on command line only
-    Always,  // -L: Follow all symlinks
-}
-
-enum PatternMatcher {
-    Glob(Pattern),
-    Substring { pattern_bytes: Box<[u8]> },
-}
-
-impl PatternMatcher {
-    fn matches(&self, filename: &str) -> bool {
-        match self {
-            PatternMatcher::Glob(pattern) => pattern.matches(filename),
-            PatternMatcher::Substring { pattern_bytes, .. } => {
-                let filename_lower = filename.to_lowercase();
-                FinderBuilder::new()
-                    .build_forward(pattern_bytes)
-                    .find(filename_lower.as_bytes())
-                    .is_some()
-            }
-        }
-    }
-}
-
-fn create_pattern_matcher(pattern: &str) -> PatternMatcher {
-    if pattern.contains('*') || pattern.contains('?') {
-        PatternMatcher::Glob(Pattern::new(pattern).expect("Invalid glob pattern"))
-    } else {
-        let pattern_lower = pattern.to_lowercase();
-        let pattern_bytes = pattern_lower.as_bytes().to_vec().into_boxed_slice();
-
-        PatternMatcher::Substring { pattern_bytes }
-    }
-}
+use regex::Regex;
+use rfind::actions::{shell_quote, Action, Executor, Impact, SafetyLimits};
+use rfind::checkpoint::Checkpoint;
+use rfind::cleanup;
+use rfind::config::{self, Config};
+use rfind::du::DirSizes;
+use rfind::fields::{self, Field};
+use rfind::filters;
+use rfind::format::{format_size, format_time, FieldFormat, SizeFormat, TimeFormat};
+use rfind::history::{self, HistoryEntry};
+use rfind::journal::{self, Journal};
+use rfind::jump::{self, JumpDatabase};
+use rfind::manifest::{self, ManifestWriter};
+use rfind::memory::{MemoryBudget, PathBuffer};
+use rfind::permissions::{self, PermissionIssue};
+use rfind::plugin::Plugins;
+use rfind::presets;
+use rfind::query::Query;
+use rfind::rank::TopN;
+use rfind::rpc;
+use rfind::saved;
+use rfind::scanner::{PatternMatcher, PatternSyntax};
+use rfind::server::{self, ServerOptions};
+use rfind::summary::ExtensionSummary;
+use rfind::{ErrorHandler, ScanError, ScanOperation, Search, SearchOptions, SymlinkMode};
+use serde_json::json;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{IsTerminal, LineWriter, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Options acting on matches, which print nothing of their own
+const ACTIONS: [&str; 5] = ["exec", "rename", "move_to", "delete", "delete_empty_dirs"];
 
 /// Parallel recursive file finder
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    // Later values win, so the command line can override RFIND_OPTS
+    args_override_self = true
+)]
 struct Args {
-    /// Pattern to search for (glob patterns like *.log or substring search)
-    #[arg(required = true)]
-    pattern: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Pattern to search for (glob patterns like *.log or substring search);
+    /// optional with --has-file, --preset, --empty, --delete-empty-dirs,
+    /// --broken-symlinks, --lname or --patterns-from
+    #[arg(required_unless_present_any = ["lsp_like", "has_file", "preset", "empty", "delete_empty_dirs", "broken_symlinks", "lname", "patterns_from"])]
+    pattern: Option<String>,
+
+    /// Read more patterns from FILE, one per line (blank lines and lines
+    /// starting with '#' are skipped). Names matching any of them or the
+    /// PATTERN match.
+    #[arg(long = "patterns-from", value_name = "FILE")]
+    patterns_from: Option<PathBuf>,
+
+    /// Treat the patterns as regular expressions, matched anywhere in the
+    /// name unless anchored with ^ or $
+    #[arg(long = "regex", conflicts_with = "iregex")]
+    regex: bool,
+
+    /// Like --regex, but ignoring case
+    #[arg(long = "iregex")]
+    iregex: bool,
 
     /// Starting directory (defaults to root directory)
     #[arg(short, long, default_value = "/")]
     dir: PathBuf,
 
+    /// Change to DIR before searching, so that a relative --dir, --exec
+    /// commands and relative output all resolve against it
+    #[arg(long = "base-directory", value_name = "DIR")]
+    base_directory: Option<PathBuf>,
+
+    /// Apply the options of a profile from the config file (~/.rfind/config.toml,
+    /// or $RFIND_CONFIG) before the rest of the command line. Can be repeated.
+    #[arg(long = "profile", value_name = "NAME")]
+    profile: Vec<String>,
+
     /// Maximum search depth
     #[arg(short, long, default_value = "100")]
     max_depth: usize,
 
-    /// Number of worker threads (defaults to number of CPU cores)
+    /// Number of worker threads (defaults to number of CPU cores); 1 walks in
+    /// sorted, deterministic order
     #[arg(short = 'j', long)]
     threads: Option<usize>,
 
     /// Never follow symbolic links (default)
-    #[arg(short = 'P', long, group = "symlink_mode")]
+    #[arg(short = 'P', long, group = "symlink_mode", overrides_with_all = ["cmd_follow", "follow_all"])]
     no_follow: bool,
 
     /// Follow symbolic links on command line only
-    #[arg(short = 'H', long, group = "symlink_mode")]
+    #[arg(short = 'H', long, group = "symlink_mode", overrides_with_all = ["no_follow", "follow_all"])]
     cmd_follow: bool,
 
     /// Follow all symbolic links
-    #[arg(short = 'L', long, group = "symlink_mode")]
+    #[arg(short = 'L', long, group = "symlink_mode", overrides_with_all = ["no_follow", "cmd_follow"])]
     follow_all: bool,
 
     /// Filter the results by type.
@@ -99,26 +122,622 @@ struct Args {
     #[arg(long = "print0")]
     print0: bool,
 
-    /// Filter by modification time (format: [+-]N[smhd])
+    /// Print each match as a JSON object on its own line (newline-delimited JSON)
+    #[arg(long = "json", group = "structured", conflicts_with_all = ["print0", "ranking"])]
+    json: bool,
+
+    /// Print matches as CSV, with a header line naming the --fields
+    #[arg(long = "csv", group = "structured", conflicts_with_all = ["print0", "ranking"])]
+    csv: bool,
+
+    /// Comma-separated fields to output with --json or --csv: path, name,
+    /// ext, type, size, mtime, owner, perm, target [default: path]
+    #[arg(
+        long = "fields",
+        value_name = "FIELDS",
+        value_delimiter = ',',
+        value_parser = Field::parse,
+        requires = "structured"
+    )]
+    fields: Vec<Field>,
+
+    /// Print the first match and stop searching right away
+    #[arg(
+        short = '1',
+        long = "first",
+        conflicts_with_all = ["ranking", "delete_empty_dirs", "checkpoint", "resume"]
+    )]
+    first: bool,
+
+    /// Print each symlink match followed by `->` and the path it points to
+    #[arg(long = "list-targets", conflicts_with_all = ACTIONS)]
+    list_targets: bool,
+
+    /// Print the pattern each match was found by before it (as a "pattern"
+    /// key with --json), for searches with several patterns from
+    /// --patterns-from
+    #[arg(long = "show-pattern", conflicts_with_all = ["csv", "fields"])]
+    show_pattern: bool,
+
+    /// Print only the N largest matches, biggest first with their sizes, once
+    /// the scan is done
+    #[arg(long = "largest", value_name = "N", group = "ranking", conflicts_with_all = ACTIONS)]
+    largest: Option<usize>,
+
+    /// Print only the N most recently modified matches, newest first with
+    /// their modification times, once the scan is done
+    #[arg(long = "newest", value_name = "N", group = "ranking", conflicts_with_all = ACTIONS)]
+    newest: Option<usize>,
+
+    /// Print only the N least recently modified matches, oldest first with
+    /// their modification times, once the scan is done
+    #[arg(long = "oldest", value_name = "N", group = "ranking", conflicts_with_all = ACTIONS)]
+    oldest: Option<usize>,
+
+    /// Instead of listing matches, print a table of their count and total size
+    /// by extension, largest first (directories are left out)
+    #[arg(long = "summarize-ext", group = "ranking", conflicts_with_all = ACTIONS)]
+    summarize_ext: bool,
+
+    /// How to show sizes with --largest, --summarize-ext and the size field of
+    /// --csv and --fields: bytes, human (powers of 1024, e.g. 1.4G) or si
+    /// (powers of 1000) [default: human, or bytes for --csv and --json]
+    #[arg(long = "size-format")]
+    size_format: Option<SizeFormat>,
+
+    /// How to show times with --newest, --oldest and the mtime field of --csv
+    /// and --fields: epoch (seconds), iso (local RFC 3339) or relative ("3
+    /// days ago") [default: iso, or epoch for --csv and --json]
+    #[arg(long = "time-style")]
+    time_style: Option<TimeFormat>,
+
+    /// Show times with --newest, --oldest and the mtime field of --csv and
+    /// --fields in a strftime format, e.g. '%Y-%m-%d %H:%M'
+    #[arg(long = "time-format", value_parser = TimeFormat::strftime, conflicts_with = "time_style")]
+    time_format: Option<TimeFormat>,
+
+    /// Print matches below the current directory relative to it: bare
+    /// (always), with a leading ./ like find (never), or bare unless --print0
+    /// is given (auto). Without this flag, absolute paths are printed.
+    #[arg(
+        long = "strip-cwd-prefix",
+        value_name = "WHEN",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "auto"
+    )]
+    strip_cwd_prefix: Option<StripCwdPrefix>,
+
+    /// Filter by modification time (format: [+-]N[smhdwMy])
     /// Examples: +1d (more than 1 day), -2m (less than 2 minutes), 3d (exactly 3 days), +1h (more than 1 hour), -45s (less than 45 seconds)
     #[arg(long = "mtime", allow_hyphen_values = true)]
     mtime: Option<String>,
 
-    /// Filter by access time (format: [+-]N[smhd])
+    /// Filter by access time (format: [+-]N[smhdwMy])
     #[arg(long = "atime", allow_hyphen_values = true)]
     atime: Option<String>,
 
-    /// Filter by change time (format: [+-]N[smhd])
+    /// Filter by change time (format: [+-]N[smhdwMy])
     #[arg(long = "ctime", allow_hyphen_values = true)]
     ctime: Option<String>,
 
-    /// Filter by file size (format: [+-]N[ckMG])
-    /// Examples: +1M (more than 1MiB), -500k (less than 500KiB), 1G (approximately 1GiB)
+    /// Count days in --mtime, --atime and --ctime as calendar days before
+    /// today rather than 24-hour steps back from now, like find -daystart:
+    /// --mtime 1d --daystart matches anything modified yesterday
+    #[arg(long = "daystart")]
+    daystart: bool,
+
+    /// Only match entries modified more recently than FILE
+    #[arg(long = "newer", value_name = "FILE")]
+    newer: Option<PathBuf>,
+
+    /// Like find -newerXY: only match entries whose X time is later than the
+    /// Y time of REF. X and Y are a (access), B (birth), c (change) or m
+    /// (modification); Y may also be t, making REF a timestamp such as
+    /// 2024-06-01, '2024-06-01 13:30' or @1717200000. Can be repeated.
+    /// Examples: --newer-xy mt 2024-06-01, --newer-xy cm build.stamp
+    #[arg(long = "newer-xy", num_args = 2, value_names = ["XY", "REF"])]
+    newer_xy: Vec<String>,
+
+    /// Filter by file size (format: [+-]N[c|b|k|M|G|kB|MB|GB])
+    /// Examples: +1M (more than 1MiB), -500k (less than 500KiB), 1G (approximately 1GiB),
+    /// +1.5GB (more than 1.5 billion bytes)
     #[arg(long = "size", allow_hyphen_values = true)]
     size: Option<String>,
+
+    /// Interpret --size like find -size: a number without a unit counts
+    /// 512-byte blocks, and sizes are rounded up to whole units before comparing
+    #[arg(long = "find-compat")]
+    find_compat: bool,
+
+    /// Only match empty regular files and directories without any entries
+    #[arg(long = "empty")]
+    empty: bool,
+
+    /// Only match symlinks whose target no longer exists (or that form a loop)
+    #[arg(long = "broken-symlinks")]
+    broken_symlinks: bool,
+
+    /// Only match symlinks whose target, as stored in the link, matches a glob
+    /// (like find -lname). Example: --lname '/old/storage/*'
+    #[arg(long = "lname", value_name = "PATTERN")]
+    lname: Option<String>,
+
+    /// Only match entries stored locally, leaving out cloud placeholders
+    /// (OneDrive Files On-Demand, iCloud Drive) whose contents have not been
+    /// downloaded
+    #[arg(
+        long = "only-local",
+        visible_alias = "only-downloaded",
+        conflicts_with = "only_cloud"
+    )]
+    only_local: bool,
+
+    /// Only match cloud placeholders whose contents have not been downloaded
+    #[arg(long = "only-cloud")]
+    only_cloud: bool,
+
+    /// Filter with an expression over entry fields
+    /// Example: 'size > 10*MB && name.ends_with(".log") && age_days > 30'
+    /// Fields: name, path, ext, size, age_secs, age_days, is_file, is_dir, is_symlink
+    #[arg(long = "where", value_name = "EXPR")]
+    where_expr: Option<String>,
+
+    /// Only match directories directly containing FILE, e.g. .git or
+    /// Cargo.toml to find project roots (may be given multiple times; all must exist)
+    #[arg(long = "has-file", value_name = "FILE")]
+    has_file: Vec<String>,
+
+    /// Skip dependency, build, cache and VCS directories such as node_modules,
+    /// target, .venv, __pycache__ and .git (the list can be changed with
+    /// dev_clutter in the config file)
+    #[arg(long = "no-dev-clutter")]
+    no_dev_clutter: bool,
+
+    /// Leave out entries whose path relative to the starting directory matches
+    /// a regex; excluded directories are not descended into. Can be repeated.
+    /// Example: --exclude-regex '(^|/)v[0-9]+\.[0-9]+(/|$)'
+    #[arg(long = "exclude-regex", value_name = "REGEX", value_parser = Regex::new)]
+    exclude_regex: Vec<Regex>,
+
+    /// Only match files with an extension from a group: images, videos, audio,
+    /// documents, code, or one defined under [presets] in the config file
+    /// (may be given multiple times). `security` instead matches risky
+    /// permissions, and labels each match with the rules it broke.
+    #[arg(long = "preset", value_name = "NAME")]
+    preset: Vec<String>,
+
+    /// Skip any path whose stat/readdir takes longer than this (e.g. 2s, 500ms),
+    /// so a hung network mount cannot stall the search
+    #[arg(long = "stat-timeout", value_name = "DURATION", value_parser = humantime::parse_duration)]
+    stat_timeout: Option<Duration>,
+
+    /// Try listing a directory or reading an entry's metadata up to N more
+    /// times, with a short pause, after a transient error (EINTR, EAGAIN,
+    /// ESTALE or a Windows sharing violation) before counting it as failed
+    #[arg(long = "io-retries", value_name = "N", default_value_t = rfind::scanner::DEFAULT_IO_RETRIES)]
+    io_retries: u32,
+
+    /// Abort the search with a non-zero exit status once more than N
+    /// directories or entries could not be read, after printing the matches
+    /// found so far
+    #[arg(long = "max-errors", value_name = "N")]
+    max_errors: Option<u64>,
+
+    /// Follow macOS Finder aliases to folders the same way as symlinks
+    /// (with -L, or -H for the starting directory). No effect on other platforms.
+    #[arg(long = "resolve-aliases")]
+    resolve_aliases: bool,
+
+    /// Also match the pattern against the names of files inside zip, tar and
+    /// tar.gz archives, reporting hits as archive.zip!/path/inside
+    #[arg(long = "search-archives")]
+    search_archives: bool,
+
+    /// Only share directories down to this depth between threads; deeper ones
+    /// are scanned by the thread that found them, which suits very deep trees
+    #[arg(long = "fanout-depth", value_name = "K")]
+    fanout_depth: Option<usize>,
+
+    /// Scan DIR (and the directories leading to it) before anything else, so
+    /// its matches show up first (may be given multiple times)
+    #[arg(long = "prefer", value_name = "DIR")]
+    prefer: Vec<PathBuf>,
+
+    /// Scan the documents, desktop, downloads and code directories (~/code,
+    /// ~/src, ~/projects, ...) in your home directory first
+    #[arg(long = "prefer-common")]
+    prefer_common: bool,
+
+    /// Skip (and report) directories holding more than N entries, such as huge
+    /// cache directories. The starting directory is always scanned.
+    #[arg(long = "max-dir-entries", value_name = "N")]
+    max_dir_entries: Option<usize>,
+
+    /// Scan DIR even if it exceeds --max-dir-entries (may be given multiple times)
+    #[arg(
+        long = "include-large",
+        value_name = "DIR",
+        requires = "max_dir_entries"
+    )]
+    include_large: Vec<PathBuf>,
+
+    /// Keep the memory held by buffered matches, visited directories and sort
+    /// keys under about SIZE (format: N[c|b|k|M|G|kB|MB|GB]), spilling to a
+    /// temporary file or using leaner strategies beyond it
+    #[arg(long = "max-memory", value_name = "SIZE", value_parser = parse_size_limit)]
+    max_memory: Option<u64>,
+
+    /// Number of matches that may wait to be printed before the scan pauses,
+    /// so a slow consumer (a pager, a remote terminal) paces the scan
+    #[arg(long = "result-buffer", value_name = "N", default_value_t = rfind::scanner::DEFAULT_RESULT_BUFFER)]
+    result_buffer: usize,
+
+    /// Never pause the scan for a slow consumer, buffering every pending match
+    /// in memory instead
+    #[arg(long = "no-backpressure", conflicts_with = "result_buffer")]
+    no_backpressure: bool,
+
+    /// Write a JSON Lines manifest (path, size, mtime) of every match to FILE
+    #[arg(long = "manifest", value_name = "FILE")]
+    manifest: Option<PathBuf>,
+
+    /// Include a SHA-256 checksum of each file in the --manifest
+    #[arg(long = "checksum", requires = "manifest")]
+    checksum: bool,
+
+    /// Never read the contents of files larger than SIZE (format: N[c|b|k|M|G|kB|MB|GB]);
+    /// such files get no --checksum and are reported as skipped
+    #[arg(long = "max-filesize", value_name = "SIZE", value_parser = parse_size_limit)]
+    max_filesize: Option<u64>,
+
+    /// Re-scan and report files added, removed or changed since the manifest
+    /// FILE was written. Exits with status 1 if anything drifted.
+    #[arg(
+        long = "verify-manifest",
+        value_name = "FILE",
+        conflicts_with = "manifest"
+    )]
+    verify_manifest: Option<PathBuf>,
+
+    /// Periodically save the search progress to FILE, so an interrupted scan
+    /// can be continued with --resume. The file is removed once the scan completes.
+    #[arg(long = "checkpoint", value_name = "FILE")]
+    checkpoint: Option<PathBuf>,
+
+    /// How often to save the --checkpoint file
+    #[arg(
+        long = "checkpoint-interval",
+        value_name = "DURATION",
+        default_value = "30s",
+        value_parser = humantime::parse_duration
+    )]
+    checkpoint_interval: Duration,
+
+    /// Continue an interrupted scan from a checkpoint FILE (saving further
+    /// progress to the same file unless --checkpoint is given)
+    #[arg(long = "resume", value_name = "FILE")]
+    resume: Option<PathBuf>,
+
+    /// Record every skipped directory, permission failure and timed-out path
+    /// (with the reason) in FILE instead of discarding it
+    #[arg(long = "error-log", value_name = "FILE")]
+    error_log: Option<PathBuf>,
+
+    /// Format of log events on stderr and in the --error-log file.
+    /// `json` writes one object per line with level, target, path and error fields.
+    #[arg(long = "log-format", value_enum, default_value = "text")]
+    log_format: LogFormat,
+
+    /// Load a filter plugin from a shared library, or the filters and actions
+    /// of a .toml plugin manifest (may be given multiple times).
+    /// Only entries accepted by every plugin are reported.
+    #[arg(long = "plugin", value_name = "PLUGIN")]
+    plugins: Vec<PathBuf>,
+
+    /// Load a sandboxed WebAssembly filter module (may be given multiple times).
+    /// Requires rfind to be built with the `wasm` feature.
+    #[arg(long = "wasm-filter", value_name = "MODULE")]
+    wasm_filters: Vec<PathBuf>,
+
+    /// Only match entries for which COMMAND exits with status 0, with {}
+    /// replaced by the path (appended if absent), e.g. 'ffprobe -v error {}'.
+    /// Runs after every other filter.
+    #[arg(long = "filter-cmd", value_name = "COMMAND")]
+    filter_cmd: Option<String>,
+
+    /// Number of --filter-cmd commands allowed to run at once (defaults to
+    /// number of CPU cores)
+    #[arg(long = "filter-jobs", value_name = "N", requires = "filter_cmd")]
+    filter_jobs: Option<usize>,
+
+    /// Run COMMAND on each match, with {} replaced by its path.
+    /// The command ends at a ';' argument (quote it from the shell).
+    /// Ending it with '{} +' instead runs it on many matches at once.
+    #[arg(
+        long = "exec",
+        value_name = "COMMAND",
+        num_args = 1..,
+        value_terminator = ";",
+        allow_hyphen_values = true
+    )]
+    exec: Option<Vec<String>>,
+
+    /// Number of batched --exec commands ('{} +') allowed to run at once
+    #[arg(
+        long = "exec-jobs",
+        value_name = "N",
+        default_value = "1",
+        requires = "exec"
+    )]
+    exec_jobs: usize,
+
+    /// Rename each match, replacing the first FROM in its file name with TO
+    #[arg(long = "rename", value_name = "FROM=TO", value_parser = Action::parse_rename)]
+    rename: Option<Action>,
+
+    /// Move each match into DIR
+    #[arg(long = "move-to", value_name = "DIR", conflicts_with = "delete")]
+    move_to: Option<PathBuf>,
+
+    /// Delete each match (directories only if empty once the matches inside
+    /// them are deleted). Refused when searching from /
+    #[arg(long = "delete", conflicts_with = "rename")]
+    delete: bool,
+
+    /// Delete matching empty directories, deepest first, along with any
+    /// directory below the starting one that is left empty by that
+    #[arg(long = "delete-empty-dirs", conflicts_with_all = ["delete", "rename", "move_to"])]
+    delete_empty_dirs: bool,
+
+    /// With --delete, move matches to the trash / recycle bin instead of
+    /// removing them permanently
+    #[arg(long = "trash", requires = "delete")]
+    trash: bool,
+
+    /// Ask on stderr before running the --exec command on, or deleting, each
+    /// match
+    #[arg(long = "ok", conflicts_with = "dry_run")]
+    ok: bool,
+
+    /// Ask for confirmation before destructive actions that would affect more
+    /// than N entries
+    #[arg(long = "max-affected", value_name = "N", default_value = "100")]
+    max_affected: usize,
+
+    /// Ask for confirmation before destructive actions that would affect more
+    /// than SIZE of files (format: N[c|b|k|M|G|kB|MB|GB])
+    #[arg(
+        long = "max-affected-size",
+        value_name = "SIZE",
+        default_value = "1G",
+        value_parser = parse_size_limit
+    )]
+    max_affected_size: u64,
+
+    /// Run destructive actions without asking, even above the safety limits
+    #[arg(long = "force")]
+    force: bool,
+
+    /// Print what --delete, --delete-empty-dirs, --rename, --move-to and
+    /// destructive --exec commands would do, without changing anything
+    #[arg(long = "dry-run")]
+    dry_run: bool,
+
+    /// When done, print a JSON summary of the run (matches, directories and
+    /// entries scanned, errors, duration) as the last line on stderr
+    #[arg(long = "stats-json")]
+    stats_json: bool,
+
+    /// Speak JSON-RPC over stdin/stdout (LSP-style framing) for editor integrations
+    #[arg(long = "lsp-like")]
+    lsp_like: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Save a search under a name, to repeat it later with `rfind run NAME`
+    /// Example: rfind save-search bigvideos -- '*.mp4' --size +1G --dir ~/Media
+    SaveSearch {
+        /// Name to save the search as
+        name: String,
+
+        /// The search arguments, exactly as they would be passed to rfind
+        #[arg(last = true, required = true)]
+        args: Vec<String>,
+    },
+
+    /// Run a saved search, or list the saved searches if no name is given
+    Run {
+        /// Name of the saved search
+        name: Option<String>,
+
+        /// Extra arguments appended to the saved ones
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+
+    /// List recent searches, with their result count and duration.
+    /// Searches are only recorded when RFIND_HISTORY=1 is set.
+    History {
+        /// Number of searches to show
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+    },
+
+    /// Run a search from the history again, from the directory it was run in
+    Rerun {
+        /// Number of the search, as shown by `rfind history`
+        number: usize,
+    },
+
+    /// Print shell functions and key bindings for rfind
+    /// Example: eval "$(rfind init zsh)"
+    Init {
+        /// Shell to generate the integration for
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+
+    /// Print the best match from the jump database of visited directories
+    /// Example: cd "$(rfind jump proj api)"
+    Jump {
+        /// Keywords to match, in order; the last one must be in the directory name
+        keywords: Vec<String>,
+
+        /// Record a visit to DIR instead of querying
+        #[arg(long, value_name = "DIR", conflicts_with_all = ["keywords", "list"])]
+        add: Option<PathBuf>,
+
+        /// Print every match, best first
+        #[arg(short, long)]
+        list: bool,
+    },
+
+    /// Reverse the changes recorded for a run of --rename, --move-to or --delete
+    /// (where possible). Run ids are printed when a run finishes.
+    Undo {
+        /// Run id, e.g. 20261015T174312Z-4242
+        run_id: String,
+    },
+
+    /// Run an SQL-like query over the filesystem
+    /// Example: "SELECT path, size FROM ~/code WHERE ext = 'rs' ORDER BY size DESC LIMIT 20"
+    Query {
+        /// The query: SELECT cols FROM dir [WHERE cond] [ORDER BY col [ASC|DESC]] [LIMIT n]
+        query: String,
+
+        /// Number of worker threads (defaults to number of CPU cores)
+        #[arg(short = 'j', long)]
+        threads: Option<usize>,
+
+        /// How to show sizes: bytes, human (powers of 1024, e.g. 1.4G) or si
+        /// (powers of 1000)
+        #[arg(long = "size-format", default_value = "bytes")]
+        size_format: SizeFormat,
+
+        /// How to show modification times: epoch (seconds), iso (local RFC 3339)
+        /// or relative ("3 days ago")
+        #[arg(long = "time-style", default_value = "epoch")]
+        time_style: TimeFormat,
+
+        /// Show modification times with a strftime format, e.g. '%Y-%m-%d %H:%M'
+        #[arg(long = "time-format", value_parser = TimeFormat::strftime, conflicts_with = "time_style")]
+        time_format: Option<TimeFormat>,
+    },
+
+    /// Report security problems below a directory
+    /// Example: rfind audit perms /srv
+    Audit {
+        #[command(subcommand)]
+        audit: Audit,
+    },
+
+    /// Print the total size of the files below each directory, largest first
+    /// Example: rfind du ~/code --depth 2
+    Du {
+        /// Directory to measure
+        #[arg(default_value = ".")]
+        root: PathBuf,
+
+        /// Only list directories down to this many levels below the root
+        /// (deeper files still count towards them)
+        #[arg(short = 'd', long)]
+        depth: Option<usize>,
+
+        /// Number of worker threads (defaults to number of CPU cores)
+        #[arg(short = 'j', long)]
+        threads: Option<usize>,
+
+        /// How to show sizes: bytes, human (powers of 1024, e.g. 1.4G) or si
+        /// (powers of 1000)
+        #[arg(long = "size-format", default_value = "human")]
+        size_format: SizeFormat,
+    },
+
+    /// Serve searches over HTTP as newline-delimited JSON
+    /// Example: curl 'http://127.0.0.1:7878/search?q=*.rs&type=f'
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:7878")]
+        listen: String,
+
+        /// Directory searched when a request does not specify one
+        #[arg(short, long, default_value = "/")]
+        dir: PathBuf,
+
+        /// Number of worker threads per search (defaults to number of CPU cores)
+        #[arg(short = 'j', long)]
+        threads: Option<usize>,
+    },
+
+    /// Serve searches over gRPC (see proto/rfind.proto)
+    #[cfg(feature = "grpc")]
+    Grpc {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:7879")]
+        listen: std::net::SocketAddr,
+
+        /// Directory searched when a request does not specify one
+        #[arg(short, long, default_value = "/")]
+        dir: PathBuf,
+
+        /// Number of worker threads per search (defaults to number of CPU cores)
+        #[arg(short = 'j', long)]
+        threads: Option<usize>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum Audit {
+    /// List world-writable entries, setuid and setgid files, entries whose
+    /// owner or group does not exist, and sticky bits on files
+    Perms {
+        /// Directory to audit
+        #[arg(default_value = "/")]
+        root: PathBuf,
+
+        /// Number of worker threads (defaults to number of CPU cores)
+        #[arg(short = 'j', long)]
+        threads: Option<usize>,
+    },
 }
 
 impl Args {
+    /// How sizes and times are shown in --csv and --json, from --size-format,
+    /// --time-style and --time-format. Plain numbers unless asked otherwise,
+    /// like `rfind query`
+    fn field_format(&self) -> FieldFormat {
+        self.format_or(SizeFormat::Bytes, TimeFormat::Epoch)
+    }
+
+    /// How sizes and times are shown next to ranked or summarized matches:
+    /// human readable unless asked otherwise
+    fn display_format(&self) -> FieldFormat {
+        self.format_or(SizeFormat::Human, TimeFormat::Iso)
+    }
+
+    fn format_or(&self, size: SizeFormat, time: TimeFormat) -> FieldFormat {
+        FieldFormat {
+            size: self.size_format.unwrap_or(size),
+            time: self
+                .time_format
+                .clone()
+                .or_else(|| self.time_style.clone())
+                .unwrap_or(time),
+        }
+    }
+
+    fn pattern_syntax(&self) -> PatternSyntax {
+        if self.regex {
+            PatternSyntax::Regex
+        } else if self.iregex {
+            PatternSyntax::RegexIgnoreCase
+        } else {
+            PatternSyntax::Glob
+        }
+    }
+
     fn symlink_mode(&self) -> SymlinkMode {
         if self.follow_all {
             SymlinkMode::Always
@@ -130,566 +749,1454 @@ impl Args {
     }
 }
 
-struct ScannerContext {
-    work: WorkUnit,
-    pattern: Arc<PatternMatcher>,
-    symlink_mode: SymlinkMode,
-    is_command_line: bool,                       // True for initial directory
-    visited_paths: Arc<Mutex<HashSet<PathBuf>>>, // For loop detection
-    root_path: PathBuf,
-    type_filter: filters::TypeFilter,
-    mtime_filter: Option<filters::TimeFilter>,
-    atime_filter: Option<filters::TimeFilter>,
-    ctime_filter: Option<filters::TimeFilter>,
-    now: SystemTime,
-    size_filter: Option<filters::SizeFilter>,
-    system_checker: Arc<SystemPathChecker>,
-}
-
-fn normalize_path(path: &Path, root: &Path) -> PathBuf {
-    if let Some(relative) = diff_paths(path, root) {
-        // Always use the root path and join with relative to preserve symlink paths
-        root.to_path_buf().join(relative)
-    } else {
-        // If diff_paths fails, return the original path
-        path.to_path_buf()
-    }
-}
-/// Represents a work unit for directory scanning
-#[derive(Debug, Clone)]
-struct WorkUnit {
-    path: PathBuf,
-    depth: usize,
-}
-
-struct ScannerChannels {
-    dir_tx: Sender<WorkUnit>,
-    result_tx: Sender<PathBuf>,
-}
-
-fn handle_directory(
-    path: PathBuf,
-    depth: usize,
-    _ctx: &ScannerContext,
-    channels: &ScannerChannels,
-) -> Result<(), Box<dyn Error>> {
-    channels.dir_tx.send(WorkUnit {
-        path,
-        depth: depth + 1,
-    })?;
-    Ok(())
-}
-
-fn should_follow_symlink(ctx: &ScannerContext, is_command_path: bool) -> bool {
-    match ctx.symlink_mode {
-        SymlinkMode::Never => false,
-        SymlinkMode::Command => is_command_path,
-        SymlinkMode::Always => true,
-    }
-}
-
-/// Checks if the file/directory/symlink should be recorded as a match
-/// based on the --type / -t filter provided by the user.
-fn is_type_match(
-    metadata: &std::fs::Metadata,
-    filter: filters::TypeFilter,
-    ctx: &ScannerContext,
-) -> bool {
-    let file_type = metadata.file_type();
-    let base_match = match filter {
-        filters::TypeFilter::Any => true,
-        filters::TypeFilter::File => file_type.is_file(),
-        filters::TypeFilter::Dir => file_type.is_dir(),
-        filters::TypeFilter::Symlink => file_type.is_symlink(),
-    };
-
-    if !base_match {
-        return false;
+/// Load the `--plugin` libraries and manifests
+fn load_plugins(args: &Args) -> Plugins {
+    let jobs = args.filter_jobs.unwrap_or_else(num_cpus::get);
+    let mut plugins = Plugins::default();
+    for plugin_path in &args.plugins {
+        plugins.load(plugin_path, jobs).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
     }
+    plugins
+}
 
-    // Apply size filter if present
-    if let Some(size_filter) = &ctx.size_filter {
-        if !size_filter.matches(metadata.len()) {
-            return false;
+/// Collect the actions to run on each match, in the order they are applied
+fn build_executor(args: &Args, plugin_actions: Vec<Action>) -> Executor {
+    let mut actions = Vec::new();
+    if let Some(command) = &args.exec {
+        actions.push(Action::exec(command).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }));
+    }
+    actions.extend(plugin_actions);
+    if let Some(rename) = &args.rename {
+        actions.push(rename.clone());
+    }
+    if let Some(dir) = &args.move_to {
+        actions.push(Action::MoveTo(dir.clone()));
+    }
+    if args.delete_empty_dirs {
+        actions.push(Action::Delete);
+    }
+    if args.delete {
+        actions.push(if args.trash {
+            Action::Trash
+        } else {
+            Action::Delete
+        });
+    }
+    let mut executor = Executor::new(actions, args.dry_run).with_exec_jobs(args.exec_jobs);
+    if args.ok {
+        executor = executor.with_confirmation();
+    }
+    if args.dry_run || !executor.is_destructive() {
+        return executor;
+    }
+    match Journal::create() {
+        Ok(journal) => executor.with_journal(journal),
+        Err(e) => {
+            eprintln!("Failed to set up the undo journal: {}", e);
+            std::process::exit(1);
         }
     }
+}
 
-    // Apply time filters
-    if let Some(mtime_filter) = &ctx.mtime_filter {
-        if !mtime_filter.matches(metadata.modified().unwrap_or(ctx.now), ctx.now) {
-            return false;
-        }
+fn parse_size_limit(s: &str) -> Result<u64, String> {
+    if s.starts_with('+') || s.starts_with('-') {
+        return Err("Size limit must not have a + or - prefix".to_string());
     }
+    filters::SizeFilter::parse(s).map(|size| size.to_bytes())
+}
 
-    if let Some(atime_filter) = &ctx.atime_filter {
-        if !atime_filter.matches(metadata.accessed().unwrap_or(ctx.now), ctx.now) {
-            return false;
-        }
+/// The matches held in `paths`, exiting if they cannot be read back
+fn buffered(paths: &mut PathBuffer) -> impl Iterator<Item = PathBuf> + '_ {
+    let iter = paths.iter().unwrap_or_else(|e| {
+        eprintln!("Failed to read back buffered matches: {}", e);
+        std::process::exit(1);
+    });
+    iter.map(|path| {
+        path.unwrap_or_else(|e| {
+            eprintln!("Failed to read back buffered matches: {}", e);
+            std::process::exit(1);
+        })
+    })
+}
+
+/// Whether `dir` is the root of the filesystem (or of a drive on Windows)
+fn is_filesystem_root(dir: &Path) -> bool {
+    std::fs::canonicalize(dir).is_ok_and(|dir| dir.parent().is_none())
+}
+
+/// Show a sample of what is about to be changed and ask whether to continue.
+/// Exits unless the user agrees; without a terminal to ask on, refuses outright.
+fn confirm_bulk_action(
+    paths: impl Iterator<Item = PathBuf>,
+    impact: &Impact,
+    limits: &SafetyLimits,
+) {
+    const PREVIEW: usize = 10;
+
+    eprintln!(
+        "About to modify {} entries ({}), above the safety limit of {} entries / {}:",
+        impact.entries,
+        format_size(impact.bytes, SizeFormat::Human),
+        limits.max_entries,
+        format_size(limits.max_bytes, SizeFormat::Human)
+    );
+    for path in paths.take(PREVIEW) {
+        eprintln!("  {}", path.display());
+    }
+    if impact.entries > PREVIEW {
+        eprintln!("  ... and {} more", impact.entries - PREVIEW);
+    }
+
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        eprintln!("Refusing to continue without confirmation; use --force to override");
+        std::process::exit(1);
+    }
+    eprint!("Proceed? [y/N] ");
+    let mut answer = String::new();
+    let _ = stdin.read_line(&mut answer);
+    if !matches!(answer.trim(), "y" | "Y" | "yes") {
+        eprintln!("Aborted");
+        std::process::exit(1);
     }
+}
+
+/// Build the combined filter from the command line flags, exiting on invalid input
+fn build_entry_filter(
+    args: &Args,
+    plugin_filters: Vec<Box<dyn filters::EntryFilter>>,
+) -> filters::AllOf {
+    // Parse time filters
+    let mtime_filter = args
+        .mtime
+        .as_deref()
+        .map(filters::TimeFilter::parse)
+        .transpose()
+        .unwrap_or_else(|e| {
+            eprintln!("Invalid mtime filter: {}", e);
+            std::process::exit(1);
+        });
+
+    let atime_filter = args
+        .atime
+        .as_deref()
+        .map(filters::TimeFilter::parse)
+        .transpose()
+        .unwrap_or_else(|e| {
+            eprintln!("Invalid atime filter: {}", e);
+            std::process::exit(1);
+        });
 
-    if let Some(ctime_filter) = &ctx.ctime_filter {
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::MetadataExt;
-            let ctime = SystemTime::UNIX_EPOCH + Duration::from_secs(metadata.ctime() as u64);
-            if !ctime_filter.matches(ctime, ctx.now) {
-                return false;
+    let ctime_filter = args
+        .ctime
+        .as_deref()
+        .map(filters::TimeFilter::parse)
+        .transpose()
+        .unwrap_or_else(|e| {
+            eprintln!("Invalid ctime filter: {}", e);
+            std::process::exit(1);
+        });
+    let parse_size = if args.find_compat {
+        filters::SizeFilter::parse_find_compat
+    } else {
+        filters::SizeFilter::parse
+    };
+    let size_filter = args
+        .size
+        .as_deref()
+        .map(parse_size)
+        .transpose()
+        .unwrap_or_else(|e| {
+            eprintln!("Invalid size filter: {}", e);
+            std::process::exit(1);
+        });
+
+    let mut entry_filter = filters::AllOf::default();
+    entry_filter.push(args.type_filter);
+    if args.delete_empty_dirs {
+        entry_filter.push(filters::TypeFilter::Dir);
+    }
+    if args.empty || args.delete_empty_dirs {
+        entry_filter.push(filters::EmptyFilter);
+    }
+    if args.broken_symlinks {
+        entry_filter.push(filters::BrokenSymlinkFilter);
+    }
+    if let Some(pattern) = &args.lname {
+        let filter = filters::LinkTargetFilter::new(pattern).unwrap_or_else(|e| {
+            eprintln!("Invalid --lname pattern {}", e);
+            std::process::exit(1);
+        });
+        entry_filter.push(filter);
+    }
+    if args.only_local {
+        entry_filter.push(filters::CloudFilter::Local);
+    }
+    if args.only_cloud {
+        entry_filter.push(filters::CloudFilter::Cloud);
+    }
+    if let Some(size_filter) = size_filter {
+        entry_filter.push(size_filter);
+    }
+    for (field, time_filter) in [
+        (filters::TimeField::Modified, mtime_filter),
+        (filters::TimeField::Accessed, atime_filter),
+        (filters::TimeField::Changed, ctime_filter),
+    ] {
+        if let Some(mut time_filter) = time_filter {
+            if args.daystart {
+                time_filter = time_filter.measured_from_daystart();
             }
+            entry_filter.push(filters::TimestampFilter::new(field, time_filter));
         }
-        #[cfg(not(unix))]
-        {
-            // Fall back to mtime on non-Unix systems
-            if !ctime_filter.matches(metadata.modified().unwrap_or(ctx.now), ctx.now) {
-                return false;
-            }
+    }
+
+    if let Some(reference) = &args.newer {
+        let reference = filters::reference_time(reference, filters::TimeField::Modified)
+            .unwrap_or_else(|e| {
+                eprintln!("Invalid --newer reference: {}", e);
+                std::process::exit(1);
+            });
+        entry_filter.push(filters::NewerFilter::new(
+            filters::TimeField::Modified,
+            reference,
+        ));
+    }
+    for pair in args.newer_xy.chunks(2) {
+        let filter = filters::NewerFilter::parse(&pair[0], &pair[1]).unwrap_or_else(|e| {
+            eprintln!("Invalid --newer-xy filter: {}", e);
+            std::process::exit(1);
+        });
+        entry_filter.push(filter);
+    }
+
+    if args.preset.iter().any(|name| name == presets::SECURITY) {
+        entry_filter.push(filters::from_fn(|entry: &filters::EntryInfo| {
+            !permissions::issues(entry.metadata).is_empty()
+        }));
+    }
+    let groups: Vec<&String> = args
+        .preset
+        .iter()
+        .filter(|name| *name != presets::SECURITY)
+        .collect();
+    if !groups.is_empty() {
+        let config = load_config("--preset");
+        let mut extensions = Vec::new();
+        for name in groups {
+            extensions.extend(config.preset_extensions(name).unwrap_or_else(|e| {
+                eprintln!("Cannot apply --preset: {}", e);
+                std::process::exit(1);
+            }));
         }
+        entry_filter.push(filters::ExtensionFilter::new(extensions));
     }
 
-    true
-}
+    if !args.has_file.is_empty() {
+        let has_file = filters::HasFileFilter::new(args.has_file.clone()).unwrap_or_else(|e| {
+            eprintln!("Invalid --has-file: {}", e);
+            std::process::exit(1);
+        });
+        entry_filter.push(has_file);
+    }
 
-fn handle_symlink(
-    path: &Path,
-    _file_type: std::fs::FileType,
-    ctx: &ScannerContext,
-    channels: &ScannerChannels,
-) -> Result<bool, Box<dyn Error>> {
-    if !should_follow_symlink(ctx, ctx.is_command_line) {
-        return Ok(false);
+    if let Some(expr) = &args.where_expr {
+        let where_filter = filters::WhereFilter::parse(expr).unwrap_or_else(|e| {
+            eprintln!("Invalid where filter: {}", e);
+            std::process::exit(1);
+        });
+        entry_filter.push(where_filter);
     }
 
-    // Keep the original symlink path for directory traversal
-    let symlink_path = path.to_path_buf();
+    entry_filter.0.extend(plugin_filters);
 
-    // Check for symlink loops using canonical paths
-    let canonical = path.canonicalize().ok();
-    if let Some(canonical_path) = canonical {
-        let mut visited = ctx.visited_paths.lock();
-        if !visited.insert(canonical_path) {
-            return Ok(false);
-        }
+    #[cfg(feature = "wasm")]
+    for module_path in &args.wasm_filters {
+        let module = rfind::wasm::WasmFilter::load(module_path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        entry_filter.push(module);
+    }
+    #[cfg(not(feature = "wasm"))]
+    if let Some(module_path) = args.wasm_filters.first() {
+        eprintln!(
+            "Cannot load {}: rfind was built without the `wasm` feature",
+            module_path.display()
+        );
+        std::process::exit(1);
     }
 
-    match std::fs::metadata(&symlink_path) {
-        Ok(metadata) => {
-            if metadata.is_dir() {
-                // Use the original symlink path for directory traversal
-                handle_directory(symlink_path, ctx.work.depth, ctx, channels)?;
-                Ok(false)
-            } else {
-                Ok(metadata.is_file())
-            }
+    // Spawning a process is the most expensive check, so it comes last
+    if let Some(command) = &args.filter_cmd {
+        let jobs = args.filter_jobs.unwrap_or_else(num_cpus::get);
+        let command_filter = filters::CommandFilter::parse(command, jobs).unwrap_or_else(|e| {
+            eprintln!("Invalid --filter-cmd: {}", e);
+            std::process::exit(1);
+        });
+        entry_filter.push(command_filter);
+    }
+
+    entry_filter
+}
+
+/// Shells supported by `rfind init`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    fn init_script(self) -> &'static str {
+        match self {
+            Shell::Bash => include_str!("../shell/rfind.bash"),
+            Shell::Zsh => include_str!("../shell/rfind.zsh"),
+            Shell::Fish => include_str!("../shell/rfind.fish"),
         }
-        Err(_) => Ok(false),
     }
 }
 
-struct ScannerConfig {
-    work_rx: Receiver<WorkUnit>,
-    dir_tx: Sender<WorkUnit>,
-    result_tx: Sender<PathBuf>,
-    pattern: Arc<PatternMatcher>,
-    active_scanners: Arc<AtomicUsize>,
-    max_depth: usize,
-    symlink_mode: SymlinkMode,
-    root_path: PathBuf,
-    type_filter: filters::TypeFilter,
-    mtime_filter: Option<filters::TimeFilter>,
-    atime_filter: Option<filters::TimeFilter>,
-    ctime_filter: Option<filters::TimeFilter>,
-    now: SystemTime,
-    size_filter: Option<filters::SizeFilter>,
-    system_checker: Arc<SystemPathChecker>,
+/// When `--strip-cwd-prefix` leaves out the leading `./`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum StripCwdPrefix {
+    Auto,
+    Always,
+    Never,
 }
 
-fn spawn_scanner_thread(config: ScannerConfig) -> thread::JoinHandle<()> {
-    let visited_paths = Arc::new(Mutex::new(HashSet::with_capacity(1000)));
+/// The patterns of a search, to tell which one found a match (--show-pattern)
+struct PatternLabels(Vec<(String, PatternMatcher)>);
 
-    thread::spawn(move || {
-        let channels = ScannerChannels {
-            dir_tx: config.dir_tx,
-            result_tx: config.result_tx,
-        };
+impl PatternLabels {
+    /// Labels for `patterns`, which have been checked to be valid in `syntax`
+    fn new(patterns: &[String], syntax: PatternSyntax) -> Self {
+        PatternLabels(
+            patterns
+                .iter()
+                .filter_map(|pattern| Some((pattern.clone(), syntax.matcher(pattern).ok()?)))
+                .collect(),
+        )
+    }
 
-        while let Ok(work) = config.work_rx.recv() {
-            config.active_scanners.fetch_add(1, Ordering::SeqCst);
+    /// The first pattern matching the name of `path`
+    fn matching(&self, path: &Path) -> Option<&str> {
+        let name = path.file_name()?.to_str()?;
+        self.0
+            .iter()
+            .find(|(_, matcher)| matcher.matches(name))
+            .map(|(pattern, _)| pattern.as_str())
+    }
+}
 
-            if work.depth > config.max_depth {
-                config.active_scanners.fetch_sub(1, Ordering::SeqCst);
-                continue;
-            }
+/// Turns absolute matches into the paths that get printed
+struct PathDisplay {
+    /// Current directory, and whether to prefix paths below it with `./`
+    relative_to: Option<(PathBuf, bool)>,
+}
 
-            let ctx = ScannerContext {
-                work: work.clone(),
-                pattern: Arc::clone(&config.pattern),
-                symlink_mode: config.symlink_mode,
-                is_command_line: work.depth == 0,
-                visited_paths: Arc::clone(&visited_paths),
-                root_path: config.root_path.clone(),
-                type_filter: config.type_filter,
-                mtime_filter: config.mtime_filter.clone(),
-                atime_filter: config.atime_filter.clone(),
-                ctime_filter: config.ctime_filter.clone(),
-                now: config.now,
-                size_filter: config.size_filter.clone(),
-                system_checker: Arc::clone(&config.system_checker),
+impl PathDisplay {
+    fn new(args: &Args) -> Self {
+        let relative_to = args.strip_cwd_prefix.and_then(|mode| {
+            let dot_prefix = match mode {
+                StripCwdPrefix::Always => false,
+                StripCwdPrefix::Never => true,
+                // A leading ./ keeps names starting with - from being read as options
+                StripCwdPrefix::Auto => args.print0,
             };
+            let cwd = std::env::current_dir().ok()?;
+            Some((cwd.canonicalize().unwrap_or(cwd), dot_prefix))
+        });
+        PathDisplay { relative_to }
+    }
 
-            // More defensive read_dir handling
-            let read_dir = match std::fs::read_dir(&work.path) {
-                Ok(dir) => dir,
-                Err(e) => {
-                    debug!("Failed to read directory {:?}: {}", work.path, e);
-                    config.active_scanners.fetch_sub(1, Ordering::SeqCst);
-                    continue;
-                }
-            };
+    /// The path as printed, followed by the target of a symlink if asked for
+    fn show_match(&self, path: &Path, list_targets: bool) -> String {
+        let shown = self.show(path);
+        if !list_targets {
+            return shown;
+        }
+        match std::fs::read_link(path) {
+            Ok(target) => format!("{} -> {}", shown, target.display()),
+            Err(_) => shown,
+        }
+    }
+
+    /// The match as a JSON object
+    fn json(&self, path: &Path, list_targets: bool) -> serde_json::Value {
+        let mut object = json!({ "path": self.show(path) });
+        if let (true, Ok(target)) = (list_targets, std::fs::read_link(path)) {
+            object["target"] = json!(target.to_string_lossy());
+        }
+        object
+    }
 
-            for entry in read_dir.filter_map(|e| e.ok()) {
-                if let Err(e) = handle_entry(entry, &ctx, &channels) {
-                    debug!("Error processing entry: {}", e);
+    fn show(&self, path: &Path) -> String {
+        if let Some((cwd, dot_prefix)) = &self.relative_to {
+            if let Ok(relative) = path.strip_prefix(cwd) {
+                if relative.as_os_str().is_empty() {
+                    return ".".to_string();
                 }
+                let prefix = if *dot_prefix { "./" } else { "" };
+                return format!("{}{}", prefix, relative.display());
             }
+        }
+        path.display().to_string()
+    }
+}
 
-            config.active_scanners.fetch_sub(1, Ordering::SeqCst);
+/// The matches kept for --largest, --newest or --oldest
+/// The metadata a match was filtered by: that of a symlink's target with
+/// -L, like in the scanner, and of the entry itself otherwise or when the
+/// link is broken
+fn match_metadata(path: &Path, args: &Args) -> std::io::Result<std::fs::Metadata> {
+    if args.follow_all {
+        if let Ok(metadata) = std::fs::metadata(path) {
+            return Ok(metadata);
         }
-    })
+    }
+    std::fs::symlink_metadata(path)
+}
+
+enum Ranking {
+    Largest(TopN<u64>),
+    Newest(TopN<SystemTime>),
+    Oldest(TopN<Reverse<SystemTime>>),
 }
 
-struct ThreadPool {
-    scanner_handles: Vec<thread::JoinHandle<()>>,
-    distributor_handle: thread::JoinHandle<()>,
-    result_receiver: Receiver<PathBuf>,
-}
-
-struct ChannelSet {
-    work_tx: Sender<WorkUnit>,
-    work_rx: Receiver<WorkUnit>,
-    result_tx: Sender<PathBuf>,
-    result_rx: Receiver<PathBuf>,
-    dir_tx: Sender<WorkUnit>,
-    dir_rx: Receiver<WorkUnit>,
-}
-
-fn create_channels(thread_count: usize) -> ChannelSet {
-    let (work_tx, work_rx) = bounded(thread_count * 8);
-    let (result_tx, result_rx) = unbounded();
-    let (dir_tx, dir_rx) = unbounded();
-
-    ChannelSet {
-        work_tx,
-        work_rx,
-        result_tx,
-        result_rx,
-        dir_tx,
-        dir_rx,
-    }
-}
-
-fn spawn_work_distributor(
-    work_tx: Sender<WorkUnit>,
-    dir_rx: Receiver<WorkUnit>,
-    active_scanners: Arc<AtomicUsize>,
-) -> thread::JoinHandle<()> {
-    thread::spawn(move || {
-        let mut pending_dirs = HashSet::new();
-        pending_dirs.insert(String::from("initial"));
-
-        let mut empty_reads = 0;
-        const MAX_EMPTY_READS: u8 = 3;
-
-        loop {
-            match dir_rx.try_recv() {
-                Ok(dir) => {
-                    empty_reads = 0;
-                    pending_dirs.insert(dir.path.to_string_lossy().to_string());
-                    if work_tx.send(dir).is_err() {
-                        break;
-                    }
+impl Ranking {
+    fn new(args: &Args) -> Option<Self> {
+        if let Some(n) = args.largest {
+            Some(Ranking::Largest(TopN::new(n)))
+        } else if let Some(n) = args.newest {
+            Some(Ranking::Newest(TopN::new(n)))
+        } else {
+            args.oldest.map(|n| Ranking::Oldest(TopN::new(n)))
+        }
+    }
+
+    fn push(&mut self, path: PathBuf, metadata: &std::fs::Metadata) {
+        match self {
+            Ranking::Largest(top) => top.push(metadata.len(), path),
+            Ranking::Newest(top) => {
+                if let Ok(mtime) = metadata.modified() {
+                    top.push(mtime, path);
                 }
-                Err(crossbeam_channel::TryRecvError::Empty) => {
-                    empty_reads += 1;
-                    if empty_reads >= MAX_EMPTY_READS
-                        && active_scanners.load(Ordering::SeqCst) == 0
-                        && dir_rx.is_empty()
-                    {
-                        break;
-                    }
-                    thread::sleep(std::time::Duration::from_micros(100));
+            }
+            Ranking::Oldest(top) => {
+                if let Ok(mtime) = metadata.modified() {
+                    top.push(Reverse(mtime), path);
                 }
-                Err(crossbeam_channel::TryRecvError::Disconnected) => break,
             }
         }
-    })
-}
+    }
 
-struct ThreadPoolOptions {
-    thread_count: usize,
-    pattern: Arc<PatternMatcher>,
-    channels: ChannelSet,
-    max_depth: usize,
-    symlink_mode: SymlinkMode,
-    root_path: PathBuf,
-    type_filter: filters::TypeFilter,
-    mtime_filter: Option<filters::TimeFilter>,
-    atime_filter: Option<filters::TimeFilter>,
-    ctime_filter: Option<filters::TimeFilter>,
-    now: SystemTime,
-    size_filter: Option<filters::SizeFilter>,
-}
-
-#[derive(Default)]
-struct SystemPathChecker {
-    system_paths: Vec<PathBuf>,
-}
-
-impl SystemPathChecker {
-    fn new() -> Self {
-        #[cfg(test)]
-        return SystemPathChecker::default();
-
-        let mut checker = SystemPathChecker::default();
-
-        #[cfg(target_os = "macos")]
-        {
-            checker.system_paths.extend_from_slice(&[
-                PathBuf::from("/System"),
-                PathBuf::from("/Library"),
-                PathBuf::from("/private"),
-                PathBuf::from("/Volumes"),
-            ]);
-        }
-
-        #[cfg(target_os = "linux")]
-        {
-            checker.system_paths.extend_from_slice(&[
-                PathBuf::from("/proc"),
-                PathBuf::from("/sys"),
-                PathBuf::from("/dev"),
-                PathBuf::from("/run"),
-                PathBuf::from("/private"),
-            ]);
-        }
-
-        #[cfg(target_os = "windows")]
-        {
-            checker.system_paths.extend_from_slice(&[
-                PathBuf::from("C:\\Windows"),
-                PathBuf::from("C:\\Program Files\\Windows"),
-                PathBuf::from("C:\\ProgramData\\Microsoft"),
-                PathBuf::from("C:\\System Volume Information"),
-            ]);
-        }
-
-        checker
-    }
-
-    #[inline]
-    fn is_system_path(&self, path: &Path) -> bool {
-        // Case-insensitive check for Windows paths
-        #[cfg(target_os = "windows")]
-        {
-            let path_str = path.to_string_lossy().to_lowercase();
-            self.system_paths.iter().any(|sys_path| {
-                path_str.starts_with(&sys_path.to_string_lossy().to_lowercase())
-                    || path_str.contains("\\system32")
-                    || path_str.contains("\\syswow64")
-            })
+    /// Print the kept matches, each after the value it was ranked by
+    fn print(self, path_display: &PathDisplay, args: &Args) {
+        let now = SystemTime::now();
+        let format = args.display_format();
+        let rows: Vec<(String, PathBuf)> = match self {
+            Ranking::Largest(top) => top
+                .into_sorted_vec()
+                .into_iter()
+                .map(|(size, path)| (format_size(size, format.size), path))
+                .collect(),
+            Ranking::Newest(top) => top
+                .into_sorted_vec()
+                .into_iter()
+                .map(|(mtime, path)| (format_time(mtime, now, &format.time), path))
+                .collect(),
+            Ranking::Oldest(top) => top
+                .into_sorted_vec()
+                .into_iter()
+                .map(|(Reverse(mtime), path)| (format_time(mtime, now, &format.time), path))
+                .collect(),
+        };
+        for (value, path) in rows {
+            if args.print0 {
+                print!("{}\t{}\0", value, path_display.show(&path));
+            } else {
+                println!("{}\t{}", value, path_display.show(&path).green());
+            }
         }
+    }
+}
 
-        // Case-sensitive check for Unix-like systems
-        #[cfg(not(target_os = "windows"))]
-        {
-            self.system_paths
-                .iter()
-                .any(|sys_path| path.starts_with(sys_path))
+/// The fields printed by --csv, and by --json when --fields is given
+fn structured_fields(args: &Args) -> Vec<Field> {
+    if !args.fields.is_empty() {
+        return args.fields.clone();
+    }
+    let mut fields = vec![Field::Path];
+    if args.list_targets {
+        fields.push(Field::Target);
+    }
+    fields
+}
+
+/// Print a match in the output format asked for. With `security`, the match
+/// is labelled with the most serious issue's severity and every rule it broke.
+fn print_match(
+    path: &Path,
+    path_display: &PathDisplay,
+    args: &Args,
+    security: bool,
+    pattern_labels: Option<&PatternLabels>,
+) {
+    let issues = if security {
+        std::fs::symlink_metadata(path)
+            .map(|metadata| permissions::issues(&metadata))
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let label = permissions::max_severity(&issues).map(|severity| {
+        let rules: Vec<&str> = issues.iter().map(|issue| issue.name()).collect();
+        (severity, rules)
+    });
+    let pattern = pattern_labels.and_then(|labels| labels.matching(path));
+
+    if args.csv {
+        let fields = structured_fields(args);
+        let mut record = fields::csv_record(
+            &fields,
+            path,
+            &path_display.show(path),
+            &args.field_format(),
+        );
+        // Matching the columns added to the header
+        if security {
+            let (severity, rules) = label
+                .map(|(severity, rules)| (severity.to_string(), rules.join(",")))
+                .unwrap_or_default();
+            record.push(',');
+            record.push_str(&fields::csv_line(&[severity, rules]));
         }
+        println!("{}", record);
+        return;
+    }
+    if args.json {
+        let mut object = if args.fields.is_empty() {
+            path_display.json(path, args.list_targets)
+        } else {
+            fields::json_object(
+                &args.fields,
+                path,
+                &path_display.show(path),
+                &args.field_format(),
+            )
+        };
+        if let Some(pattern) = pattern {
+            object["pattern"] = json!(pattern);
+        }
+        if let Some((severity, rules)) = &label {
+            object["severity"] = json!(severity.to_string());
+            object["rules"] = json!(rules);
+        }
+        println!("{}", object);
+        return;
+    }
+    let shown = path_display.show_match(path, args.list_targets);
+    let mut prefix = pattern
+        .map(|pattern| format!("{}\t", pattern))
+        .unwrap_or_default();
+    if let Some((severity, rules)) = label {
+        prefix.push_str(&format!("{}\t{}\t", severity, rules.join(",")));
+    }
+    if args.print0 {
+        print!("{}{}\0", prefix, shown);
+        std::io::stdout().flush().expect("Failed to flush stdout");
+    } else {
+        println!("{}{}", prefix, shown.green());
     }
 }
 
-// Update handle_entry function to use SystemPathChecker
-fn handle_entry(
-    entry: std::fs::DirEntry,
-    ctx: &ScannerContext,
-    channels: &ScannerChannels,
-) -> Result<(), Box<dyn Error>> {
-    let path = entry.path();
+fn print_extension_summary(summary: &ExtensionSummary, size_format: SizeFormat) {
+    let rows = summary.rows();
+    let width = rows
+        .iter()
+        .map(|(ext, _)| ext.len())
+        .chain(std::iter::once("EXTENSION".len()))
+        .max()
+        .unwrap_or_default();
+    println!(
+        "{:<width$}  {:>8}  {:>10}",
+        "EXTENSION",
+        "COUNT",
+        "SIZE",
+        width = width
+    );
+    for (ext, totals) in rows {
+        println!(
+            "{:<width$}  {:>8}  {:>10}",
+            ext,
+            totals.count,
+            format_size(totals.bytes, size_format),
+            width = width
+        );
+    }
+}
 
-    // Skip system paths early
-    if ctx.system_checker.is_system_path(&path) {
-        debug!("Skipping system path: {:?}", path);
-        return Ok(());
+/// Output format for log events
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    /// Human readable lines
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+fn init_logging(format: LogFormat) {
+    let mut builder = env_logger::Builder::from_env(Env::default().default_filter_or("error"));
+    if format == LogFormat::Json {
+        builder.format(|buf, record| {
+            let event = json!({
+                "level": record.level().as_str().to_lowercase(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+            writeln!(buf, "{}", event)
+        });
     }
+    builder.init();
+}
 
-    let metadata = entry.metadata()?;
-    let relative_path = normalize_path(&path, &ctx.root_path);
+/// Structured form of a scan error: deliberate skips are informational,
+/// anything else is a warning
+fn scan_error_event(error: &ScanError) -> serde_json::Value {
+    let level = match error.operation {
+        ScanOperation::Skip | ScanOperation::LargeDirectory => "info",
+        _ => "warn",
+    };
+    json!({
+        "level": level,
+        "target": "rfind::scanner",
+        "operation": error.operation.to_string(),
+        "path": error.path.to_string_lossy(),
+        "error": error.message,
+        "retries": error.retries,
+    })
+}
 
-    // Rest of the original handle_entry logic remains the same...
-    if metadata.file_type().is_symlink() {
-        if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-            if ctx.pattern.matches(file_name) && is_type_match(&metadata, ctx.type_filter, ctx) {
-                channels.result_tx.send(relative_path.clone())?;
-            }
-        }
+/// Return a handler writing each scan error to `output` as a line of the given format
+fn error_writer(output: impl Write + Send + 'static, format: LogFormat) -> ErrorHandler {
+    let output = Mutex::new(LineWriter::new(output));
+    Arc::new(move |error: &ScanError| {
+        let _ = match format {
+            LogFormat::Text => writeln!(output.lock(), "{}", error),
+            LogFormat::Json => writeln!(output.lock(), "{}", scan_error_event(error)),
+        };
+    })
+}
 
-        match handle_symlink(&path, metadata.file_type(), ctx, channels) {
-            Ok(_) => (),
-            Err(e) => debug!("Error handling symlink {:?}: {}", path, e),
+/// Decide where scan errors go: the --error-log file if given, otherwise
+/// stderr for JSON logging, and nowhere for plain text output
+fn scan_error_handler(args: &Args) -> Option<ErrorHandler> {
+    match (&args.error_log, args.log_format) {
+        (Some(path), format) => {
+            let file = File::create(path).unwrap_or_else(|e| {
+                eprintln!("Failed to open error log {}: {}", path.display(), e);
+                std::process::exit(1);
+            });
+            Some(error_writer(file, format))
         }
-        return Ok(());
+        (None, LogFormat::Json) => Some(error_writer(std::io::stderr(), LogFormat::Json)),
+        (None, LogFormat::Text) => None,
     }
+}
 
-    if metadata.file_type().is_dir() {
-        handle_directory(path.clone(), ctx.work.depth, ctx, channels)?;
+fn run_undo(run_id: &str) {
+    let entries = journal::journal_dir()
+        .and_then(|dir| journal::load(&dir, run_id))
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to read journal for run {}: {}", run_id, e);
+            std::process::exit(1);
+        });
 
-        if is_type_match(&metadata, ctx.type_filter, ctx) {
-            if let Some(dir_name) = path.file_name().and_then(|n| n.to_str()) {
-                if ctx.pattern.matches(dir_name) {
-                    channels.result_tx.send(relative_path)?;
-                }
-            }
-        }
-    } else if metadata.file_type().is_file() {
-        if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-            if ctx.pattern.matches(file_name) && is_type_match(&metadata, ctx.type_filter, ctx) {
-                channels.result_tx.send(relative_path)?;
+    // Newest first, so chained renames and moves unwind in order
+    let mut failures = 0;
+    for entry in entries.iter().rev() {
+        match journal::undo(entry) {
+            Ok(()) => println!("Restored {}", entry.path.display()),
+            Err(e) => {
+                eprintln!("Cannot restore {}: {}", entry.path.display(), e);
+                failures += 1;
             }
         }
     }
-
-    Ok(())
-}
-
-// Update setup_thread_pool to include SystemPathChecker
-fn setup_thread_pool(pool_options: ThreadPoolOptions) -> ThreadPool {
-    let active_scanners = Arc::new(AtomicUsize::new(0));
-    let system_checker = Arc::new(SystemPathChecker::new());
-    let mut scanner_handles = Vec::with_capacity(pool_options.thread_count);
-
-    for _ in 0..pool_options.thread_count {
-        let scanner_config = ScannerConfig {
-            work_rx: pool_options.channels.work_rx.clone(),
-            dir_tx: pool_options.channels.dir_tx.clone(),
-            result_tx: pool_options.channels.result_tx.clone(),
-            pattern: Arc::clone(&pool_options.pattern),
-            active_scanners: Arc::clone(&active_scanners),
-            max_depth: pool_options.max_depth,
-            symlink_mode: pool_options.symlink_mode,
-            root_path: pool_options.root_path.clone(),
-            type_filter: pool_options.type_filter,
-            mtime_filter: pool_options.mtime_filter.clone(),
-            atime_filter: pool_options.atime_filter.clone(),
-            ctime_filter: pool_options.ctime_filter.clone(),
-            now: pool_options.now,
-            size_filter: pool_options.size_filter.clone(),
-            system_checker: Arc::clone(&system_checker),
-        };
-        scanner_handles.push(spawn_scanner_thread(scanner_config));
+    if failures > 0 {
+        std::process::exit(1);
     }
+}
 
-    // Rest of the setup_thread_pool implementation remains the same...
-    ThreadPool {
-        scanner_handles,
-        distributor_handle: spawn_work_distributor(
-            pool_options.channels.work_tx,
-            pool_options.channels.dir_rx,
-            active_scanners,
-        ),
-        result_receiver: pool_options.channels.result_rx,
+/// Parse search arguments the way they would be parsed on the command line,
+/// after the defaults from RFIND_OPTS and the options of any --profile
+fn parse_search_args(search_args: &[String]) -> Result<Args, clap::Error> {
+    let defaults = default_opts();
+    let parse = |profile_args: &[String]| {
+        Args::try_parse_from(
+            std::iter::once("rfind")
+                .chain(defaults.iter().map(String::as_str))
+                .chain(profile_args.iter().map(String::as_str))
+                .chain(search_args.iter().map(String::as_str)),
+        )
+    };
+    let args = parse(&[])?;
+    if args.command.is_some() {
+        return Err(clap::Error::raw(
+            clap::error::ErrorKind::InvalidSubcommand,
+            "Saved searches and RFIND_OPTS cannot run subcommands\n",
+        ));
+    }
+    if args.profile.is_empty() {
+        return Ok(args);
     }
+    parse(&profile_args(&args.profile))
 }
 
-fn main() {
-    let args = Args::parse();
-
-    // Parse time filters
-    let mtime_filter = args
-        .mtime
-        .as_deref()
-        .map(filters::TimeFilter::parse)
-        .transpose()
+/// The options of the named profiles, from the config file
+fn profile_args(names: &[String]) -> Vec<String> {
+    load_config("--profile")
+        .profile_args(names)
         .unwrap_or_else(|e| {
-            eprintln!("Invalid mtime filter: {}", e);
+            eprintln!("Cannot apply --profile: {}", e);
             std::process::exit(1);
-        });
+        })
+}
 
-    let atime_filter = args
-        .atime
-        .as_deref()
-        .map(filters::TimeFilter::parse)
-        .transpose()
+/// Read the config file, exiting with an error naming the option that needed it
+fn load_config(option: &str) -> Config {
+    config::config_file()
+        .map_err(|e| e.to_string())
+        .and_then(|path| Config::load(&path))
         .unwrap_or_else(|e| {
-            eprintln!("Invalid atime filter: {}", e);
+            eprintln!("Cannot apply {}: {}", option, e);
             std::process::exit(1);
-        });
+        })
+}
 
-    let ctime_filter = args
-        .ctime
-        .as_deref()
-        .map(filters::TimeFilter::parse)
-        .transpose()
-        .unwrap_or_else(|e| {
-            eprintln!("Invalid ctime filter: {}", e);
+/// Personal default options from RFIND_OPTS, split like a shell would
+fn default_opts() -> Vec<String> {
+    let opts = match std::env::var("RFIND_OPTS") {
+        Ok(opts) => opts,
+        Err(_) => return Vec::new(),
+    };
+    shell_words::split(&opts).unwrap_or_else(|e| {
+        eprintln!("Invalid RFIND_OPTS: {}", e);
+        std::process::exit(1);
+    })
+}
+
+fn saved_searches_file() -> PathBuf {
+    saved::searches_file().unwrap_or_else(|e| {
+        eprintln!("Failed to locate saved searches: {}", e);
+        std::process::exit(1);
+    })
+}
+
+fn run_save_search(name: &str, search_args: &[String]) {
+    // Catch mistakes now rather than when the search is run
+    if let Err(e) = parse_search_args(search_args) {
+        e.exit();
+    }
+    if let Err(e) = saved::save(&saved_searches_file(), name, search_args) {
+        eprintln!("Failed to save search '{}': {}", name, e);
+        std::process::exit(1);
+    }
+    println!("Saved search '{}' (run it with: rfind run {})", name, name);
+}
+
+fn list_saved_searches() {
+    let searches = saved::load(&saved_searches_file()).unwrap_or_else(|e| {
+        eprintln!("Failed to read saved searches: {}", e);
+        std::process::exit(1);
+    });
+    for (name, search_args) in searches {
+        let quoted: Vec<String> = search_args.iter().map(|arg| shell_quote(arg)).collect();
+        println!("{}\t{}", name, quoted.join(" "));
+    }
+}
+
+/// The arguments saved as `name`, followed by `extra`
+fn saved_search_args(name: &str, extra: &[String]) -> Vec<String> {
+    let searches = saved::load(&saved_searches_file()).unwrap_or_else(|e| {
+        eprintln!("Failed to read saved searches: {}", e);
+        std::process::exit(1);
+    });
+    let mut search_args = match searches.get(name) {
+        Some(search_args) => search_args.clone(),
+        None => {
+            eprintln!("No saved search named '{}'", name);
             std::process::exit(1);
-        });
-    let size_filter = args
-        .size
-        .as_deref()
-        .map(filters::SizeFilter::parse)
-        .transpose()
+        }
+    };
+    search_args.extend_from_slice(extra);
+    search_args
+}
+
+fn load_history() -> Vec<HistoryEntry> {
+    history::history_file()
+        .and_then(|path| history::load(&path))
         .unwrap_or_else(|e| {
-            eprintln!("Invalid size filter: {}", e);
+            eprintln!("Failed to read search history: {}", e);
+            std::process::exit(1);
+        })
+}
+
+fn run_history(limit: usize) {
+    let entries = load_history();
+    if entries.is_empty() && !history::is_enabled() {
+        eprintln!(
+            "No searches recorded; set {}=1 to record them",
+            history::HISTORY_ENV
+        );
+        return;
+    }
+    let skip = entries.len().saturating_sub(limit);
+    for (number, entry) in entries.iter().enumerate().skip(skip) {
+        let when = UNIX_EPOCH + Duration::from_secs(entry.timestamp);
+        let quoted: Vec<String> = entry.args.iter().map(|arg| shell_quote(arg)).collect();
+        println!(
+            "{:>4}  {}  {} result(s) in {}  rfind {}",
+            number + 1,
+            humantime::format_rfc3339_seconds(when),
+            entry.results,
+            humantime::format_duration(Duration::from_millis(entry.duration_ms)),
+            quoted.join(" ")
+        );
+    }
+}
+
+/// The arguments of search `number` in the history, after switching to the
+/// directory it was run from
+fn history_args(number: usize) -> Vec<String> {
+    let entries = load_history();
+    let entry = match number.checked_sub(1).and_then(|index| entries.get(index)) {
+        Some(entry) => entry,
+        None => {
+            eprintln!("No search number {} in the history", number);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = std::env::set_current_dir(&entry.cwd) {
+        eprintln!("Cannot change to {}: {}", entry.cwd.display(), e);
+        std::process::exit(1);
+    }
+    entry.args.clone()
+}
+
+fn run_jump(keywords: &[String], add: Option<&Path>, list: bool) {
+    let path = jump::database_file().unwrap_or_else(|e| {
+        eprintln!("Failed to locate the jump database: {}", e);
+        std::process::exit(1);
+    });
+    let mut database = JumpDatabase::load(&path).unwrap_or_else(|e| {
+        eprintln!("Failed to read the jump database: {}", e);
+        std::process::exit(1);
+    });
+
+    if let Some(dir) = add {
+        let dir = dir.canonicalize().unwrap_or_else(|e| {
+            eprintln!("Cannot add {}: {}", dir.display(), e);
             std::process::exit(1);
         });
-    let pattern = Arc::new(create_pattern_matcher(&args.pattern));
-    let thread_count = args.threads.unwrap_or_else(num_cpus::get);
-    let symlink_mode = args.symlink_mode();
+        database.add(&dir);
+        if let Err(e) = database.save(&path) {
+            eprintln!("Failed to save the jump database: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    let channels = create_channels(thread_count);
+    let matches = database.query(keywords);
+    if matches.is_empty() {
+        eprintln!("No visited directory matches");
+        std::process::exit(1);
+    }
+    let shown = if list { matches.len() } else { 1 };
+    for dir in &matches[..shown] {
+        println!("{}", dir.display());
+    }
+}
 
-    // Keep original path for normalization
-    let root_path = args.dir.clone();
+fn run_verify_manifest(path: &Path, search: Search, max_filesize: Option<u64>) {
+    let entries = manifest::load(path).unwrap_or_else(|e| {
+        eprintln!("Failed to read manifest {}: {}", path.display(), e);
+        std::process::exit(1);
+    });
+    let drift = manifest::verify(entries, search, max_filesize);
+    for difference in &drift {
+        println!("{}", difference);
+    }
+    if !drift.is_empty() {
+        std::process::exit(1);
+    }
+}
 
-    // Use canonicalized path for actual filesystem operations
-    let work_path = std::fs::canonicalize(&args.dir).unwrap_or_else(|_| args.dir.clone());
+fn run_query(query: &str, threads: Option<usize>, format: &FieldFormat) {
+    let query = Query::parse(query).unwrap_or_else(|e| {
+        eprintln!("Invalid query: {}", e);
+        std::process::exit(1);
+    });
+    let mut options = query.search_options().unwrap_or_else(|e| {
+        eprintln!("Invalid query: {}", e);
+        std::process::exit(1);
+    });
+    if let Some(threads) = threads {
+        options.threads = threads;
+    }
 
-    // Submit initial work unit with the canonicalized path
-    channels
-        .work_tx
-        .send(WorkUnit {
-            path: work_path,
-            depth: 0,
-        })
-        .expect("Failed to send initial work");
+    let search = Search::start(options).unwrap_or_else(|e| {
+        eprintln!("Invalid query: {}", e);
+        std::process::exit(1);
+    });
+    for row in query.rows(search) {
+        println!("{}", query.format_row(&row, format));
+    }
+}
 
-    let thread_pool = setup_thread_pool(ThreadPoolOptions {
-        thread_count,
+fn run_audit_perms(root: &Path, threads: Option<usize>) {
+    let mut options = SearchOptions::new("", root);
+    if let Some(threads) = threads {
+        options.threads = threads;
+    }
+    options.filter = Arc::new(filters::from_fn(|entry: &filters::EntryInfo| {
+        !permissions::issues(entry.metadata).is_empty()
+    }));
+
+    let mut found: BTreeMap<PermissionIssue, Vec<PathBuf>> = BTreeMap::new();
+    for path in Search::start(options).expect("an empty pattern is valid") {
+        if let Ok(metadata) = std::fs::symlink_metadata(&path) {
+            for issue in permissions::issues(&metadata) {
+                found.entry(issue).or_default().push(path.clone());
+            }
+        }
+    }
+    for (issue, mut paths) in found {
+        paths.sort();
+        println!("{} ({})", issue, paths.len());
+        for path in paths {
+            println!("  {}", path.display());
+        }
+    }
+}
+
+fn run_du(root: &Path, depth: Option<usize>, threads: Option<usize>, size_format: SizeFormat) {
+    // Matches are reported below the canonical root, like searches with -d
+    let root = std::fs::canonicalize(root).unwrap_or_else(|e| {
+        eprintln!("Cannot measure {}: {}", root.display(), e);
+        std::process::exit(1);
+    });
+    let mut options = SearchOptions::new("", &root);
+    if let Some(threads) = threads {
+        options.threads = threads;
+    }
+    // Sizes are tallied as the walker examines each entry; nothing is matched
+    let sizes = Arc::new(DirSizes::new(&root, depth));
+    options.filter = Arc::new(filters::from_fn({
+        let sizes = Arc::clone(&sizes);
+        move |entry: &filters::EntryInfo| {
+            sizes.add(entry.path, entry.metadata);
+            false
+        }
+    }));
+    Search::start(options)
+        .expect("an empty pattern is valid")
+        .for_each(drop);
+
+    let rows: Vec<(String, PathBuf)> = sizes
+        .rows()
+        .into_iter()
+        .map(|(dir, bytes)| (format_size(bytes, size_format), dir))
+        .collect();
+    let width = rows
+        .iter()
+        .map(|(size, _)| size.len())
+        .chain(std::iter::once("SIZE".len()))
+        .max()
+        .unwrap_or_default();
+    println!("{:>width$}  DIRECTORY", "SIZE", width = width);
+    for (size, dir) in rows {
+        println!("{:>width$}  {}", size, dir.display(), width = width);
+    }
+}
+
+fn run_server(listen: &str, dir: &Path, threads: Option<usize>) {
+    let listener = TcpListener::bind(listen).unwrap_or_else(|e| {
+        eprintln!("Failed to listen on {}: {}", listen, e);
+        std::process::exit(1);
+    });
+    if let Ok(addr) = listener.local_addr() {
+        eprintln!("Listening on http://{}", addr);
+    }
+
+    let options = ServerOptions {
+        root: dir.to_path_buf(),
+        threads: threads.unwrap_or_else(num_cpus::get),
+    };
+    if let Err(e) = server::serve(listener, options) {
+        eprintln!("Server error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn main() {
+    let started = Instant::now();
+    let mut args = Args::parse();
+    // The search as it will be recorded in the history
+    let mut invocation: Vec<String> = std::env::args_os()
+        .skip(1)
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    match &args.command {
+        Some(Command::SaveSearch { name, args }) => return run_save_search(name, args),
+        Some(Command::Run { name: None, .. }) => return list_saved_searches(),
+        Some(Command::Run {
+            name: Some(name),
+            args: extra,
+        }) => invocation = saved_search_args(name, extra),
+        Some(Command::History { limit }) => return run_history(*limit),
+        Some(Command::Init { shell }) => return print!("{}", shell.init_script()),
+        Some(Command::Jump {
+            keywords,
+            add,
+            list,
+        }) => return run_jump(keywords, add.as_deref(), *list),
+        Some(Command::Rerun { number }) => invocation = history_args(*number),
+        _ => {}
+    }
+    // Searches are parsed again with the defaults from RFIND_OPTS and any
+    // profiles in front
+    let reparse = match args.command {
+        None => !args.profile.is_empty() || !default_opts().is_empty(),
+        Some(Command::Run { .. }) | Some(Command::Rerun { .. }) => true,
+        _ => false,
+    };
+    if reparse {
+        args = parse_search_args(&invocation).unwrap_or_else(|e| e.exit());
+    }
+    init_logging(args.log_format);
+
+    match &args.command {
+        Some(Command::SaveSearch { .. })
+        | Some(Command::Run { .. })
+        | Some(Command::History { .. })
+        | Some(Command::Rerun { .. })
+        | Some(Command::Init { .. })
+        | Some(Command::Jump { .. }) => unreachable!(),
+        Some(Command::Query {
+            query,
+            threads,
+            size_format,
+            time_style,
+            time_format,
+        }) => {
+            let format = FieldFormat {
+                size: *size_format,
+                time: time_format.clone().unwrap_or_else(|| time_style.clone()),
+            };
+            return run_query(query, *threads, &format);
+        }
+        Some(Command::Undo { run_id }) => return run_undo(run_id),
+        Some(Command::Audit {
+            audit: Audit::Perms { root, threads },
+        }) => return run_audit_perms(root, *threads),
+        Some(Command::Du {
+            root,
+            depth,
+            threads,
+            size_format,
+        }) => return run_du(root, *depth, *threads, *size_format),
+        Some(Command::Serve {
+            listen,
+            dir,
+            threads,
+        }) => return run_server(listen, dir, *threads),
+        #[cfg(feature = "grpc")]
+        Some(Command::Grpc {
+            listen,
+            dir,
+            threads,
+        }) => {
+            let listener = TcpListener::bind(listen).unwrap_or_else(|e| {
+                eprintln!("Failed to listen on {}: {}", listen, e);
+                std::process::exit(1);
+            });
+            if let Ok(addr) = listener.local_addr() {
+                eprintln!("Listening on {} (gRPC)", addr);
+            }
+            let options = ServerOptions {
+                root: dir.clone(),
+                threads: threads.unwrap_or_else(num_cpus::get),
+            };
+            if let Err(e) = rfind::grpc::serve(listener, options) {
+                eprintln!("Server error: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        None => {}
+    }
+
+    // Recorded in the history, so that rerunning the search changes to the
+    // base directory again from the same place
+    let launch_dir = std::env::current_dir().unwrap_or_default();
+    if let Some(base) = &args.base_directory {
+        if let Err(e) = std::env::set_current_dir(base) {
+            eprintln!("Cannot change to {}: {}", base.display(), e);
+            std::process::exit(1);
+        }
+    }
+
+    if args.lsp_like {
+        let options = ServerOptions {
+            root: args.dir.clone(),
+            threads: args.threads.unwrap_or_else(num_cpus::get),
+        };
+        let stdin = std::io::stdin();
+        if let Err(e) = rpc::run(stdin.lock(), std::io::stdout(), options) {
+            eprintln!("JSON-RPC error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if (args.delete || args.delete_empty_dirs) && !args.dry_run && is_filesystem_root(&args.dir) {
+        eprintln!(
+            "Refusing to delete matches under {}; choose a directory with --dir",
+            args.dir.display()
+        );
+        std::process::exit(1);
+    }
+
+    let Plugins {
+        filters: plugin_filters,
+        actions: plugin_actions,
+    } = load_plugins(&args);
+    let entry_filter = build_entry_filter(&args, plugin_filters);
+    let on_error = scan_error_handler(&args);
+    // Directories skipped for their size are always reported, on stderr if nowhere else
+    let on_search_error = match &on_error {
+        Some(handler) => Some(Arc::clone(handler)),
+        None if args.max_dir_entries.is_some() => {
+            let handler: ErrorHandler = Arc::new(|error: &ScanError| {
+                if error.operation == ScanOperation::LargeDirectory {
+                    eprintln!("Skipped {}: {}", error.path.display(), error.message)
+                }
+            });
+            Some(handler)
+        }
+        None => None,
+    };
+    // Content skips are always reported, on stderr if nowhere else
+    let on_content_skip = on_error.clone().unwrap_or_else(|| {
+        Arc::new(|error: &ScanError| {
+            eprintln!("Skipped {}: {}", error.path.display(), error.message)
+        })
+    });
+    let pattern_syntax = args.pattern_syntax();
+    let mut patterns: Vec<String> = args.pattern.iter().cloned().collect();
+    if let Some(path) = &args.patterns_from {
+        let from_file = rfind::scanner::read_patterns(path, pattern_syntax).unwrap_or_else(|e| {
+            eprintln!("Cannot read patterns from {}: {}", path.display(), e);
+            std::process::exit(1);
+        });
+        if from_file.is_empty() && patterns.is_empty() {
+            eprintln!("No patterns in {}", path.display());
+            std::process::exit(1);
+        }
+        patterns.extend(from_file);
+    }
+    // Those from the file have been checked already
+    if let Some(pattern) = &args.pattern {
+        if let Err(e) = pattern_syntax.validate(pattern) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+    let pattern_labels = args
+        .show_pattern
+        .then(|| PatternLabels::new(&patterns, pattern_syntax));
+    let pattern = if patterns.is_empty() {
+        String::new()
+    } else {
+        patterns.remove(0)
+    };
+    let resume = args.resume.as_deref().map(|path| {
+        Checkpoint::load(path)
+            .and_then(|checkpoint| {
+                checkpoint.check_compatible(&args.dir, &pattern)?;
+                Ok(checkpoint)
+            })
+            .unwrap_or_else(|e| {
+                eprintln!("Cannot resume from {}: {}", path.display(), e);
+                std::process::exit(1);
+            })
+    });
+    let checkpoint = args.checkpoint.clone().or_else(|| args.resume.clone());
+    let mut prefer = args.prefer.clone();
+    if args.prefer_common {
+        prefer.extend(rfind::scanner::common_directories());
+    }
+    let memory_budget = args.max_memory.map(MemoryBudget::new);
+    let mut search = Search::start(SearchOptions {
         pattern,
-        channels,
+        pattern_syntax,
+        extra_patterns: patterns,
+        root: args.dir.clone(),
         max_depth: args.max_depth,
-        symlink_mode,
-        root_path,
-        type_filter: args.type_filter,
-        mtime_filter,
-        atime_filter,
-        ctime_filter,
+        threads: args.threads.unwrap_or_else(num_cpus::get),
+        symlink_mode: args.symlink_mode(),
+        filter: Arc::new(entry_filter),
         now: SystemTime::now(),
-        size_filter,
+        stat_timeout: args.stat_timeout,
+        io_retries: args.io_retries,
+        on_error: on_search_error,
+        max_errors: args.max_errors,
+        checkpoint: checkpoint.clone(),
+        checkpoint_interval: args.checkpoint_interval,
+        resume,
+        search_archives: args.search_archives,
+        resolve_aliases: args.resolve_aliases,
+        fanout_depth: args.fanout_depth,
+        prefer,
+        max_dir_entries: args.max_dir_entries,
+        include_large: args.include_large.clone(),
+        prune: if args.no_dev_clutter {
+            load_config("--no-dev-clutter").dev_clutter()
+        } else {
+            Vec::new()
+        },
+        exclude: args.exclude_regex.clone(),
+        memory_budget: memory_budget.clone(),
+        result_buffer: (!args.no_backpressure).then_some(args.result_buffer),
+    })
+    .unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
     });
 
+    // Stop cleanly on Ctrl-C so the final checkpoint is written
+    if checkpoint.is_some() {
+        let cancel = search.cancel_token();
+        if let Err(e) = ctrlc::set_handler(move || cancel.cancel()) {
+            eprintln!("Failed to install Ctrl-C handler: {}", e);
+        }
+    }
+
+    if let Some(path) = &args.verify_manifest {
+        return run_verify_manifest(path, search, args.max_filesize);
+    }
+
     // Process results
-    while let Ok(path) = thread_pool.result_receiver.recv() {
-        if args.print0 {
-            print!("{}\0", path.display());
-            std::io::stdout().flush().expect("Failed to flush stdout");
+    let executor = build_executor(&args, plugin_actions);
+    let mut manifest = args.manifest.as_deref().map(|path| {
+        let writer = ManifestWriter::create(path, args.checksum)
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to create manifest {}: {}", path.display(), e);
+                std::process::exit(1);
+            })
+            .with_skip_handler(on_content_skip.clone());
+        match args.max_filesize {
+            Some(max) => writer.with_max_filesize(max, Some(on_content_skip.clone())),
+            None => writer,
+        }
+    });
+    let path_display = PathDisplay::new(&args);
+    let mut ranking = Ranking::new(&args);
+    let mut summary = args.summarize_ext.then(ExtensionSummary::default);
+    let security = args.preset.iter().any(|name| name == presets::SECURITY);
+    if args.csv && ranking.is_none() && summary.is_none() && executor.is_empty() {
+        let mut header = fields::csv_header(&structured_fields(&args));
+        if security {
+            header.push_str(",severity,rules");
+        }
+        println!("{}", header);
+    }
+
+    let mut failures = 0;
+    let mut matches = 0;
+    let mut process = |path: PathBuf| {
+        matches += 1;
+        if let Some(manifest) = &mut manifest {
+            if let Err(e) = manifest.add(&path) {
+                eprintln!("Failed to add {} to manifest: {}", path.display(), e);
+                failures += 1;
+            }
+        }
+        if let Some(ranking) = &mut ranking {
+            // Archive members have no metadata of their own
+            if let Ok(metadata) = match_metadata(&path, &args) {
+                ranking.push(path, &metadata);
+            }
+        } else if let Some(summary) = &mut summary {
+            if let Ok(metadata) = match_metadata(&path, &args) {
+                summary.add(&path, &metadata);
+            }
+        } else if !executor.is_empty() {
+            if let Err(e) = executor.apply(&path) {
+                eprintln!("Failed to process {}: {}", path.display(), e);
+                failures += 1;
+            }
         } else {
-            println!("{}", format!("{}", path.display()).green());
+            print_match(
+                &path,
+                &path_display,
+                &args,
+                security,
+                pattern_labels.as_ref(),
+            );
+        }
+    };
+
+    // With --first the scan is cancelled as soon as a match arrives, before
+    // it is acted on
+    let cancel = search.cancel_token();
+    let limit = if args.first { 1 } else { usize::MAX };
+    let mut found = (&mut search).take(limit).inspect(|_| {
+        if args.first {
+            cancel.cancel();
+        }
+    });
+
+    // With --ok every change is confirmed on its own
+    let confirm = executor.is_destructive() && !args.dry_run && !args.force && !args.ok;
+    if confirm || args.ok || args.delete || args.delete_empty_dirs {
+        // The full set of matches is needed before anything is changed, and
+        // --ok prompts only once the scan can no longer write to the terminal
+        let mut paths = PathBuffer::new(memory_budget);
+        // With --delete, matched directories are held back and removed after
+        // everything else, deepest first, so that matches inside them are
+        // gone by then
+        let mut dirs = Vec::new();
+        for path in &mut found {
+            if args.delete && std::fs::symlink_metadata(&path).is_ok_and(|m| m.is_dir()) {
+                dirs.push(path);
+                continue;
+            }
+            paths.push(path).unwrap_or_else(|e| {
+                eprintln!("Failed to buffer matches: {}", e);
+                std::process::exit(1);
+            });
+        }
+        dirs.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+        for dir in dirs {
+            paths.push(dir).unwrap_or_else(|e| {
+                eprintln!("Failed to buffer matches: {}", e);
+                std::process::exit(1);
+            });
+        }
+        if args.delete_empty_dirs {
+            // Ordering the removals needs every candidate in memory
+            let dirs = paths.into_vec().unwrap_or_else(|e| {
+                eprintln!("Failed to read back buffered matches: {}", e);
+                std::process::exit(1);
+            });
+            paths = PathBuffer::from(cleanup::empty_dir_removal_order(dirs, &args.dir));
+        }
+        let impact = Impact::measure(buffered(&mut paths));
+        let limits = SafetyLimits {
+            max_entries: args.max_affected,
+            max_bytes: args.max_affected_size,
+        };
+        if confirm && !limits.allows(&impact) {
+            confirm_bulk_action(buffered(&mut paths), &impact, &limits);
+        }
+        buffered(&mut paths).for_each(&mut process);
+    } else {
+        found.for_each(&mut process);
+    }
+
+    if let Some(ranking) = ranking {
+        ranking.print(&path_display, &args);
+    }
+    if let Some(summary) = summary {
+        print_extension_summary(&summary, args.display_format().size);
+    }
+    match executor.finish() {
+        Ok(failed) => failures += failed,
+        Err(e) => {
+            eprintln!("Failed to run batched command: {}", e);
+            failures += 1;
         }
     }
 
+    let timed_out = search.timed_out_paths();
+    let stats = search.stats();
+    let too_many_errors = args.max_errors.is_some_and(|max| stats.errors > max);
+    // Stopping after the first match or on too many errors is not an
+    // interruption, and is reported on its own
+    let interrupted = search.cancel_token().is_cancelled() && !args.first && !too_many_errors;
+
     // Wait for all threads to complete
-    for handle in thread_pool.scanner_handles {
-        handle.join().unwrap();
+    search.join();
+
+    let timed_out_count = timed_out.len();
+    // With JSON logging, timeouts have already been reported as events
+    if !timed_out.is_empty() && args.log_format == LogFormat::Text {
+        eprintln!("Skipped {} path(s) that timed out:", timed_out.len());
+        for path in timed_out {
+            eprintln!("  {}", path.display());
+        }
+    }
+
+    if let Some(path) = checkpoint.filter(|_| interrupted) {
+        eprintln!(
+            "Search interrupted; continue with --resume {}",
+            path.display()
+        );
+    }
+
+    if let Some(journal) = executor.journal().filter(|journal| !journal.is_empty()) {
+        eprintln!(
+            "Changes recorded as run {} (undo with: rfind undo {})",
+            journal.run_id(),
+            journal.run_id()
+        );
+    }
+
+    if args.stats_json {
+        let summary = json!({
+            "matches": matches,
+            "dirs_scanned": stats.dirs_scanned,
+            "entries_scanned": stats.entries_scanned,
+            "errors": stats.errors,
+            "skipped": stats.skipped,
+            "timed_out": timed_out_count,
+            "failures": failures,
+            "interrupted": interrupted,
+            "duration_ms": started.elapsed().as_millis() as u64,
+        });
+        eprintln!("{}", summary);
+    }
+
+    if history::is_enabled() {
+        let mut entry = HistoryEntry::new(invocation, matches, started.elapsed());
+        entry.cwd = launch_dir;
+        if let Err(e) = history::history_file().and_then(|path| history::append(&path, &entry)) {
+            eprintln!("Failed to record search history: {}", e);
+        }
+    }
+
+    if too_many_errors {
+        eprintln!(
+            "Search aborted after {} errors (--max-errors {}); results are incomplete",
+            stats.errors,
+            args.max_errors.unwrap_or_default()
+        );
+        std::process::exit(1);
+    }
+    if interrupted {
+        std::process::exit(130);
+    }
+    if failures > 0 {
+        std::process::exit(1);
     }
-    thread_pool.distributor_handle.join().unwrap();
 }
@@ -0,0 +1,210 @@
+//! Manifests of matched files (`--manifest`) and drift detection against them
+//! (`--verify-manifest`).
+//!
+//! A manifest is a JSON Lines file with one object per match:
+//! `{"path": "...", "size": 123, "mtime": 1700000000, "checksum": "..."}`.
+//! The SHA-256 checksum is only present when requested with `--checksum`, and is
+//! left out for files larger than `--max-filesize` and for cloud placeholders.
+
+use crate::checksum::sha256_file;
+use crate::placeholder::{self, is_placeholder_at};
+use crate::plugin::entry_mtime;
+use crate::scanner::{ErrorHandler, ScanError, ScanOperation};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, LineWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// A single manifest line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub path: PathBuf,
+    pub size: u64,
+    /// Modification time in seconds since the Unix epoch
+    pub mtime: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+}
+
+impl ManifestEntry {
+    /// Describe `path` as it is now. Checksums are only computed for regular
+    /// files no larger than `max_filesize`, and never for cloud placeholders.
+    pub fn from_path(path: &Path, checksum: bool, max_filesize: Option<u64>) -> io::Result<Self> {
+        let metadata = placeholder::symlink_metadata(path)?;
+        let checksum = if checksum
+            && metadata.is_file()
+            && max_filesize.is_none_or(|max| metadata.len() <= max)
+            && !is_placeholder_at(path, &metadata)
+        {
+            Some(sha256_file(path)?)
+        } else {
+            None
+        };
+        Ok(ManifestEntry {
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            mtime: entry_mtime(&metadata),
+            checksum,
+        })
+    }
+}
+
+/// Appends entries to a manifest file
+pub struct ManifestWriter {
+    output: LineWriter<File>,
+    checksum: bool,
+    max_filesize: Option<u64>,
+    on_skip: Option<ErrorHandler>,
+}
+
+impl ManifestWriter {
+    pub fn create(path: &Path, checksum: bool) -> io::Result<Self> {
+        Ok(ManifestWriter {
+            output: LineWriter::new(File::create(path)?),
+            checksum,
+            max_filesize: None,
+            on_skip: None,
+        })
+    }
+
+    /// Don't checksum files larger than `max_filesize` bytes, reporting each
+    /// one to `on_skip` instead
+    pub fn with_max_filesize(mut self, max_filesize: u64, on_skip: Option<ErrorHandler>) -> Self {
+        self.max_filesize = Some(max_filesize);
+        self.on_skip = on_skip;
+        self
+    }
+
+    /// Report files whose contents are not read, such as cloud placeholders,
+    /// to `on_skip`
+    pub fn with_skip_handler(mut self, on_skip: ErrorHandler) -> Self {
+        self.on_skip = Some(on_skip);
+        self
+    }
+
+    pub fn add(&mut self, path: &Path) -> io::Result<()> {
+        let entry = ManifestEntry::from_path(path, self.checksum, self.max_filesize)?;
+        if let (true, None, Some(on_skip)) = (self.checksum, &entry.checksum, &self.on_skip) {
+            let message = match self.max_filesize {
+                Some(max) if entry.size > max => Some(format!(
+                    "larger than --max-filesize ({} bytes), not read",
+                    max
+                )),
+                _ if placeholder::symlink_metadata(path)
+                    .is_ok_and(|m| is_placeholder_at(path, &m)) =>
+                {
+                    Some(placeholder::SKIP_REASON.to_string())
+                }
+                _ => None,
+            };
+            if let Some(message) = message {
+                on_skip(&ScanError {
+                    path: path.to_path_buf(),
+                    operation: ScanOperation::Skip,
+                    kind: None,
+                    message,
+                    retries: 0,
+                });
+            }
+        }
+        let line = serde_json::to_string(&entry).map_err(io::Error::other)?;
+        writeln!(self.output, "{}", line)
+    }
+}
+
+/// Read every entry of a manifest file
+pub fn load(path: &Path) -> Result<Vec<ManifestEntry>, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut entries = Vec::new();
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line)
+            .map_err(|e| format!("Invalid manifest entry on line {}: {}", number + 1, e))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// A difference between a manifest and the files found now
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Drift {
+    /// Matched now but not listed in the manifest
+    Added(PathBuf),
+    /// Listed in the manifest but no longer matched
+    Removed(PathBuf),
+    /// Present in both, with the listed fields differing
+    Changed(PathBuf, Vec<&'static str>),
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Drift::Added(path) => write!(f, "added: {}", path.display()),
+            Drift::Removed(path) => write!(f, "removed: {}", path.display()),
+            Drift::Changed(path, fields) => {
+                write!(f, "changed: {} ({})", path.display(), fields.join(", "))
+            }
+        }
+    }
+}
+
+/// Compare `manifest` against the paths matched by a new scan. Checksums are
+/// only recomputed for entries that recorded one, and not for files that are
+/// now larger than `max_filesize`.
+pub fn verify(
+    manifest: Vec<ManifestEntry>,
+    current: impl IntoIterator<Item = PathBuf>,
+    max_filesize: Option<u64>,
+) -> Vec<Drift> {
+    let mut expected: BTreeMap<PathBuf, ManifestEntry> = manifest
+        .into_iter()
+        .map(|entry| (entry.path.clone(), entry))
+        .collect();
+    let mut seen = HashSet::new();
+    let mut drift = Vec::new();
+
+    let mut current: Vec<PathBuf> = current.into_iter().collect();
+    current.sort();
+    for path in current {
+        if !seen.insert(path.clone()) {
+            continue;
+        }
+        let old = match expected.remove(&path) {
+            Some(old) => old,
+            None => {
+                drift.push(Drift::Added(path));
+                continue;
+            }
+        };
+        let new = match ManifestEntry::from_path(&path, old.checksum.is_some(), max_filesize) {
+            Ok(new) => new,
+            // Vanished between the scan and now
+            Err(_) => {
+                drift.push(Drift::Removed(path));
+                continue;
+            }
+        };
+
+        let mut fields = Vec::new();
+        if new.size != old.size {
+            fields.push("size");
+        }
+        if new.mtime != old.mtime {
+            fields.push("mtime");
+        }
+        if new.checksum.is_some() && new.checksum != old.checksum {
+            fields.push("checksum");
+        }
+        if !fields.is_empty() {
+            drift.push(Drift::Changed(path, fields));
+        }
+    }
+
+    drift.extend(expected.into_keys().map(Drift::Removed));
+    drift
+}
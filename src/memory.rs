@@ -0,0 +1,281 @@
+//! Keeping memory use bounded (`--max-memory`).
+//!
+//! Structures that grow with the size of the tree (buffered results, the
+//! directories visited through links, sort keys) reserve an estimate of what
+//! they hold from a shared [`MemoryBudget`]. Once it runs out they switch to
+//! leaner strategies instead of growing further: buffered results spill to a
+//! temporary file, and visited directories are only remembered by a 64-bit
+//! fingerprint.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// A limit on the estimated memory held by buffered paths, shared by
+/// everything that draws from it
+#[derive(Debug)]
+pub struct MemoryBudget {
+    limit: u64,
+    used: AtomicU64,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: u64) -> Arc<Self> {
+        Arc::new(MemoryBudget {
+            limit,
+            used: AtomicU64::new(0),
+        })
+    }
+
+    /// Take `bytes` from the budget, or nothing if that would exceed it
+    pub fn try_reserve(&self, bytes: u64) -> bool {
+        self.used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(bytes).filter(|&total| total <= self.limit)
+            })
+            .is_ok()
+    }
+
+    /// Give back `bytes` reserved earlier
+    pub fn release(&self, bytes: u64) {
+        self.used.fetch_sub(bytes, Ordering::Relaxed);
+    }
+
+    pub fn used(&self) -> u64 {
+        self.used.load(Ordering::Relaxed)
+    }
+}
+
+/// Reserve `bytes` from an optional budget; without one there is no limit
+pub fn try_reserve(budget: &Option<Arc<MemoryBudget>>, bytes: u64) -> bool {
+    budget
+        .as_ref()
+        .is_none_or(|budget| budget.try_reserve(bytes))
+}
+
+/// Estimated memory held by a stored path
+pub fn path_cost(path: &Path) -> u64 {
+    (mem::size_of::<PathBuf>() + path.as_os_str().len()) as u64
+}
+
+/// Paths seen so far, for loop detection. Paths are kept in full while the
+/// budget allows, then only fingerprints of new ones, which cost a fixed
+/// eight bytes each.
+#[derive(Debug)]
+pub struct VisitedSet {
+    paths: HashSet<PathBuf>,
+    fingerprints: HashSet<u64>,
+    reserved: u64,
+    budget: Option<Arc<MemoryBudget>>,
+}
+
+impl VisitedSet {
+    pub fn new(budget: Option<Arc<MemoryBudget>>) -> Self {
+        VisitedSet {
+            paths: HashSet::new(),
+            fingerprints: HashSet::new(),
+            reserved: 0,
+            budget,
+        }
+    }
+
+    /// Record `path`, returning whether it had not been seen before
+    pub fn insert(&mut self, path: PathBuf) -> bool {
+        let fingerprint = fingerprint(&path);
+        if self.paths.contains(&path) || self.fingerprints.contains(&fingerprint) {
+            return false;
+        }
+        let cost = path_cost(&path);
+        if try_reserve(&self.budget, cost) {
+            self.reserved += cost;
+            self.paths.insert(path)
+        } else {
+            self.fingerprints.insert(fingerprint)
+        }
+    }
+}
+
+impl Drop for VisitedSet {
+    fn drop(&mut self) {
+        if let Some(budget) = &self.budget {
+            budget.release(self.reserved);
+        }
+    }
+}
+
+fn fingerprint(path: &Path) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A list of paths kept in memory until the budget runs out, then written to
+/// a temporary file that is removed when the buffer is dropped. Paths come
+/// back in the order they were pushed.
+#[derive(Debug)]
+pub struct PathBuffer {
+    memory: Vec<PathBuf>,
+    reserved: u64,
+    spill: Option<Spill>,
+    len: usize,
+    budget: Option<Arc<MemoryBudget>>,
+}
+
+#[derive(Debug)]
+struct Spill {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl PathBuffer {
+    pub fn new(budget: Option<Arc<MemoryBudget>>) -> Self {
+        PathBuffer {
+            memory: Vec::new(),
+            reserved: 0,
+            spill: None,
+            len: 0,
+            budget,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the paths have been moved out of memory
+    pub fn is_spilled(&self) -> bool {
+        self.spill.is_some()
+    }
+
+    pub fn push(&mut self, path: PathBuf) -> io::Result<()> {
+        self.len += 1;
+        if self.spill.is_none() {
+            let cost = path_cost(&path);
+            if try_reserve(&self.budget, cost) {
+                self.reserved += cost;
+                self.memory.push(path);
+                return Ok(());
+            }
+            self.start_spill()?;
+        }
+        let spill = self.spill.as_mut().expect("spill started above");
+        write_path(&mut spill.writer, &path)
+    }
+
+    /// Move everything held in memory to a new temporary file
+    fn start_spill(&mut self) -> io::Result<()> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "rfind-spill-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut writer = BufWriter::new(File::create_new(&path)?);
+        for buffered in &self.memory {
+            write_path(&mut writer, buffered)?;
+        }
+        self.memory = Vec::new();
+        self.release();
+        self.spill = Some(Spill { path, writer });
+        Ok(())
+    }
+
+    fn release(&mut self) {
+        if let Some(budget) = &self.budget {
+            budget.release(mem::take(&mut self.reserved));
+        }
+    }
+
+    /// Iterate over the paths, reading them back from disk if they were spilled
+    pub fn iter(&mut self) -> io::Result<Iter<'_>> {
+        match &mut self.spill {
+            Some(spill) => {
+                spill.writer.flush()?;
+                let mut file = spill.writer.get_ref().try_clone()?;
+                file.seek(SeekFrom::Start(0))?;
+                Ok(Iter::Spilled(BufReader::new(file), self.len))
+            }
+            None => Ok(Iter::Memory(self.memory.iter())),
+        }
+    }
+
+    /// All the paths, read back into memory whatever the budget
+    pub fn into_vec(mut self) -> io::Result<Vec<PathBuf>> {
+        if self.spill.is_none() {
+            self.release();
+            return Ok(mem::take(&mut self.memory));
+        }
+        self.iter()?.collect()
+    }
+}
+
+impl From<Vec<PathBuf>> for PathBuffer {
+    fn from(memory: Vec<PathBuf>) -> Self {
+        PathBuffer {
+            len: memory.len(),
+            memory,
+            reserved: 0,
+            spill: None,
+            budget: None,
+        }
+    }
+}
+
+impl Drop for PathBuffer {
+    fn drop(&mut self) {
+        self.release();
+        if let Some(spill) = &self.spill {
+            let _ = fs::remove_file(&spill.path);
+        }
+    }
+}
+
+/// Paths from a [`PathBuffer`]
+pub enum Iter<'a> {
+    Memory(std::slice::Iter<'a, PathBuf>),
+    Spilled(BufReader<File>, usize),
+}
+
+impl Iterator for Iter<'_> {
+    type Item = io::Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Iter::Memory(paths) => paths.next().cloned().map(Ok),
+            Iter::Spilled(_, 0) => None,
+            Iter::Spilled(reader, remaining) => {
+                *remaining -= 1;
+                Some(read_path(reader))
+            }
+        }
+    }
+}
+
+/// Paths are stored as a little-endian length followed by their bytes
+fn write_path(writer: &mut impl Write, path: &Path) -> io::Result<()> {
+    let bytes = path.as_os_str().as_encoded_bytes();
+    writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+fn read_path(reader: &mut impl Read) -> io::Result<PathBuf> {
+    let mut len = [0; 8];
+    reader.read_exact(&mut len)?;
+    let mut bytes = vec![0; u64::from_le_bytes(len) as usize];
+    reader.read_exact(&mut bytes)?;
+    // SAFETY: the bytes were produced by `as_encoded_bytes` in this process
+    Ok(PathBuf::from(unsafe {
+        OsString::from_encoded_bytes_unchecked(bytes)
+    }))
+}
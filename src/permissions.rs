@@ -0,0 +1,247 @@
+//! Checks for risky permissions, used by `rfind audit perms` and
+//! `--preset security`.
+//!
+//! Only Unix modes and owners are inspected; on other platforms no entry ever
+//! has an issue.
+
+use std::fmt;
+use std::fs::Metadata;
+
+/// A permission problem found on an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PermissionIssue {
+    /// Writable by everyone; directories with the sticky bit (like /tmp) are fine
+    WorldWritable,
+    /// Runs as its owner (set-user-ID)
+    Setuid,
+    /// Runs with its group (set-group-ID)
+    Setgid,
+    /// Owned by a user ID with no account
+    NoUser,
+    /// Owned by a group ID with no group
+    NoGroup,
+    /// Sticky bit on something other than a directory, where it has no use
+    Sticky,
+}
+
+impl PermissionIssue {
+    pub const ALL: [PermissionIssue; 6] = [
+        PermissionIssue::WorldWritable,
+        PermissionIssue::Setuid,
+        PermissionIssue::Setgid,
+        PermissionIssue::NoUser,
+        PermissionIssue::NoGroup,
+        PermissionIssue::Sticky,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PermissionIssue::WorldWritable => "world-writable",
+            PermissionIssue::Setuid => "setuid",
+            PermissionIssue::Setgid => "setgid",
+            PermissionIssue::NoUser => "nouser",
+            PermissionIssue::NoGroup => "nogroup",
+            PermissionIssue::Sticky => "sticky",
+        }
+    }
+}
+
+impl fmt::Display for PermissionIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How urgently an issue deserves attention
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+        })
+    }
+}
+
+impl PermissionIssue {
+    pub fn severity(self) -> Severity {
+        match self {
+            PermissionIssue::WorldWritable | PermissionIssue::Setuid => Severity::High,
+            PermissionIssue::Setgid | PermissionIssue::NoUser => Severity::Medium,
+            PermissionIssue::NoGroup | PermissionIssue::Sticky => Severity::Low,
+        }
+    }
+}
+
+/// The severity of the most serious of `issues`, if there are any
+pub fn max_severity(issues: &[PermissionIssue]) -> Option<Severity> {
+    issues.iter().map(|issue| issue.severity()).max()
+}
+
+#[cfg(unix)]
+mod unix {
+    use parking_lot::Mutex;
+    use std::collections::HashMap;
+
+    pub const SETUID: u32 = 0o4000;
+    pub const SETGID: u32 = 0o2000;
+    pub const STICKY: u32 = 0o1000;
+    pub const WORLD_WRITABLE: u32 = 0o0002;
+
+    /// Account names of IDs (`None` when there is no account), cached since
+    /// the lookups go through NSS
+    #[derive(Default)]
+    struct IdCache {
+        users: HashMap<u32, Option<String>>,
+        groups: HashMap<u32, Option<String>>,
+    }
+
+    static CACHE: Mutex<Option<IdCache>> = Mutex::new(None);
+
+    /// Buffer size for the reentrant lookups, ample for any real entry
+    const BUFFER_SIZE: usize = 16 * 1024;
+
+    /// Copy a name returned by a lookup
+    ///
+    /// # Safety
+    /// `name` must point to a NUL-terminated string
+    unsafe fn to_string(name: *const libc::c_char) -> String {
+        std::ffi::CStr::from_ptr(name)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn lookup_user(uid: u32) -> Option<String> {
+        let mut buf = vec![0 as libc::c_char; BUFFER_SIZE];
+        let mut result = std::ptr::null_mut();
+        // SAFETY: all pointers refer to live locals and `buf.len()` is the
+        // length of `buf`; on success `pw_name` points into `buf`
+        unsafe {
+            let mut passwd: libc::passwd = std::mem::zeroed();
+            libc::getpwuid_r(uid, &mut passwd, buf.as_mut_ptr(), buf.len(), &mut result);
+            (!result.is_null()).then(|| to_string(passwd.pw_name))
+        }
+    }
+
+    fn lookup_group(gid: u32) -> Option<String> {
+        let mut buf = vec![0 as libc::c_char; BUFFER_SIZE];
+        let mut result = std::ptr::null_mut();
+        // SAFETY: as in `lookup_user`
+        unsafe {
+            let mut group: libc::group = std::mem::zeroed();
+            libc::getgrgid_r(gid, &mut group, buf.as_mut_ptr(), buf.len(), &mut result);
+            (!result.is_null()).then(|| to_string(group.gr_name))
+        }
+    }
+
+    pub fn user_name(uid: u32) -> Option<String> {
+        let mut cache = CACHE.lock();
+        let cache = cache.get_or_insert_with(IdCache::default);
+        cache
+            .users
+            .entry(uid)
+            .or_insert_with(|| lookup_user(uid))
+            .clone()
+    }
+
+    pub fn group_name(gid: u32) -> Option<String> {
+        let mut cache = CACHE.lock();
+        let cache = cache.get_or_insert_with(IdCache::default);
+        cache
+            .groups
+            .entry(gid)
+            .or_insert_with(|| lookup_group(gid))
+            .clone()
+    }
+}
+
+/// The name of the entry's owner, or its numeric user ID when it has no
+/// account. `None` on platforms without Unix owners.
+pub fn owner(metadata: &Metadata) -> Option<String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let uid = metadata.uid();
+        Some(unix::user_name(uid).unwrap_or_else(|| uid.to_string()))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// The permission bits as four octal digits, e.g. `0755`. `None` on
+/// platforms without Unix modes.
+pub fn octal_mode(metadata: &Metadata) -> Option<String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        Some(format!("{:04o}", metadata.permissions().mode() & 0o7777))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// Whether the setuid, setgid or sticky bit is set
+pub fn has_special_mode(metadata: &Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & (unix::SETUID | unix::SETGID | unix::STICKY) != 0
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        false
+    }
+}
+
+/// Every issue with the entry's permissions, in [`PermissionIssue::ALL`]
+/// order. Symlinks are never reported, since their own mode is meaningless.
+pub fn issues(metadata: &Metadata) -> Vec<PermissionIssue> {
+    let mut issues = Vec::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if metadata.file_type().is_symlink() {
+            return issues;
+        }
+        let mode = metadata.mode();
+        let is_dir = metadata.is_dir();
+        if mode & unix::WORLD_WRITABLE != 0 && !(is_dir && mode & unix::STICKY != 0) {
+            issues.push(PermissionIssue::WorldWritable);
+        }
+        if has_special_mode(metadata) {
+            if mode & unix::SETUID != 0 {
+                issues.push(PermissionIssue::Setuid);
+            }
+            // On directories setgid only makes new entries inherit the group
+            if mode & unix::SETGID != 0 && !is_dir {
+                issues.push(PermissionIssue::Setgid);
+            }
+        }
+        if unix::user_name(metadata.uid()).is_none() {
+            issues.push(PermissionIssue::NoUser);
+        }
+        if unix::group_name(metadata.gid()).is_none() {
+            issues.push(PermissionIssue::NoGroup);
+        }
+        if mode & unix::STICKY != 0 && !is_dir {
+            issues.push(PermissionIssue::Sticky);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = metadata;
+    issues
+}
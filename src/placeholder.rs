@@ -0,0 +1,88 @@
+//! Cloud placeholders: files whose contents are only downloaded on first
+//! access, such as OneDrive Files On-Demand and iCloud Drive.
+//!
+//! Their metadata (name, size, times) is available locally, so metadata
+//! filters work as usual, but reading them downloads ("hydrates") the file.
+//! rfind never reads placeholders: checksums and archive listings skip them.
+//!
+//! iCloud Drive keeps evicted files either as dataless APFS files or, on
+//! older systems, as hidden `.Name.ext.icloud` stubs. Stubs are reported under
+//! the name of the file they stand for (`Name.ext`).
+
+use std::ffi::OsString;
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// `FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS`: reading the file fetches it first
+#[cfg(windows)]
+const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x0040_0000;
+
+/// `SF_DATALESS`: the file's contents are held by a file provider
+#[cfg(target_os = "macos")]
+const SF_DATALESS: u32 = 0x4000_0000;
+
+/// Reason given when content is not read from a placeholder
+pub const SKIP_REASON: &str = "cloud placeholder, not downloaded";
+
+/// Whether the entry is a placeholder whose contents are not stored locally
+pub fn is_placeholder(metadata: &Metadata) -> bool {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        metadata.file_attributes() & FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS != 0
+    }
+    #[cfg(target_os = "macos")]
+    {
+        use std::os::macos::fs::MetadataExt;
+        metadata.st_flags() & SF_DATALESS != 0
+    }
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        let _ = metadata;
+        false
+    }
+}
+
+/// Like [`is_placeholder`], but also true when `path` is the logical name of
+/// an iCloud stub
+pub fn is_placeholder_at(path: &Path, metadata: &Metadata) -> bool {
+    is_placeholder(metadata) || stub_path(path).is_some_and(|stub| stub.symlink_metadata().is_ok())
+}
+
+/// The name of the file an iCloud stub named `stub_name` stands for:
+/// `.Report.pdf.icloud` becomes `Report.pdf`
+pub fn icloud_logical_name(stub_name: &str) -> Option<&str> {
+    stub_name
+        .strip_prefix('.')?
+        .strip_suffix(".icloud")
+        .filter(|name| !name.is_empty())
+}
+
+/// The logical name of the iCloud stub at `path`, if it is one. Stubs are
+/// only recognized on macOS.
+pub fn logical_name(path: &Path) -> Option<OsString> {
+    if !cfg!(target_os = "macos") {
+        return None;
+    }
+    let name = path.file_name()?.to_str()?;
+    icloud_logical_name(name).map(OsString::from)
+}
+
+/// The iCloud stub standing for the file at `path`, which need not exist
+fn stub_path(path: &Path) -> Option<PathBuf> {
+    if !cfg!(target_os = "macos") {
+        return None;
+    }
+    let name = path.file_name()?.to_str()?;
+    Some(path.with_file_name(format!(".{}.icloud", name)))
+}
+
+/// [`fs::symlink_metadata`], falling back to the metadata of the iCloud stub
+/// when `path` is the logical name of one
+pub fn symlink_metadata(path: &Path) -> io::Result<Metadata> {
+    fs::symlink_metadata(path).or_else(|e| match stub_path(path) {
+        Some(stub) if e.kind() == io::ErrorKind::NotFound => fs::symlink_metadata(stub),
+        _ => Err(e),
+    })
+}
@@ -0,0 +1,227 @@
+//! Native filter plugins loaded at runtime.
+//!
+//! A plugin is a shared library exposing the following C ABI:
+//!
+//! ```c
+//! struct rfind_entry {
+//!     const char *path;   /* NUL-terminated path of the entry on disk */
+//!     uint64_t size;      /* size in bytes */
+//!     uint32_t kind;      /* 0 = other, 1 = file, 2 = dir, 3 = symlink */
+//!     int64_t mtime;      /* modification time, seconds since the Unix epoch */
+//! };
+//!
+//! uint32_t rfind_plugin_abi_version(void);          /* must return 1 */
+//! int32_t rfind_filter(const struct rfind_entry *); /* non-zero keeps the entry */
+//! ```
+//!
+//! `rfind_filter` is called concurrently from every scanner thread, so it must
+//! be thread-safe. The entry and its path are only valid during the call. On
+//! Unix the path holds the raw bytes of the file name, which need not be UTF-8.
+//!
+//! Filters and actions can also be registered without writing any code, with
+//! a TOML manifest passed to `--plugin` in place of a library:
+//!
+//! ```toml
+//! # Keep entries for which the command exits with status 0
+//! [[filter]]
+//! command = ["dlp-classify", "--quiet", "{}"]
+//!
+//! # Native plugins, relative to the manifest
+//! [[filter]]
+//! library = "libmyfilter.so"
+//!
+//! # Run on every match, like --exec
+//! [[action]]
+//! command = ["dlp-quarantine", "{}"]
+//! ```
+
+use crate::actions::Action;
+use crate::filters::{CommandFilter, EntryFilter, EntryInfo};
+use libloading::{Library, Symbol};
+use serde::Deserialize;
+use std::ffi::{CString, OsStr};
+use std::fs::{self, Metadata};
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// ABI version understood by this build of rfind
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// Entry description passed to plugins
+#[repr(C)]
+pub struct RawEntry {
+    pub path: *const c_char,
+    pub size: u64,
+    pub kind: u32,
+    pub mtime: i64,
+}
+
+/// Encode the entry type as passed across the plugin ABI
+pub(crate) fn entry_kind(metadata: &Metadata) -> u32 {
+    let file_type = metadata.file_type();
+    if file_type.is_file() {
+        1
+    } else if file_type.is_dir() {
+        2
+    } else if file_type.is_symlink() {
+        3
+    } else {
+        0
+    }
+}
+
+/// Modification time in seconds since the Unix epoch (0 if unavailable)
+pub(crate) fn entry_mtime(metadata: &Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+type AbiVersionFn = unsafe extern "C" fn() -> u32;
+/// `rfind_filter`, called from every scanner thread at once
+type FilterFn = unsafe extern "C" fn(*const RawEntry) -> i32;
+
+/// A filter backed by a dynamically loaded shared library
+pub struct NativePlugin {
+    filter: FilterFn,
+    // Keeps the library mapped for as long as `filter` may be called
+    _library: Library,
+}
+
+impl NativePlugin {
+    /// Load a plugin from the shared library at `path`
+    pub fn load(path: &Path) -> Result<Self, String> {
+        // SAFETY: loading a library runs its initialisers; plugins are trusted
+        // code explicitly requested by the user on the command line.
+        let library = unsafe { Library::new(path) }
+            .map_err(|e| format!("Failed to load plugin {}: {}", path.display(), e))?;
+
+        let version = unsafe {
+            let version: Symbol<AbiVersionFn> = library
+                .get(b"rfind_plugin_abi_version\0")
+                .map_err(|e| format!("Plugin {} has no ABI version: {}", path.display(), e))?;
+            version()
+        };
+        if version != PLUGIN_ABI_VERSION {
+            return Err(format!(
+                "Plugin {} uses ABI version {}, expected {}",
+                path.display(),
+                version,
+                PLUGIN_ABI_VERSION
+            ));
+        }
+
+        let filter = unsafe {
+            let filter: Symbol<FilterFn> = library
+                .get(b"rfind_filter\0")
+                .map_err(|e| format!("Plugin {} has no rfind_filter: {}", path.display(), e))?;
+            *filter
+        };
+
+        Ok(NativePlugin {
+            filter,
+            _library: library,
+        })
+    }
+}
+
+impl EntryFilter for NativePlugin {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        #[cfg(unix)]
+        let path = CString::new(entry.disk_path.as_os_str().as_encoded_bytes());
+        #[cfg(not(unix))]
+        let path = CString::new(entry.disk_path.to_string_lossy().into_owned());
+        let path = match path {
+            Ok(path) => path,
+            Err(_) => return false,
+        };
+
+        let raw = RawEntry {
+            path: path.as_ptr(),
+            size: entry.metadata.len(),
+            kind: entry_kind(entry.metadata),
+            mtime: entry_mtime(entry.metadata),
+        };
+
+        // SAFETY: `raw` and the string it points to outlive the call, and the
+        // symbol was checked against the expected ABI version on load.
+        unsafe { (self.filter)(&raw) != 0 }
+    }
+}
+
+/// The filters and actions registered by `--plugin` arguments
+#[derive(Default)]
+pub struct Plugins {
+    pub filters: Vec<Box<dyn EntryFilter>>,
+    pub actions: Vec<Action>,
+}
+
+impl Plugins {
+    /// Load a plugin manifest (a `.toml` file) or a native plugin library,
+    /// limiting each command filter to `jobs` concurrent commands
+    pub fn load(&mut self, path: &Path, jobs: usize) -> Result<(), String> {
+        if path.extension() != Some(OsStr::new("toml")) {
+            self.filters.push(Box::new(NativePlugin::load(path)?));
+            return Ok(());
+        }
+
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read plugin manifest {}: {}", path.display(), e))?;
+        let manifest: Manifest = toml::from_str(&contents)
+            .map_err(|e| format!("Invalid plugin manifest {}: {}", path.display(), e))?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        for filter in manifest.filter {
+            match (filter.command, filter.library) {
+                (Some(command), None) if !command.is_empty() => self
+                    .filters
+                    .push(Box::new(CommandFilter::new(command, jobs))),
+                (None, Some(library)) => self
+                    .filters
+                    .push(Box::new(NativePlugin::load(&base.join(library))?)),
+                _ => {
+                    return Err(format!(
+                    "Invalid plugin manifest {}: each filter needs either a command or a library",
+                    path.display()
+                ))
+                }
+            }
+        }
+        for action in manifest.action {
+            if action.command.is_empty() {
+                return Err(format!(
+                    "Invalid plugin manifest {}: an action has an empty command",
+                    path.display()
+                ));
+            }
+            self.actions.push(Action::exec(&action.command)?);
+        }
+        Ok(())
+    }
+}
+
+/// A declarative plugin, see the module documentation
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    #[serde(default)]
+    filter: Vec<ManifestFilter>,
+    #[serde(default)]
+    action: Vec<ManifestAction>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestFilter {
+    command: Option<Vec<String>>,
+    library: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestAction {
+    command: Vec<String>,
+}
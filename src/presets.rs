@@ -0,0 +1,84 @@
+//! Curated lists behind rfind's presets: the directories pruned by
+//! `--no-dev-clutter` and the extension groups of `--preset`. Each can be
+//! replaced in the config file.
+//!
+//! `--preset security` is the exception: rather than extensions, it keeps
+//! entries with risky permissions (see [`crate::permissions`]).
+
+/// Directory names pruned by `--no-dev-clutter`: dependencies, build output,
+/// caches and version control metadata, which rarely hold what you are after
+pub const DEV_CLUTTER: &[&str] = &[
+    "node_modules",
+    "bower_components",
+    "target",
+    "build",
+    "dist",
+    "out",
+    ".venv",
+    "venv",
+    "__pycache__",
+    ".pytest_cache",
+    ".mypy_cache",
+    ".tox",
+    ".gradle",
+    ".next",
+    ".nuxt",
+    ".cache",
+    ".git",
+    ".hg",
+    ".svn",
+];
+
+/// The preset matching risky permissions instead of extensions
+pub const SECURITY: &str = "security";
+
+/// Extension groups for `--preset`, by name. Extensions are lowercase and
+/// matched case-insensitively.
+pub const EXTENSION_GROUPS: &[(&str, &[&str])] = &[
+    (
+        "images",
+        &[
+            "jpg", "jpeg", "png", "gif", "bmp", "tif", "tiff", "webp", "heic", "heif", "avif",
+            "svg", "ico", "psd", "raw", "dng", "cr2", "cr3", "nef", "nrw", "arw", "srf", "sr2",
+            "orf", "rw2", "raf", "pef", "srw", "x3f", "3fr", "erf", "kdc", "mrw",
+        ],
+    ),
+    (
+        "videos",
+        &[
+            "mp4", "m4v", "mkv", "mov", "avi", "wmv", "flv", "webm", "mpg", "mpeg", "m2ts", "mts",
+            "3gp", "ogv", "vob",
+        ],
+    ),
+    (
+        "audio",
+        &[
+            "mp3", "m4a", "aac", "flac", "wav", "ogg", "oga", "opus", "wma", "aiff", "aif", "alac",
+            "ape", "mid", "midi",
+        ],
+    ),
+    (
+        "documents",
+        &[
+            "pdf", "doc", "docx", "odt", "rtf", "txt", "md", "tex", "xls", "xlsx", "ods", "csv",
+            "ppt", "pptx", "odp", "pages", "numbers", "key", "epub",
+        ],
+    ),
+    (
+        "code",
+        &[
+            "rs", "c", "h", "cc", "cpp", "cxx", "hpp", "hh", "cs", "go", "java", "kt", "kts",
+            "scala", "swift", "m", "mm", "py", "rb", "php", "pl", "lua", "js", "jsx", "mjs", "cjs",
+            "ts", "tsx", "vue", "svelte", "sh", "bash", "zsh", "fish", "ps1", "sql", "r", "jl",
+            "hs", "ml", "ex", "exs", "erl", "clj", "dart", "zig", "nim",
+        ],
+    ),
+];
+
+/// The built-in extensions of the group `name`
+pub fn extension_group(name: &str) -> Option<&'static [&'static str]> {
+    EXTENSION_GROUPS
+        .iter()
+        .find(|(group, _)| *group == name)
+        .map(|(_, extensions)| *extensions)
+}
@@ -0,0 +1,572 @@
+//! A small SQL-like front end, e.g.
+//!
+//! ```text
+//! SELECT path, size FROM ~/code WHERE ext = 'rs' AND mtime > now() - 7d ORDER BY size DESC LIMIT 20
+//! ```
+//!
+//! The WHERE clause is translated into a [`WhereFilter`] expression and the
+//! query runs on the regular parallel scanner.
+
+use crate::filters::{SizeUnit, WhereFilter};
+use crate::format::{format_size, format_time, FieldFormat};
+use crate::scanner::SearchOptions;
+use std::cmp::Ordering;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// A column that can be selected or ordered by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Path,
+    Name,
+    Ext,
+    Type,
+    Size,
+    Mtime,
+}
+
+impl Column {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "path" => Ok(Column::Path),
+            "name" => Ok(Column::Name),
+            "ext" => Ok(Column::Ext),
+            "type" => Ok(Column::Type),
+            "size" => Ok(Column::Size),
+            "mtime" => Ok(Column::Mtime),
+            other => Err(format!(
+                "Unknown column '{}'. Use path, name, ext, type, size or mtime",
+                other
+            )),
+        }
+    }
+}
+
+/// A matching entry together with its metadata
+#[derive(Debug)]
+pub struct Row {
+    pub path: PathBuf,
+    pub metadata: Option<Metadata>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Text(String),
+    Number(i64),
+}
+
+impl Row {
+    fn value(&self, column: Column) -> Value {
+        let text = |s: Option<&std::ffi::OsStr>| {
+            Value::Text(
+                s.map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            )
+        };
+        match column {
+            Column::Path => Value::Text(self.path.display().to_string()),
+            Column::Name => text(self.path.file_name()),
+            Column::Ext => text(self.path.extension()),
+            Column::Type => Value::Text(
+                match self.metadata.as_ref().map(|m| m.file_type()) {
+                    Some(t) if t.is_file() => "file",
+                    Some(t) if t.is_dir() => "dir",
+                    Some(t) if t.is_symlink() => "symlink",
+                    _ => "other",
+                }
+                .to_string(),
+            ),
+            Column::Size => Value::Number(self.metadata.as_ref().map_or(0, |m| m.len() as i64)),
+            Column::Mtime => Value::Number(
+                self.metadata
+                    .as_ref()
+                    .and_then(|m| m.modified().ok())
+                    .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+                    .map_or(0, |d| d.as_secs() as i64),
+            ),
+        }
+    }
+}
+
+/// A parsed query
+#[derive(Debug, Clone)]
+pub struct Query {
+    pub columns: Vec<Column>,
+    pub from: PathBuf,
+    /// WHERE clause translated to a `--where` expression
+    pub filter: Option<String>,
+    /// Sort keys, `true` meaning descending
+    pub order_by: Vec<(Column, bool)>,
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    /// Parse a query of the form
+    /// `SELECT cols FROM dir [WHERE cond] [ORDER BY col [ASC|DESC], ...] [LIMIT n]`
+    pub fn parse(sql: &str) -> Result<Self, String> {
+        let mut parser = Parser::new(sql);
+
+        parser.expect_keyword("SELECT")?;
+        let mut columns = Vec::new();
+        loop {
+            match parser.next_token()? {
+                Some(Token::Symbol("*")) => {
+                    columns.extend([Column::Path, Column::Type, Column::Size, Column::Mtime])
+                }
+                Some(Token::Ident(name)) => columns.push(Column::parse(&name)?),
+                other => return Err(format!("Expected a column, found {}", describe(&other))),
+            }
+            if !parser.eat_symbol(",")? {
+                break;
+            }
+        }
+
+        parser.expect_keyword("FROM")?;
+        let from = expand_home(&parser.next_path()?);
+
+        let mut query = Query {
+            columns,
+            from,
+            filter: None,
+            order_by: Vec::new(),
+            limit: None,
+        };
+
+        if parser.eat_keyword("WHERE")? {
+            query.filter = Some(parser.or_expr()?);
+        }
+
+        if parser.eat_keyword("ORDER")? {
+            parser.expect_keyword("BY")?;
+            loop {
+                let column = match parser.next_token()? {
+                    Some(Token::Ident(name)) => Column::parse(&name)?,
+                    other => return Err(format!("Expected a column, found {}", describe(&other))),
+                };
+                let descending = if parser.eat_keyword("DESC")? {
+                    true
+                } else {
+                    parser.eat_keyword("ASC")?;
+                    false
+                };
+                query.order_by.push((column, descending));
+                if !parser.eat_symbol(",")? {
+                    break;
+                }
+            }
+        }
+
+        if parser.eat_keyword("LIMIT")? {
+            query.limit = match parser.next_token()? {
+                Some(Token::Number(n, None)) if n >= 0 => Some(n as usize),
+                other => return Err(format!("Expected a number, found {}", describe(&other))),
+            };
+        }
+
+        match parser.next_token()? {
+            None => Ok(query),
+            other => Err(format!("Unexpected {}", describe(&other))),
+        }
+    }
+
+    /// Build the scanner options for this query
+    pub fn search_options(&self) -> Result<SearchOptions, String> {
+        let mut options = SearchOptions::new("", self.from.clone());
+        if let Some(filter) = &self.filter {
+            options.filter = Arc::new(WhereFilter::parse(filter)?);
+        }
+        Ok(options)
+    }
+
+    /// Collect, order and limit the matches produced by a search
+    pub fn rows(&self, matches: impl Iterator<Item = PathBuf>) -> Vec<Row> {
+        let to_row = |path: PathBuf| Row {
+            metadata: std::fs::symlink_metadata(&path).ok(),
+            path,
+        };
+
+        if self.order_by.is_empty() {
+            return matches
+                .take(self.limit.unwrap_or(usize::MAX))
+                .map(to_row)
+                .collect();
+        }
+
+        let mut rows: Vec<Row> = matches.map(to_row).collect();
+        rows.sort_by(|a, b| {
+            self.order_by
+                .iter()
+                .map(|&(column, descending)| {
+                    let ordering = compare(&a.value(column), &b.value(column));
+                    if descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                })
+                .find(|o| *o != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+        if let Some(limit) = self.limit {
+            rows.truncate(limit);
+        }
+        rows
+    }
+
+    /// Format the selected columns of a row, separated by tabs
+    pub fn format_row(&self, row: &Row, format: &FieldFormat) -> String {
+        let now = SystemTime::now();
+        self.columns
+            .iter()
+            .map(|&column| match row.value(column) {
+                Value::Text(s) => s,
+                Value::Number(n) if column == Column::Size => format_size(n as u64, format.size),
+                Value::Number(n) if column == Column::Mtime => {
+                    let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(n as u64);
+                    format_time(mtime, now, &format.time)
+                }
+                Value::Number(n) => n.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\t")
+    }
+}
+
+fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.cmp(b),
+        (Value::Text(a), Value::Text(b)) => a.cmp(b),
+        (Value::Number(_), Value::Text(_)) => Ordering::Less,
+        (Value::Text(_), Value::Number(_)) => Ordering::Greater,
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    if let Some(rest) = path.strip_prefix('~') {
+        if rest.is_empty() || rest.starts_with('/') {
+            if let Some(dirs) = directories_next::BaseDirs::new() {
+                return dirs.home_dir().join(rest.trim_start_matches('/'));
+            }
+        }
+    }
+    Path::new(path).to_path_buf()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    /// A number with an optional unit suffix (e.g. `7d`, `10MB`)
+    Number(i64, Option<String>),
+    Symbol(&'static str),
+}
+
+fn describe(token: &Option<Token>) -> String {
+    match token {
+        None => "end of query".to_string(),
+        Some(Token::Ident(s)) => format!("'{}'", s),
+        Some(Token::Str(s)) => format!("'{}'", s),
+        Some(Token::Number(n, unit)) => format!("'{}{}'", n, unit.as_deref().unwrap_or("")),
+        Some(Token::Symbol(s)) => format!("'{}'", s),
+    }
+}
+
+const SYMBOLS: [&str; 13] = [
+    "<=", ">=", "<>", "!=", "=", "<", ">", "(", ")", ",", "+", "-", "*",
+];
+
+/// Variables the WHERE clause may reference (see [`WhereFilter`])
+const FIELDS: [&str; 12] = [
+    "name",
+    "path",
+    "ext",
+    "size",
+    "mtime",
+    "age_secs",
+    "age_days",
+    "is_file",
+    "is_dir",
+    "is_symlink",
+    "true",
+    "false",
+];
+
+/// Recursive descent parser that also translates the WHERE clause to a
+/// `--where` expression
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    peeked: Option<Option<Token>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Parser {
+            input,
+            pos: 0,
+            peeked: None,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn lex(&mut self) -> Result<Option<Token>, String> {
+        self.skip_whitespace();
+        let rest = &self.input[self.pos..];
+        let c = match rest.chars().next() {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+
+        if c == '\'' {
+            let mut value = String::new();
+            let mut chars = rest.char_indices().skip(1).peekable();
+            while let Some((i, c)) = chars.next() {
+                if c == '\'' {
+                    // '' is an escaped quote
+                    if let Some((_, '\'')) = chars.peek() {
+                        chars.next();
+                        value.push('\'');
+                        continue;
+                    }
+                    self.pos += i + 1;
+                    return Ok(Some(Token::Str(value)));
+                }
+                value.push(c);
+            }
+            return Err("Unterminated string literal".to_string());
+        }
+
+        if c.is_ascii_digit() {
+            let digits = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let unit_len = rest[digits..]
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len() - digits);
+            let value = rest[..digits]
+                .parse::<i64>()
+                .map_err(|_| format!("Invalid number '{}'", &rest[..digits]))?;
+            let unit = rest[digits..digits + unit_len].to_string();
+            self.pos += digits + unit_len;
+            return Ok(Some(Token::Number(
+                value,
+                if unit.is_empty() { None } else { Some(unit) },
+            )));
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            self.pos += len;
+            return Ok(Some(Token::Ident(rest[..len].to_string())));
+        }
+
+        for symbol in SYMBOLS {
+            if rest.starts_with(symbol) {
+                self.pos += symbol.len();
+                return Ok(Some(Token::Symbol(symbol)));
+            }
+        }
+
+        Err(format!("Unexpected character '{}'", c))
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, String> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.lex(),
+        }
+    }
+
+    fn peek(&mut self) -> Result<&Option<Token>, String> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.lex()?);
+        }
+        Ok(self.peeked.as_ref().unwrap())
+    }
+
+    /// Read a directory: either a quoted string or everything up to the next whitespace
+    fn next_path(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        if self.input[self.pos..].starts_with('\'') {
+            return match self.next_token()? {
+                Some(Token::Str(s)) => Ok(s),
+                _ => unreachable!(),
+            };
+        }
+        let rest = &self.input[self.pos..];
+        let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        if len == 0 {
+            return Err("Expected a directory after FROM".to_string());
+        }
+        self.pos += len;
+        Ok(rest[..len].to_string())
+    }
+
+    fn is_keyword(token: &Option<Token>, keyword: &str) -> bool {
+        matches!(token, Some(Token::Ident(s)) if s.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> Result<bool, String> {
+        if Self::is_keyword(self.peek()?, keyword) {
+            self.peeked = None;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        if self.eat_keyword(keyword)? {
+            Ok(())
+        } else {
+            Err(format!(
+                "Expected {}, found {}",
+                keyword,
+                describe(self.peek()?)
+            ))
+        }
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> Result<bool, String> {
+        if matches!(self.peek()?, Some(Token::Symbol(s)) if *s == symbol) {
+            self.peeked = None;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn or_expr(&mut self) -> Result<String, String> {
+        let mut expr = self.and_expr()?;
+        while self.eat_keyword("OR")? {
+            expr = format!("({} || {})", expr, self.and_expr()?);
+        }
+        Ok(expr)
+    }
+
+    fn and_expr(&mut self) -> Result<String, String> {
+        let mut expr = self.not_expr()?;
+        while self.eat_keyword("AND")? {
+            expr = format!("({} && {})", expr, self.not_expr()?);
+        }
+        Ok(expr)
+    }
+
+    fn not_expr(&mut self) -> Result<String, String> {
+        if self.eat_keyword("NOT")? {
+            return Ok(format!("!{}", self.not_expr()?));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<String, String> {
+        let lhs = self.additive()?;
+
+        let negated = self.eat_keyword("NOT")?;
+        if self.eat_keyword("LIKE")? {
+            let like = format!("like({}, {})", lhs, self.additive()?);
+            return Ok(if negated { format!("!{}", like) } else { like });
+        }
+        if negated {
+            return Err("Expected LIKE after NOT".to_string());
+        }
+
+        let op = match self.peek()? {
+            Some(Token::Symbol("=")) => "==",
+            Some(Token::Symbol("<>")) | Some(Token::Symbol("!=")) => "!=",
+            Some(Token::Symbol("<")) => "<",
+            Some(Token::Symbol("<=")) => "<=",
+            Some(Token::Symbol(">")) => ">",
+            Some(Token::Symbol(">=")) => ">=",
+            _ => return Ok(lhs),
+        };
+        self.peeked = None;
+        Ok(format!("({} {} {})", lhs, op, self.additive()?))
+    }
+
+    fn additive(&mut self) -> Result<String, String> {
+        let mut expr = self.term()?;
+        loop {
+            let op = if self.eat_symbol("+")? {
+                "+"
+            } else if self.eat_symbol("-")? {
+                "-"
+            } else {
+                return Ok(expr);
+            };
+            expr = format!("({} {} {})", expr, op, self.term()?);
+        }
+    }
+
+    fn term(&mut self) -> Result<String, String> {
+        let mut expr = self.factor()?;
+        while self.eat_symbol("*")? {
+            expr = format!("({} * {})", expr, self.factor()?);
+        }
+        Ok(expr)
+    }
+
+    fn factor(&mut self) -> Result<String, String> {
+        match self.next_token()? {
+            Some(Token::Symbol("(")) => {
+                let expr = self.or_expr()?;
+                if !self.eat_symbol(")")? {
+                    return Err(format!("Expected ')', found {}", describe(self.peek()?)));
+                }
+                Ok(expr)
+            }
+            Some(Token::Symbol("-")) => Ok(format!("(-{})", self.factor()?)),
+            Some(Token::Number(n, unit)) => {
+                let multiplier = match unit.as_deref() {
+                    None => 1,
+                    Some(unit) => unit_multiplier(unit)?,
+                };
+                Ok(n.checked_mul(multiplier)
+                    .ok_or_else(|| "Number too large".to_string())?
+                    .to_string())
+            }
+            Some(Token::Str(s)) => Ok(format!(
+                "\"{}\"",
+                s.replace('\\', "\\\\").replace('"', "\\\"")
+            )),
+            Some(Token::Ident(name)) => {
+                let lower = name.to_ascii_lowercase();
+                if lower == "now" {
+                    if self.eat_symbol("(")? && !self.eat_symbol(")")? {
+                        return Err("now() takes no arguments".to_string());
+                    }
+                    return Ok("now".to_string());
+                }
+                if FIELDS.contains(&lower.as_str()) {
+                    Ok(lower)
+                } else {
+                    Err(format!("Unknown field '{}'", name))
+                }
+            }
+            other => Err(format!("Unexpected {}", describe(&other))),
+        }
+    }
+}
+
+/// Seconds for duration suffixes and bytes for size suffixes, which are
+/// those of `--size`
+fn unit_multiplier(unit: &str) -> Result<i64, String> {
+    match unit {
+        "s" => Ok(1),
+        "m" => Ok(60),
+        "h" => Ok(60 * 60),
+        "d" => Ok(24 * 60 * 60),
+        "w" => Ok(7 * 24 * 60 * 60),
+        other => SizeUnit::from_suffix(other)
+            .map(|unit| unit.bytes() as i64)
+            .ok_or_else(|| format!("Unknown unit '{}'", other)),
+    }
+}
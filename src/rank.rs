@@ -0,0 +1,50 @@
+//! Bounded selection of the top matches by some key, for `--largest` and
+//! friends. Only the best `limit` matches seen so far are kept, so memory
+//! stays constant however many entries match.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::path::PathBuf;
+
+/// The `limit` matches with the greatest keys. Ties keep the match seen first.
+#[derive(Debug, Clone)]
+pub struct TopN<K: Ord> {
+    limit: usize,
+    seen: u64,
+    /// A min-heap, so the weakest kept match is the one to evict
+    heap: BinaryHeap<Reverse<(K, Reverse<u64>, PathBuf)>>,
+}
+
+impl<K: Ord> TopN<K> {
+    pub fn new(limit: usize) -> Self {
+        TopN {
+            limit,
+            seen: 0,
+            heap: BinaryHeap::with_capacity(limit.saturating_add(1).min(1024)),
+        }
+    }
+
+    pub fn push(&mut self, key: K, path: PathBuf) {
+        self.seen += 1;
+        if self.limit == 0 {
+            return;
+        }
+        let candidate = Reverse((key, Reverse(self.seen), path));
+        if self.heap.len() < self.limit {
+            self.heap.push(candidate);
+        } else if self.heap.peek().is_some_and(|weakest| candidate < *weakest) {
+            self.heap.pop();
+            self.heap.push(candidate);
+        }
+    }
+
+    /// The kept matches, greatest key first
+    pub fn into_sorted_vec(self) -> Vec<(K, PathBuf)> {
+        // Ascending order of Reverse is descending order of the keys
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((key, _, path))| (key, path))
+            .collect()
+    }
+}
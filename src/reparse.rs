@@ -0,0 +1,44 @@
+//! Classification of links, including the Windows reparse points that behave
+//! like them.
+//!
+//! NTFS junctions and directory mount points redirect to another directory
+//! just like a directory symlink does, so rfind treats all of them the same
+//! way: they are matched as symlinks and only traversed according to
+//! `-P`/`-H`/`-L`. Other reparse points, such as OneDrive placeholders or
+//! deduplicated files, are ordinary files and directories.
+//!
+//! The reparse tag is inspected through [`std::fs::read_link`], which only
+//! succeeds for the symlink and mount-point (junction) tags. On other
+//! platforms only symlinks are links.
+
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+
+/// `FILE_ATTRIBUTE_REPARSE_POINT`
+#[cfg(windows)]
+const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+
+/// Whether the entry at `path` is a symlink, junction or mount point, given
+/// its metadata (not following links)
+pub fn is_link(path: &Path, metadata: &Metadata) -> bool {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        metadata.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT != 0
+            && std::fs::read_link(path).is_ok()
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = path;
+        metadata.file_type().is_symlink()
+    }
+}
+
+/// Key identifying the directory a link leads to, for loop detection. Volume
+/// mount points often have no drive letter to canonicalize to, so their
+/// `\\?\Volume{GUID}\` target is used instead.
+pub fn target_key(path: &Path) -> Option<PathBuf> {
+    path.canonicalize()
+        .ok()
+        .or_else(|| std::fs::read_link(path).ok())
+}
@@ -0,0 +1,203 @@
+//! JSON-RPC 2.0 over stdio for editor integrations, framed like LSP messages
+//! (`Content-Length: N\r\n\r\n<json>`).
+//!
+//! * `search` request with params `{pattern, dir?, type?, size?, mtime?, max_depth?, limit?}`:
+//!   every match is sent as a `search/result` notification `{id, path}`, and the
+//!   request is answered with `{count, cancelled}` once the search finishes.
+//! * `cancel` request (or `$/cancelRequest` notification) with params `{id}` stops
+//!   the search started by request `id`.
+//! * `shutdown` request cancels all searches; the `exit` notification ends the session.
+
+use crate::scanner::{CancelToken, Search, SearchOptions};
+use crate::server::{request_filter, ServerOptions};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+type Output = Arc<Mutex<Box<dyn Write + Send>>>;
+
+/// Read one framed message, returning `None` at end of input
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length.unwrap_or(0)];
+    input.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+fn send(output: &Output, message: &Value) {
+    let body = message.to_string();
+    let mut output = output.lock();
+    let _ = write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body);
+    let _ = output.flush();
+}
+
+fn send_error(output: &Output, id: Value, code: i64, message: &str) {
+    send(
+        output,
+        &json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message },
+        }),
+    );
+}
+
+fn search_options(
+    params: &Value,
+    options: &ServerOptions,
+) -> Result<(SearchOptions, usize), String> {
+    let get = |key: &str| params.get(key).and_then(Value::as_str);
+
+    let pattern = get("pattern").ok_or("Missing pattern parameter")?;
+    let root = get("dir")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| options.root.clone());
+
+    let mut search_options = SearchOptions::new(pattern, root);
+    search_options.threads = options.threads;
+    if let Some(depth) = params.get("max_depth").and_then(Value::as_u64) {
+        search_options.max_depth = depth as usize;
+    }
+    search_options.filter = Arc::new(request_filter(get("type"), get("size"), get("mtime"))?);
+
+    let limit = params
+        .get("limit")
+        .and_then(Value::as_u64)
+        .map_or(usize::MAX, |limit| limit as usize);
+    Ok((search_options, limit))
+}
+
+/// Serve requests from `input` until `exit` or end of input
+pub fn run(
+    mut input: impl BufRead,
+    output: impl Write + Send + 'static,
+    options: ServerOptions,
+) -> io::Result<()> {
+    let output: Output = Arc::new(Mutex::new(Box::new(output)));
+    let searches: Arc<Mutex<HashMap<String, CancelToken>>> = Arc::default();
+
+    while let Some(body) = read_message(&mut input)? {
+        let message: Value = match serde_json::from_slice(&body) {
+            Ok(message) => message,
+            Err(e) => {
+                send_error(&output, Value::Null, PARSE_ERROR, &e.to_string());
+                continue;
+            }
+        };
+
+        let id = message.get("id").cloned();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let method = message.get("method").and_then(Value::as_str).unwrap_or("");
+
+        match (method, id) {
+            ("search", Some(id)) => {
+                let (search_options, limit) = match search_options(&params, &options) {
+                    Ok(parsed) => parsed,
+                    Err(e) => {
+                        send_error(&output, id, INVALID_PARAMS, &e);
+                        continue;
+                    }
+                };
+
+                let search = match Search::start(search_options) {
+                    Ok(search) => search,
+                    Err(e) => {
+                        send_error(&output, id, INVALID_PARAMS, &e.to_string());
+                        continue;
+                    }
+                };
+                let cancel = search.cancel_token();
+                searches.lock().insert(id.to_string(), cancel.clone());
+
+                let output = Arc::clone(&output);
+                let searches = Arc::clone(&searches);
+                thread::spawn(move || {
+                    let mut count = 0;
+                    for path in search.take(limit) {
+                        count += 1;
+                        send(
+                            &output,
+                            &json!({
+                                "jsonrpc": "2.0",
+                                "method": "search/result",
+                                "params": { "id": id, "path": path.to_string_lossy() },
+                            }),
+                        );
+                    }
+                    searches.lock().remove(&id.to_string());
+                    send(
+                        &output,
+                        &json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "result": { "count": count, "cancelled": cancel.is_cancelled() },
+                        }),
+                    );
+                });
+            }
+            ("cancel", _) | ("$/cancelRequest", _) => {
+                let target = params.get("id").map(Value::to_string).unwrap_or_default();
+                let found = match searches.lock().get(&target) {
+                    Some(cancel) => {
+                        cancel.cancel();
+                        true
+                    }
+                    None => false,
+                };
+                if let Some(id) = message.get("id") {
+                    send(
+                        &output,
+                        &json!({ "jsonrpc": "2.0", "id": id, "result": { "cancelled": found } }),
+                    );
+                }
+            }
+            ("shutdown", id) => {
+                for cancel in searches.lock().values() {
+                    cancel.cancel();
+                }
+                if let Some(id) = id {
+                    send(
+                        &output,
+                        &json!({ "jsonrpc": "2.0", "id": id, "result": null }),
+                    );
+                }
+            }
+            ("exit", _) => break,
+            (_, Some(id)) => send_error(&output, id, METHOD_NOT_FOUND, "Unknown method"),
+            // Unknown notifications are ignored
+            (_, None) => {}
+        }
+    }
+
+    for cancel in searches.lock().values() {
+        cancel.cancel();
+    }
+    Ok(())
+}
@@ -0,0 +1,44 @@
+//! Saved searches for `rfind save-search` and `rfind run`.
+//!
+//! Every saved search is the full argument list it was saved with, stored by
+//! name in `~/.rfind/searches.json`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Saved argument lists, by name
+pub type SavedSearches = BTreeMap<String, Vec<String>>;
+
+/// File holding the saved searches (`~/.rfind/searches.json`)
+pub fn searches_file() -> io::Result<PathBuf> {
+    directories_next::BaseDirs::new()
+        .map(|dirs| dirs.home_dir().join(".rfind").join("searches.json"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No home directory"))
+}
+
+/// Read every saved search; a missing file means none have been saved yet
+pub fn load(path: &Path) -> Result<SavedSearches, String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| format!("Invalid saved searches in {}: {}", path.display(), e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(SavedSearches::new()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Save `args` under `name`, replacing any search saved with that name before
+pub fn save(path: &Path, name: &str, args: &[String]) -> Result<(), String> {
+    if name.is_empty() || name.starts_with('-') || name.chars().any(char::is_whitespace) {
+        return Err(format!("Invalid search name '{}'", name));
+    }
+    let mut searches = load(path)?;
+    searches.insert(name.to_string(), args.to_vec());
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let contents = serde_json::to_string_pretty(&searches).map_err(|e| e.to_string())?;
+    fs::write(path, contents).map_err(|e| e.to_string())
+}
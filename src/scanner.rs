@@ -0,0 +1,1969 @@
+//! The parallel directory scanner behind the `rfind` binary.
+
+use crate::alias;
+use crate::archive;
+use crate::checkpoint::{Checkpoint, PendingDir};
+use crate::filters::{AllOf, EntryFilter, EntryInfo};
+use crate::memory::{self, MemoryBudget, VisitedSet};
+use crate::placeholder;
+use crate::reparse;
+use crossbeam_channel::{bounded, unbounded, Receiver, RecvTimeoutError, Sender, TrySendError};
+use glob::Pattern;
+use log::debug;
+use memchr::memmem::FinderBuilder; // Uses Boyer-Moore-Horspool algorithm for substring search
+use parking_lot::Mutex;
+use pathdiff::diff_paths;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::error::Error;
+use std::ffi::OsString;
+use std::io;
+use std::path::Path;
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    Arc,
+};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use std::{collections::HashSet, path::PathBuf};
+
+#[derive(Default, Debug, Clone, Copy)]
+pub enum SymlinkMode {
+    #[default]
+    Never, // -P: Never follow symlinks
+    Command, // -H: Follow symlinks on command line only
+    Always,  // -L: Follow all symlinks
+}
+
+pub enum PatternMatcher {
+    Glob(Pattern),
+    /// Case-insensitive substring, optionally anchored with `^` and/or `$`
+    Substring {
+        pattern_bytes: Box<[u8]>,
+        anchor_start: bool,
+        anchor_end: bool,
+    },
+    /// Matches names the inner pattern does not, from a leading `!`
+    Negated(Box<PatternMatcher>),
+    /// Matches names any of the patterns matches
+    AnyOf(Vec<PatternMatcher>),
+    /// Regular expression searched for anywhere in the name
+    Regex(Regex),
+}
+
+impl PatternMatcher {
+    pub fn matches(&self, filename: &str) -> bool {
+        match self {
+            PatternMatcher::Glob(pattern) => pattern.matches(filename),
+            PatternMatcher::Substring {
+                pattern_bytes,
+                anchor_start,
+                anchor_end,
+            } => {
+                let filename_lower = filename.to_lowercase();
+                let name = filename_lower.as_bytes();
+                match (anchor_start, anchor_end) {
+                    (true, true) => name == &pattern_bytes[..],
+                    (true, false) => name.starts_with(pattern_bytes),
+                    (false, true) => name.ends_with(pattern_bytes),
+                    (false, false) => FinderBuilder::new()
+                        .build_forward(pattern_bytes)
+                        .find(name)
+                        .is_some(),
+                }
+            }
+            PatternMatcher::Negated(inner) => !inner.matches(filename),
+            PatternMatcher::AnyOf(patterns) => {
+                patterns.iter().any(|pattern| pattern.matches(filename))
+            }
+            PatternMatcher::Regex(regex) => regex.is_match(filename),
+        }
+    }
+}
+
+/// Matcher for a glob or substring pattern. Panics on an invalid glob; see
+/// [`PatternSyntax::matcher`] for a checked version.
+pub fn create_pattern_matcher(pattern: &str) -> PatternMatcher {
+    if let Some(rest) = pattern.strip_prefix('!') {
+        PatternMatcher::Negated(Box::new(create_pattern_matcher(rest)))
+    } else if pattern.contains('*') || pattern.contains('?') {
+        PatternMatcher::Glob(Pattern::new(pattern).expect("Invalid glob pattern"))
+    } else {
+        // ^ and $ anchor the substring to the start and end of the name
+        let (anchor_start, pattern) = match pattern.strip_prefix('^') {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+        let (anchor_end, pattern) = match pattern.strip_suffix('$') {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+        let pattern_lower = pattern.to_lowercase();
+        let pattern_bytes = pattern_lower.as_bytes().to_vec().into_boxed_slice();
+
+        PatternMatcher::Substring {
+            pattern_bytes,
+            anchor_start,
+            anchor_end,
+        }
+    }
+}
+
+/// Read patterns in `syntax` from a file with one per line, as for
+/// `--patterns-from`. Blank lines and lines starting with `#` are skipped,
+/// and surrounding whitespace is trimmed.
+pub fn read_patterns(path: &Path, syntax: PatternSyntax) -> std::io::Result<Vec<String>> {
+    let mut patterns = Vec::new();
+    for (number, line) in std::fs::read_to_string(path)?.lines().enumerate() {
+        let pattern = line.trim();
+        if pattern.is_empty() || pattern.starts_with('#') {
+            continue;
+        }
+        if let Err(e) = syntax.validate(pattern) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("line {}: {}", number + 1, e),
+            ));
+        }
+        patterns.push(pattern.to_string());
+    }
+    Ok(patterns)
+}
+
+/// The filesystem operation that failed while scanning
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanOperation {
+    /// Listing a directory
+    ReadDir,
+    /// Reading an entry's metadata
+    Stat,
+    /// Resolving a symlink that was to be followed
+    FollowSymlink,
+    /// Listing the members of an archive (`--search-archives`)
+    ReadArchive,
+    /// The path was deliberately skipped (e.g. a system directory)
+    Skip,
+    /// The directory was skipped for holding more than `max_dir_entries` entries
+    LargeDirectory,
+}
+
+impl std::fmt::Display for ScanOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            ScanOperation::ReadDir => "read_dir",
+            ScanOperation::Stat => "stat",
+            ScanOperation::FollowSymlink => "follow_symlink",
+            ScanOperation::ReadArchive => "read_archive",
+            ScanOperation::Skip => "skip",
+            ScanOperation::LargeDirectory => "large_directory",
+        })
+    }
+}
+
+/// A path that could not be scanned, and why
+#[derive(Debug, Clone)]
+pub struct ScanError {
+    pub path: PathBuf,
+    pub operation: ScanOperation,
+    /// `None` for deliberate skips
+    pub kind: Option<io::ErrorKind>,
+    pub message: String,
+    /// How many times the operation was tried again after transient errors
+    /// before giving up
+    pub retries: u32,
+}
+
+impl std::fmt::Display for ScanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {}: {}",
+            self.operation,
+            self.path.display(),
+            self.message
+        )?;
+        if self.retries > 0 {
+            write!(f, " (after {} retries)", self.retries)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ScanError {}
+
+/// Callback invoked from scanner threads for every [`ScanError`]
+pub type ErrorHandler = Arc<dyn Fn(&ScanError) + Send + Sync>;
+
+/// Totals for a search, see [`Search::stats`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ScanStats {
+    /// Directories listed successfully
+    pub dirs_scanned: u64,
+    /// Entries examined in those directories
+    pub entries_scanned: u64,
+    /// Failed filesystem operations
+    pub errors: u64,
+    /// Paths deliberately left out, such as system paths
+    pub skipped: u64,
+}
+
+/// Counters behind [`ScanStats`], shared by all scanner threads
+#[derive(Debug, Default)]
+struct StatsCounters {
+    dirs_scanned: AtomicU64,
+    entries_scanned: AtomicU64,
+    errors: AtomicU64,
+    skipped: AtomicU64,
+}
+
+impl StatsCounters {
+    fn snapshot(&self) -> ScanStats {
+        ScanStats {
+            dirs_scanned: self.dirs_scanned.load(Ordering::Relaxed),
+            entries_scanned: self.entries_scanned.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            skipped: self.skipped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Forwards scan errors to the user supplied handler, if any, and counts them
+#[derive(Clone, Default)]
+struct ErrorReporter {
+    handler: Option<ErrorHandler>,
+    stats: Arc<StatsCounters>,
+    /// Errors tolerated before the search is cancelled
+    max_errors: Option<u64>,
+    cancel: CancelToken,
+}
+
+impl ErrorReporter {
+    fn report(&self, path: &Path, operation: ScanOperation, error: &io::Error) {
+        self.report_retried(path, operation, error, 0)
+    }
+
+    fn report_retried(
+        &self,
+        path: &Path,
+        operation: ScanOperation,
+        error: &io::Error,
+        retries: u32,
+    ) {
+        debug!("{} failed for {:?}: {}", operation, path, error);
+        let errors = self.stats.errors.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(handler) = &self.handler {
+            handler(&ScanError {
+                path: path.to_path_buf(),
+                operation,
+                kind: Some(error.kind()),
+                message: error.to_string(),
+                retries,
+            });
+        }
+        if self.max_errors.is_some_and(|max| errors > max) {
+            self.cancel.cancel();
+        }
+    }
+
+    fn skip(&self, path: &Path, reason: &str) {
+        self.skip_as(path, ScanOperation::Skip, reason)
+    }
+
+    fn skip_as(&self, path: &Path, operation: ScanOperation, reason: &str) {
+        debug!("Skipping {:?}: {}", path, reason);
+        self.stats.skipped.fetch_add(1, Ordering::Relaxed);
+        if let Some(handler) = &self.handler {
+            handler(&ScanError {
+                path: path.to_path_buf(),
+                operation,
+                kind: None,
+                message: reason.to_string(),
+                retries: 0,
+            });
+        }
+    }
+}
+
+/// A filesystem call handed to an [`FsGuard`] helper thread
+type FsJob = Box<dyn FnOnce() + Send>;
+
+/// Runs filesystem calls that may hang (e.g. on stale NFS or FUSE mounts),
+/// giving up after the configured timeout
+#[derive(Clone, Default)]
+struct FsGuard {
+    timeout: Option<Duration>,
+    timed_out: Arc<Mutex<Vec<PathBuf>>>,
+    /// Attempts made again after a transient error, see [`FsGuard::run_retrying`]
+    retries: u32,
+    /// Thread making the calls of one scanner thread when there is a
+    /// timeout, started on first use and replaced once a call hangs
+    helper: Arc<Mutex<Option<Sender<FsJob>>>>,
+}
+
+/// Pause before the first retry of a failed call, doubled for each one after
+const RETRY_BACKOFF: Duration = Duration::from_millis(10);
+
+/// Whether an error is likely to go away if the call is simply made again,
+/// as network filesystems often report under load
+fn is_transient(error: &io::Error) -> bool {
+    #[cfg(windows)]
+    {
+        // ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION
+        if matches!(error.raw_os_error(), Some(32) | Some(33)) {
+            return true;
+        }
+    }
+    matches!(
+        error.kind(),
+        io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock
+            | io::ErrorKind::StaleNetworkFileHandle
+    )
+}
+
+impl FsGuard {
+    /// A guard for another scanner thread, with a helper thread of its own
+    fn for_worker(&self) -> FsGuard {
+        FsGuard {
+            helper: Arc::default(),
+            ..self.clone()
+        }
+    }
+
+    /// Like [`FsGuard::run`], but tries again with a growing pause after
+    /// transient errors, up to the configured number of times. Returns how
+    /// many retries were made along with the final result.
+    fn run_retrying<T, F>(&self, path: &Path, op: F) -> (io::Result<T>, u32)
+    where
+        T: Send + 'static,
+        F: Fn() -> io::Result<T> + Send + Sync + 'static,
+    {
+        let op = Arc::new(op);
+        let mut retries = 0;
+        loop {
+            let result = self.run(path, {
+                let op = Arc::clone(&op);
+                move || op()
+            });
+            match result {
+                Err(e) if retries < self.retries && is_transient(&e) => {
+                    debug!("Retrying {:?} after: {}", path, e);
+                    thread::sleep(RETRY_BACKOFF * 2u32.pow(retries));
+                    retries += 1;
+                }
+                result => return (result, retries),
+            }
+        }
+    }
+
+    fn run<T, F>(&self, path: &Path, op: F) -> io::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> io::Result<T> + Send + 'static,
+    {
+        let timeout = match self.timeout {
+            Some(timeout) => timeout,
+            None => return op(),
+        };
+
+        let (tx, rx) = bounded(1);
+        self.submit(Box::new(move || {
+            let _ = tx.send(op());
+        }));
+        match rx.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(_) => {
+                // A hung call keeps its helper thread, which exits once the
+                // call returns; later calls go to a new one
+                self.helper.lock().take();
+                debug!("Timed out accessing {:?}", path);
+                self.timed_out.lock().push(path.to_path_buf());
+                Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "operation timed out",
+                ))
+            }
+        }
+    }
+
+    /// Hand `job` to the helper thread, starting one if there is none or the
+    /// last one has died
+    fn submit(&self, job: FsJob) {
+        let mut helper = self.helper.lock();
+        let job = match &*helper {
+            Some(jobs) => match jobs.send(job) {
+                Ok(()) => return,
+                Err(unsent) => unsent.0,
+            },
+            None => job,
+        };
+        let (jobs, queue) = unbounded::<FsJob>();
+        thread::spawn(move || {
+            for job in queue {
+                job();
+            }
+        });
+        let _ = jobs.send(job);
+        *helper = Some(jobs);
+    }
+}
+
+struct ScannerContext {
+    work: WorkUnit,
+    pattern: Arc<PatternMatcher>,
+    symlink_mode: SymlinkMode,
+    is_command_line: bool,                 // True for initial directory
+    visited_paths: Arc<Mutex<VisitedSet>>, // For loop detection
+    root_path: PathBuf,
+    entry_filter: Arc<dyn EntryFilter>,
+    now: SystemTime,
+    system_checker: Arc<SystemPathChecker>,
+    fs_guard: FsGuard,
+    errors: ErrorReporter,
+    search_archives: bool,
+    resolve_aliases: bool,
+    prune: Arc<HashSet<OsString>>,
+    exclude: Option<Arc<Exclusions>>,
+    cancel: CancelToken,
+}
+
+fn normalize_path(path: &Path, root: &Path) -> PathBuf {
+    if let Some(relative) = diff_paths(path, root) {
+        // Always use the root path and join with relative to preserve symlink paths
+        root.to_path_buf().join(relative)
+    } else {
+        // If diff_paths fails, return the original path
+        path.to_path_buf()
+    }
+}
+/// Represents a work unit for directory scanning
+#[derive(Debug, Clone)]
+struct WorkUnit {
+    path: PathBuf,
+    depth: usize,
+}
+
+/// Directories queued or being scanned, tracked only when checkpointing.
+/// A directory leaves the frontier once all of its results have been sent.
+#[derive(Default)]
+struct Frontier(Mutex<HashMap<PathBuf, usize>>);
+
+impl Frontier {
+    fn add(&self, work: &WorkUnit) {
+        self.0.lock().insert(work.path.clone(), work.depth);
+    }
+
+    fn done(&self, work: &WorkUnit) {
+        self.0.lock().remove(&work.path);
+    }
+
+    fn snapshot(&self) -> Vec<PendingDir> {
+        self.0
+            .lock()
+            .iter()
+            .map(|(path, &depth)| PendingDir {
+                path: path.clone(),
+                depth,
+            })
+            .collect()
+    }
+}
+
+struct ScannerChannels {
+    dir_tx: Sender<WorkUnit>,
+    result_tx: Sender<PathBuf>,
+    frontier: Option<Arc<Frontier>>,
+    /// Directories deeper than this are scanned by the worker that found them
+    fanout_depth: Option<usize>,
+    /// Directories kept by this worker, scanned once the current one is done
+    inline: RefCell<Vec<WorkUnit>>,
+}
+
+fn handle_directory(
+    path: PathBuf,
+    depth: usize,
+    ctx: &ScannerContext,
+    channels: &ScannerChannels,
+) -> Result<(), Box<dyn Error>> {
+    // Once cancelled, nothing more gets queued; with a checkpoint the
+    // directory must still be recorded so a resumed search visits it
+    if ctx.cancel.is_cancelled() && channels.frontier.is_none() {
+        return Ok(());
+    }
+    let work = WorkUnit {
+        path,
+        depth: depth + 1,
+    };
+    if let Some(frontier) = &channels.frontier {
+        frontier.add(&work);
+    }
+    if channels
+        .fanout_depth
+        .is_some_and(|fanout| work.depth > fanout)
+    {
+        channels.inline.borrow_mut().push(work);
+        return Ok(());
+    }
+    channels.dir_tx.send(work)?;
+    Ok(())
+}
+
+fn should_follow_symlink(ctx: &ScannerContext, is_command_path: bool) -> bool {
+    match ctx.symlink_mode {
+        SymlinkMode::Never => false,
+        SymlinkMode::Command => is_command_path,
+        SymlinkMode::Always => true,
+    }
+}
+
+/// The metadata of the target of the link at `path` when links are
+/// followed, so that filters test the target like find's tests under -L.
+/// Broken links are tested as themselves.
+fn followed_metadata(path: &Path, ctx: &ScannerContext) -> Option<std::fs::Metadata> {
+    if !should_follow_symlink(ctx, ctx.is_command_line) {
+        return None;
+    }
+    ctx.fs_guard
+        .run(path, {
+            let path = path.to_path_buf();
+            move || std::fs::metadata(path)
+        })
+        .ok()
+}
+
+/// Whether the directory at `path` is one the user asked to leave out entirely
+fn is_pruned(path: &Path, ctx: &ScannerContext) -> bool {
+    path.file_name()
+        .is_some_and(|name| ctx.prune.contains(name))
+}
+
+/// Patterns of paths left out entirely, matched against the path relative
+/// to the search root
+struct Exclusions {
+    root: PathBuf,
+    patterns: Vec<Regex>,
+}
+
+impl Exclusions {
+    fn matches(&self, path: &Path) -> bool {
+        // Paths reached through a followed symlink are matched in full
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        let relative = relative.to_string_lossy();
+        self.patterns
+            .iter()
+            .any(|pattern| pattern.is_match(&relative))
+    }
+}
+
+/// Whether the entry at `path` was excluded by the user, so that it is
+/// neither reported nor descended into
+fn is_excluded(path: &Path, ctx: &ScannerContext) -> bool {
+    ctx.exclude
+        .as_ref()
+        .is_some_and(|exclude| exclude.matches(path))
+}
+
+/// Checks if the file/directory/symlink should be recorded as a match
+/// based on the type, size and time filters provided by the user. The entry
+/// is at `disk_path` and reported as `path`.
+fn is_entry_match(
+    path: &Path,
+    disk_path: &Path,
+    metadata: &std::fs::Metadata,
+    ctx: &ScannerContext,
+) -> bool {
+    ctx.entry_filter.matches(&EntryInfo {
+        path,
+        disk_path,
+        metadata,
+        now: ctx.now,
+    })
+}
+
+fn handle_symlink(
+    path: &Path,
+    _file_type: std::fs::FileType,
+    ctx: &ScannerContext,
+    channels: &ScannerChannels,
+) -> Result<bool, Box<dyn Error>> {
+    if !should_follow_symlink(ctx, ctx.is_command_line) {
+        return Ok(false);
+    }
+
+    // Keep the original symlink path for directory traversal
+    let symlink_path = path.to_path_buf();
+
+    // Check for symlink loops using canonical paths
+    let canonical = ctx.fs_guard.run(path, {
+        let path = path.to_path_buf();
+        move || Ok(reparse::target_key(&path))
+    });
+    if let Ok(Some(canonical_path)) = canonical {
+        let mut visited = ctx.visited_paths.lock();
+        if !visited.insert(canonical_path) {
+            return Ok(false);
+        }
+    }
+
+    let target_metadata = ctx.fs_guard.run(path, {
+        let path = path.to_path_buf();
+        move || std::fs::metadata(path)
+    });
+    match target_metadata {
+        Ok(metadata) => {
+            if metadata.is_dir() {
+                if is_pruned(path, ctx) {
+                    return Ok(false);
+                }
+                // Use the original symlink path for directory traversal
+                handle_directory(symlink_path, ctx.work.depth, ctx, channels)?;
+                Ok(false)
+            } else {
+                Ok(metadata.is_file())
+            }
+        }
+        // Dangling symlinks are not worth reporting
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => {
+            ctx.errors.report(path, ScanOperation::FollowSymlink, &e);
+            Ok(false)
+        }
+    }
+}
+
+struct ScannerConfig {
+    work_rx: Receiver<WorkUnit>,
+    dir_tx: Sender<WorkUnit>,
+    result_tx: Sender<PathBuf>,
+    pattern: Arc<PatternMatcher>,
+    active_scanners: Arc<AtomicUsize>,
+    max_depth: usize,
+    symlink_mode: SymlinkMode,
+    root_path: PathBuf,
+    entry_filter: Arc<dyn EntryFilter>,
+    now: SystemTime,
+    system_checker: Arc<SystemPathChecker>,
+    cancel: CancelToken,
+    fs_guard: FsGuard,
+    errors: ErrorReporter,
+    stats: Arc<StatsCounters>,
+    frontier: Option<Arc<Frontier>>,
+    search_archives: bool,
+    resolve_aliases: bool,
+    fanout_depth: Option<usize>,
+    preferred: Vec<PathBuf>,
+    max_dir_entries: Option<usize>,
+    include_large: Arc<Vec<PathBuf>>,
+    prune: Arc<HashSet<OsString>>,
+    exclude: Option<Arc<Exclusions>>,
+    memory_budget: Option<Arc<MemoryBudget>>,
+}
+
+/// Read the entries of `work` and build the context they are handled in.
+/// Errors are reported here; `None` means the directory could not be read.
+fn open_directory(
+    work: &WorkUnit,
+    config: &ScannerConfig,
+    visited_paths: &Arc<Mutex<VisitedSet>>,
+) -> Option<(ScannerContext, Vec<io::Result<std::fs::DirEntry>>)> {
+    let ctx = ScannerContext {
+        work: work.clone(),
+        pattern: Arc::clone(&config.pattern),
+        symlink_mode: config.symlink_mode,
+        is_command_line: work.depth == 0,
+        visited_paths: Arc::clone(visited_paths),
+        root_path: config.root_path.clone(),
+        entry_filter: Arc::clone(&config.entry_filter),
+        now: config.now,
+        system_checker: Arc::clone(&config.system_checker),
+        fs_guard: config.fs_guard.clone(),
+        errors: config.errors.clone(),
+        search_archives: config.search_archives,
+        resolve_aliases: config.resolve_aliases,
+        prune: Arc::clone(&config.prune),
+        exclude: config.exclude.clone(),
+        cancel: config.cancel.clone(),
+    };
+
+    // The starting directory and those explicitly included may be any size
+    let entry_limit = config
+        .max_dir_entries
+        .filter(|_| work.depth > 0 && !config.include_large.contains(&work.path));
+
+    // More defensive read_dir handling
+    let read_dir = match config.fs_guard.run_retrying(&work.path, {
+        let path = work.path.clone();
+        // Collect eagerly so that a hang while listing is also covered, but
+        // stop listing as soon as the directory turns out to be too large
+        let take = entry_limit.map_or(usize::MAX, |limit| limit.saturating_add(1));
+        move || std::fs::read_dir(&path).map(|dir| dir.take(take).collect::<Vec<_>>())
+    }) {
+        (Ok(dir), _) => dir,
+        (Err(e), retries) => {
+            ctx.errors
+                .report_retried(&work.path, ScanOperation::ReadDir, &e, retries);
+            return None;
+        }
+    };
+
+    if let Some(limit) = entry_limit.filter(|&limit| read_dir.len() > limit) {
+        let reason = format!("more than {} entries", limit);
+        ctx.errors
+            .skip_as(&work.path, ScanOperation::LargeDirectory, &reason);
+        return None;
+    }
+
+    config.stats.dirs_scanned.fetch_add(1, Ordering::Relaxed);
+    config
+        .stats
+        .entries_scanned
+        .fetch_add(read_dir.len() as u64, Ordering::Relaxed);
+    Some((ctx, read_dir))
+}
+
+fn spawn_scanner_thread(config: ScannerConfig) -> thread::JoinHandle<()> {
+    let visited_paths = Arc::new(Mutex::new(VisitedSet::new(config.memory_budget.clone())));
+
+    thread::spawn(move || {
+        let channels = ScannerChannels {
+            dir_tx: config.dir_tx.clone(),
+            result_tx: config.result_tx.clone(),
+            frontier: config.frontier.clone(),
+            fanout_depth: config.fanout_depth,
+            inline: RefCell::default(),
+        };
+        // Directories cut short by cancellation stay in the frontier
+        let done = |work: &WorkUnit| {
+            if let Some(frontier) = &channels.frontier {
+                frontier.done(work);
+            }
+        };
+        let scan = |work: WorkUnit| {
+            if work.depth > config.max_depth {
+                done(&work);
+                return;
+            }
+            let (ctx, read_dir) = match open_directory(&work, &config, &visited_paths) {
+                Some(opened) => opened,
+                None => {
+                    done(&work);
+                    return;
+                }
+            };
+
+            for entry in read_dir {
+                if config.cancel.is_cancelled() {
+                    return;
+                }
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        ctx.errors.report(&work.path, ScanOperation::ReadDir, &e);
+                        continue;
+                    }
+                };
+                if let Err(e) = handle_entry(entry, &ctx, &channels) {
+                    debug!("Error processing entry: {}", e);
+                }
+            }
+            done(&work);
+        };
+
+        while let Ok(work) = config.work_rx.recv() {
+            config.active_scanners.fetch_add(1, Ordering::SeqCst);
+
+            // Remaining work is drained without scanning once cancelled
+            if !config.cancel.is_cancelled() {
+                scan(work);
+                // Finish everything below the fan-out depth before taking more work
+                loop {
+                    let next = channels.inline.borrow_mut().pop();
+                    match next {
+                        Some(work) if !config.cancel.is_cancelled() => scan(work),
+                        _ => break,
+                    }
+                }
+            }
+            channels.inline.borrow_mut().clear();
+
+            config.active_scanners.fetch_sub(1, Ordering::SeqCst);
+        }
+    })
+}
+
+/// Single-threaded scanner used with one thread: a plain depth-first walk
+/// that visits entries sorted by name (preferred directories first) and descends into each directory as
+/// soon as it is reported, so the output order is deterministic.
+///
+/// Entries are handled exactly as in the parallel scanner; subdirectories
+/// queued on `dir_rx` by `handle_entry` are picked up right away instead of
+/// going through the work distributor.
+fn spawn_sequential_scanner(
+    config: ScannerConfig,
+    dir_rx: Receiver<WorkUnit>,
+) -> thread::JoinHandle<()> {
+    let visited_paths = Arc::new(Mutex::new(VisitedSet::new(config.memory_budget.clone())));
+
+    thread::spawn(move || {
+        let channels = ScannerChannels {
+            dir_tx: config.dir_tx.clone(),
+            result_tx: config.result_tx.clone(),
+            frontier: None,
+            fanout_depth: None,
+            inline: RefCell::default(),
+        };
+        let mut stack: Vec<(ScannerContext, std::vec::IntoIter<std::fs::DirEntry>)> = Vec::new();
+        let mut queued: Vec<WorkUnit> = config.work_rx.try_iter().collect();
+
+        loop {
+            if config.cancel.is_cancelled() {
+                break;
+            }
+            queued.extend(dir_rx.try_iter());
+            // Open in reverse so the first queued directory ends up on top
+            for work in queued.drain(..).rev() {
+                if work.depth > config.max_depth {
+                    continue;
+                }
+                if let Some((ctx, read_dir)) = open_directory(&work, &config, &visited_paths) {
+                    let mut entries = Vec::with_capacity(read_dir.len());
+                    for entry in read_dir {
+                        match entry {
+                            Ok(entry) => entries.push(entry),
+                            Err(e) => ctx.errors.report(&work.path, ScanOperation::ReadDir, &e),
+                        }
+                    }
+                    let sort_key = |entry: &std::fs::DirEntry| {
+                        (
+                            !is_preferred(&entry.path(), &config.preferred),
+                            entry.file_name(),
+                        )
+                    };
+                    // Caching the keys holds a copy of every name while sorting
+                    let key_cost: u64 = entries
+                        .iter()
+                        .map(|entry| memory::path_cost(Path::new(&entry.file_name())))
+                        .sum();
+                    if memory::try_reserve(&config.memory_budget, key_cost) {
+                        entries.sort_by_cached_key(sort_key);
+                        if let Some(budget) = &config.memory_budget {
+                            budget.release(key_cost);
+                        }
+                    } else {
+                        entries.sort_by_key(sort_key);
+                    }
+                    stack.push((ctx, entries.into_iter()));
+                }
+            }
+
+            let (ctx, entries) = match stack.last_mut() {
+                Some(top) => top,
+                None => break,
+            };
+            match entries.next() {
+                Some(entry) => {
+                    if let Err(e) = handle_entry(entry, ctx, &channels) {
+                        debug!("Error processing entry: {}", e);
+                    }
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+    })
+}
+
+struct ThreadPool {
+    scanner_handles: Vec<thread::JoinHandle<()>>,
+    /// Absent when a single thread walks the tree sequentially
+    distributor_handle: Option<thread::JoinHandle<()>>,
+    result_receiver: Receiver<PathBuf>,
+}
+
+struct ChannelSet {
+    work_tx: Sender<WorkUnit>,
+    work_rx: Receiver<WorkUnit>,
+    result_tx: Sender<PathBuf>,
+    result_rx: Receiver<PathBuf>,
+    dir_tx: Sender<WorkUnit>,
+    dir_rx: Receiver<WorkUnit>,
+}
+
+fn create_channels(thread_count: usize, result_buffer: Option<usize>) -> ChannelSet {
+    let (work_tx, work_rx) = bounded(thread_count * 8);
+    // A full result channel blocks the scanners until the consumer catches up
+    let (result_tx, result_rx) = match result_buffer {
+        Some(capacity) => bounded(capacity),
+        None => unbounded(),
+    };
+    let (dir_tx, dir_rx) = unbounded();
+
+    ChannelSet {
+        work_tx,
+        work_rx,
+        result_tx,
+        result_rx,
+        dir_tx,
+        dir_rx,
+    }
+}
+
+/// A directory waiting to be handed to a scanner
+struct QueuedWork {
+    preferred: bool,
+    /// Arrival order, so that equally preferred directories stay first in, first out
+    seq: u64,
+    work: WorkUnit,
+}
+
+impl QueuedWork {
+    fn key(&self) -> (bool, std::cmp::Reverse<u64>) {
+        (self.preferred, std::cmp::Reverse(self.seq))
+    }
+}
+
+impl PartialEq for QueuedWork {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for QueuedWork {}
+
+impl PartialOrd for QueuedWork {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedWork {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// Whether `path` is one of the preferred directories, lies inside one or
+/// leads to one
+fn is_preferred(path: &Path, preferred: &[PathBuf]) -> bool {
+    preferred
+        .iter()
+        .any(|dir| path.starts_with(dir) || dir.starts_with(path))
+}
+
+fn spawn_work_distributor(
+    work_tx: Sender<WorkUnit>,
+    dir_rx: Receiver<WorkUnit>,
+    active_scanners: Arc<AtomicUsize>,
+    cancel: CancelToken,
+    preferred: Vec<PathBuf>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        // Directories are held here rather than in the bounded work channel,
+        // so preferred ones can overtake those found earlier
+        let mut queue = BinaryHeap::new();
+        let mut seq = 0;
+
+        let mut empty_reads: u8 = 0;
+        const MAX_EMPTY_READS: u8 = 3;
+
+        loop {
+            if cancel.is_cancelled() {
+                break;
+            }
+            for work in dir_rx.try_iter() {
+                empty_reads = 0;
+                seq += 1;
+                queue.push(QueuedWork {
+                    preferred: is_preferred(&work.path, &preferred),
+                    seq,
+                    work,
+                });
+            }
+
+            let mut sent = false;
+            while let Some(next) = queue.pop() {
+                match work_tx.try_send(next.work) {
+                    Ok(()) => sent = true,
+                    Err(TrySendError::Full(work)) => {
+                        queue.push(QueuedWork { work, ..next });
+                        break;
+                    }
+                    Err(TrySendError::Disconnected(_)) => return,
+                }
+            }
+            if sent {
+                continue;
+            }
+
+            if queue.is_empty() {
+                empty_reads = empty_reads.saturating_add(1);
+                if empty_reads >= MAX_EMPTY_READS
+                    && active_scanners.load(Ordering::SeqCst) == 0
+                    && dir_rx.is_empty()
+                    && work_tx.is_empty()
+                {
+                    break;
+                }
+            }
+            thread::sleep(std::time::Duration::from_micros(100));
+        }
+    })
+}
+
+/// Document, desktop, download and code directories in the home directory,
+/// which `--prefer-common` scans first
+pub fn common_directories() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(user) = directories_next::UserDirs::new() {
+        dirs.extend(user.document_dir().map(Path::to_path_buf));
+        dirs.extend(user.desktop_dir().map(Path::to_path_buf));
+        dirs.extend(user.download_dir().map(Path::to_path_buf));
+        for name in ["code", "src", "projects", "dev", "repos", "workspace"] {
+            dirs.push(user.home_dir().join(name));
+        }
+    }
+    dirs
+}
+
+struct ThreadPoolOptions {
+    thread_count: usize,
+    pattern: Arc<PatternMatcher>,
+    channels: ChannelSet,
+    max_depth: usize,
+    symlink_mode: SymlinkMode,
+    root_path: PathBuf,
+    entry_filter: Arc<dyn EntryFilter>,
+    now: SystemTime,
+    cancel: CancelToken,
+    fs_guard: FsGuard,
+    errors: ErrorReporter,
+    stats: Arc<StatsCounters>,
+    frontier: Option<Arc<Frontier>>,
+    search_archives: bool,
+    resolve_aliases: bool,
+    fanout_depth: Option<usize>,
+    preferred: Vec<PathBuf>,
+    max_dir_entries: Option<usize>,
+    include_large: Arc<Vec<PathBuf>>,
+    prune: Arc<HashSet<OsString>>,
+    exclude: Option<Arc<Exclusions>>,
+    memory_budget: Option<Arc<MemoryBudget>>,
+}
+
+#[derive(Default)]
+struct SystemPathChecker {
+    system_paths: Vec<PathBuf>,
+}
+
+impl SystemPathChecker {
+    fn new() -> Self {
+        if cfg!(test) {
+            return SystemPathChecker::default();
+        }
+
+        let mut checker = SystemPathChecker::default();
+
+        #[cfg(target_os = "macos")]
+        {
+            checker.system_paths.extend_from_slice(&[
+                PathBuf::from("/System"),
+                PathBuf::from("/Library"),
+                PathBuf::from("/private"),
+                PathBuf::from("/Volumes"),
+            ]);
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            checker.system_paths.extend_from_slice(&[
+                PathBuf::from("/proc"),
+                PathBuf::from("/sys"),
+                PathBuf::from("/dev"),
+                PathBuf::from("/run"),
+                PathBuf::from("/private"),
+            ]);
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            checker.system_paths.extend_from_slice(&[
+                PathBuf::from("C:\\Windows"),
+                PathBuf::from("C:\\Program Files\\Windows"),
+                PathBuf::from("C:\\ProgramData\\Microsoft"),
+                PathBuf::from("C:\\System Volume Information"),
+            ]);
+        }
+
+        checker
+    }
+
+    #[inline]
+    fn is_system_path(&self, path: &Path) -> bool {
+        // Case-insensitive check for Windows paths
+        #[cfg(target_os = "windows")]
+        {
+            let path_str = path.to_string_lossy().to_lowercase();
+            self.system_paths.iter().any(|sys_path| {
+                path_str.starts_with(&sys_path.to_string_lossy().to_lowercase())
+                    || path_str.contains("\\system32")
+                    || path_str.contains("\\syswow64")
+            })
+        }
+
+        // Case-sensitive check for Unix-like systems
+        #[cfg(not(target_os = "windows"))]
+        {
+            self.system_paths
+                .iter()
+                .any(|sys_path| path.starts_with(sys_path))
+        }
+    }
+}
+
+// Update handle_entry function to use SystemPathChecker
+fn handle_entry(
+    entry: std::fs::DirEntry,
+    ctx: &ScannerContext,
+    channels: &ScannerChannels,
+) -> Result<(), Box<dyn Error>> {
+    let path = entry.path();
+
+    // Skip system paths early
+    if ctx.system_checker.is_system_path(&path) {
+        ctx.errors.skip(&path, "system path");
+        return Ok(());
+    }
+    if is_excluded(&path, ctx) {
+        return Ok(());
+    }
+
+    let metadata = match ctx.fs_guard.run_retrying(&path, move || entry.metadata()) {
+        (Ok(metadata), _) => metadata,
+        (Err(e), retries) => {
+            ctx.errors
+                .report_retried(&path, ScanOperation::Stat, &e, retries);
+            return Ok(());
+        }
+    };
+    let relative_path = normalize_path(&path, &ctx.root_path);
+
+    // Junctions and mount points on Windows are handled like symlinks
+    if reparse::is_link(&path, &metadata) {
+        if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
+            if ctx.pattern.matches(file_name) {
+                let target_metadata = followed_metadata(&path, ctx);
+                let metadata = target_metadata.as_ref().unwrap_or(&metadata);
+                if is_entry_match(&relative_path, &relative_path, metadata, ctx) {
+                    channels.result_tx.send(relative_path.clone())?;
+                }
+            }
+        }
+
+        match handle_symlink(&path, metadata.file_type(), ctx, channels) {
+            Ok(_) => (),
+            Err(e) => debug!("Error handling symlink {:?}: {}", path, e),
+        }
+        return Ok(());
+    }
+
+    if metadata.file_type().is_dir() {
+        if is_pruned(&path, ctx) {
+            return Ok(());
+        }
+        handle_directory(path.clone(), ctx.work.depth, ctx, channels)?;
+
+        if let Some(dir_name) = path.file_name().and_then(|n| n.to_str()) {
+            if ctx.pattern.matches(dir_name)
+                && is_entry_match(&relative_path, &relative_path, &metadata, ctx)
+            {
+                channels.result_tx.send(relative_path)?;
+            }
+        }
+    } else if metadata.file_type().is_file() {
+        // iCloud stubs are matched and reported as the file they stand for
+        let reported_path = match placeholder::logical_name(&path) {
+            Some(name) => relative_path.with_file_name(name),
+            None => relative_path.clone(),
+        };
+        if let Some(file_name) = reported_path.file_name().and_then(|n| n.to_str()) {
+            if ctx.pattern.matches(file_name)
+                && is_entry_match(&reported_path, &relative_path, &metadata, ctx)
+            {
+                channels.result_tx.send(reported_path.clone())?;
+            }
+        }
+        if ctx.search_archives && archive::is_archive(&path) {
+            if placeholder::is_placeholder(&metadata) {
+                ctx.errors.skip(&path, placeholder::SKIP_REASON);
+            } else {
+                handle_archive(&path, &relative_path, ctx, channels)?;
+            }
+        }
+        if ctx.resolve_aliases && should_follow_symlink(ctx, ctx.is_command_line) {
+            handle_alias(&path, ctx, channels)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Traverse the target of a Finder alias to a folder, like a followed symlink
+fn handle_alias(
+    path: &Path,
+    ctx: &ScannerContext,
+    channels: &ScannerChannels,
+) -> Result<(), Box<dyn Error>> {
+    let target = ctx.fs_guard.run(path, {
+        let path = path.to_path_buf();
+        move || Ok(alias::resolve(&path))
+    });
+    let target = match target {
+        Ok(Some(target)) if target.is_dir() => target,
+        _ => return Ok(()),
+    };
+
+    // Aliases can form loops just like symlinks
+    let canonical = target.canonicalize().unwrap_or_else(|_| target.clone());
+    if !ctx.visited_paths.lock().insert(canonical) {
+        return Ok(());
+    }
+    handle_directory(target, ctx.work.depth, ctx, channels)
+}
+
+/// Match the pattern against the names of the files inside an archive,
+/// reporting hits as `archive.zip!/path/inside`
+fn handle_archive(
+    path: &Path,
+    relative_path: &Path,
+    ctx: &ScannerContext,
+    channels: &ScannerChannels,
+) -> Result<(), Box<dyn Error>> {
+    let members = match ctx.fs_guard.run(path, {
+        let path = path.to_path_buf();
+        move || archive::member_paths(&path)
+    }) {
+        Ok(members) => members,
+        Err(e) => {
+            ctx.errors.report(path, ScanOperation::ReadArchive, &e);
+            return Ok(());
+        }
+    };
+
+    for member in members {
+        let name = member.rsplit('/').next().unwrap_or(&member);
+        if ctx.pattern.matches(name) {
+            let mut result = relative_path.as_os_str().to_owned();
+            result.push(archive::MEMBER_SEPARATOR);
+            result.push(&member);
+            channels.result_tx.send(PathBuf::from(result))?;
+        }
+    }
+    Ok(())
+}
+
+// Update setup_thread_pool to include SystemPathChecker
+fn setup_thread_pool(pool_options: ThreadPoolOptions) -> ThreadPool {
+    let active_scanners = Arc::new(AtomicUsize::new(0));
+    let system_checker = Arc::new(SystemPathChecker::new());
+    let mut scanner_handles = Vec::with_capacity(pool_options.thread_count);
+    // Checkpointing relies on the frontier kept by the parallel scanners
+    let sequential = pool_options.thread_count == 1 && pool_options.frontier.is_none();
+
+    for _ in 0..pool_options.thread_count {
+        let scanner_config = ScannerConfig {
+            work_rx: pool_options.channels.work_rx.clone(),
+            dir_tx: pool_options.channels.dir_tx.clone(),
+            result_tx: pool_options.channels.result_tx.clone(),
+            pattern: Arc::clone(&pool_options.pattern),
+            active_scanners: Arc::clone(&active_scanners),
+            max_depth: pool_options.max_depth,
+            symlink_mode: pool_options.symlink_mode,
+            root_path: pool_options.root_path.clone(),
+            entry_filter: Arc::clone(&pool_options.entry_filter),
+            now: pool_options.now,
+            system_checker: Arc::clone(&system_checker),
+            cancel: pool_options.cancel.clone(),
+            fs_guard: pool_options.fs_guard.for_worker(),
+            errors: pool_options.errors.clone(),
+            stats: Arc::clone(&pool_options.stats),
+            frontier: pool_options.frontier.clone(),
+            search_archives: pool_options.search_archives,
+            resolve_aliases: pool_options.resolve_aliases,
+            fanout_depth: pool_options.fanout_depth,
+            preferred: pool_options.preferred.clone(),
+            max_dir_entries: pool_options.max_dir_entries,
+            include_large: Arc::clone(&pool_options.include_large),
+            prune: Arc::clone(&pool_options.prune),
+            exclude: pool_options.exclude.clone(),
+            memory_budget: pool_options.memory_budget.clone(),
+        };
+        if sequential {
+            scanner_handles.push(spawn_sequential_scanner(
+                scanner_config,
+                pool_options.channels.dir_rx.clone(),
+            ));
+        } else {
+            scanner_handles.push(spawn_scanner_thread(scanner_config));
+        }
+    }
+
+    // Rest of the setup_thread_pool implementation remains the same...
+    let distributor_handle = if sequential {
+        None
+    } else {
+        Some(spawn_work_distributor(
+            pool_options.channels.work_tx,
+            pool_options.channels.dir_rx,
+            active_scanners,
+            pool_options.cancel,
+            pool_options.preferred,
+        ))
+    };
+    ThreadPool {
+        scanner_handles,
+        distributor_handle,
+        result_receiver: pool_options.channels.result_rx,
+    }
+}
+
+/// Matches that may wait for the consumer by default
+pub const DEFAULT_RESULT_BUFFER: usize = 10_000;
+
+/// Retries of a failed directory listing or stat by default, see
+/// [`SearchOptions::io_retries`]
+pub const DEFAULT_IO_RETRIES: u32 = 2;
+
+/// How search patterns are interpreted
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternSyntax {
+    /// A glob if it has `*` or `?`, otherwise a case-insensitive substring
+    #[default]
+    Glob,
+    /// A regular expression, unanchored unless it uses `^` or `$` (`--regex`)
+    Regex,
+    /// A case-insensitive regular expression (`--iregex`)
+    RegexIgnoreCase,
+}
+
+impl PatternSyntax {
+    /// Matcher for `pattern` read with this syntax
+    pub fn matcher(self, pattern: &str) -> Result<PatternMatcher, PatternError> {
+        let error = |message: String| PatternError {
+            pattern: pattern.to_string(),
+            syntax: self,
+            message,
+        };
+        match self {
+            PatternSyntax::Glob => {
+                // create_pattern_matcher expects a valid glob
+                let glob = pattern.trim_start_matches('!');
+                if glob.contains('*') || glob.contains('?') {
+                    Pattern::new(glob).map_err(|e| error(e.to_string()))?;
+                }
+                Ok(create_pattern_matcher(pattern))
+            }
+            PatternSyntax::Regex | PatternSyntax::RegexIgnoreCase => RegexBuilder::new(pattern)
+                .case_insensitive(self == PatternSyntax::RegexIgnoreCase)
+                .build()
+                .map(PatternMatcher::Regex)
+                .map_err(|e| error(e.to_string())),
+        }
+    }
+
+    /// Check `pattern` up front, without keeping its matcher
+    pub fn validate(self, pattern: &str) -> Result<(), PatternError> {
+        self.matcher(pattern).map(drop)
+    }
+}
+
+/// A search pattern that is not valid in its syntax
+#[derive(Debug, Clone)]
+pub struct PatternError {
+    pub pattern: String,
+    pub syntax: PatternSyntax,
+    pub message: String,
+}
+
+impl std::fmt::Display for PatternError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let kind = match self.syntax {
+            PatternSyntax::Glob => "glob",
+            PatternSyntax::Regex | PatternSyntax::RegexIgnoreCase => "regular expression",
+        };
+        write!(f, "invalid {} '{}': {}", kind, self.pattern, self.message)
+    }
+}
+
+impl Error for PatternError {}
+
+/// Options for a single search
+pub struct SearchOptions {
+    /// Glob pattern or substring matched against entry names
+    pub pattern: String,
+    /// How `pattern` and `extra_patterns` are interpreted
+    pub pattern_syntax: PatternSyntax,
+    /// More patterns; names matching any of them match too
+    pub extra_patterns: Vec<String>,
+    /// Directory to start from
+    pub root: PathBuf,
+    pub max_depth: usize,
+    /// Number of scanner threads
+    pub threads: usize,
+    pub symlink_mode: SymlinkMode,
+    /// Additional predicate every reported entry must satisfy
+    pub filter: Arc<dyn EntryFilter>,
+    /// Reference time for time-based filters
+    pub now: SystemTime,
+    /// Give up on any single stat/readdir/canonicalize call after this long
+    pub stat_timeout: Option<Duration>,
+    /// How many times to try listing a directory or reading an entry's
+    /// metadata again after a transient error (EINTR, EAGAIN, ESTALE or a
+    /// Windows sharing violation) before counting it as failed
+    pub io_retries: u32,
+    /// Called for every path that could not be scanned
+    pub on_error: Option<ErrorHandler>,
+    /// Cancel the search once more than this many filesystem operations have
+    /// failed, instead of carrying on with incomplete results
+    pub max_errors: Option<u64>,
+    /// Save progress to this file every `checkpoint_interval` and when the
+    /// search is cancelled; the file is removed once the search completes
+    pub checkpoint: Option<PathBuf>,
+    pub checkpoint_interval: Duration,
+    /// Continue a previous search instead of starting at `root`
+    pub resume: Option<Checkpoint>,
+    /// Also match the names of files inside zip and tar archives
+    pub search_archives: bool,
+    /// Treat macOS Finder aliases to folders like symlinks, following them
+    /// according to `symlink_mode`
+    pub resolve_aliases: bool,
+    /// Only hand directories down to this depth to other threads; deeper ones
+    /// are scanned by the thread that found them. `None` distributes them all.
+    pub fanout_depth: Option<usize>,
+    /// Directories to scan before any others, along with everything inside
+    /// them and the directories leading to them
+    pub prefer: Vec<PathBuf>,
+    /// Skip directories below the starting one that hold more than this many
+    /// entries, reporting them as [`ScanOperation::LargeDirectory`]
+    pub max_dir_entries: Option<usize>,
+    /// Directories scanned whatever their size
+    pub include_large: Vec<PathBuf>,
+    /// Names of directories that are neither reported nor descended into,
+    /// e.g. `node_modules`
+    pub prune: Vec<String>,
+    /// Entries whose path relative to the root matches any of these are
+    /// neither reported nor descended into
+    pub exclude: Vec<Regex>,
+    /// Limit on the memory held by the visited-directory set and sort keys,
+    /// past which leaner strategies are used
+    pub memory_budget: Option<Arc<MemoryBudget>>,
+    /// Number of matches that may wait for the consumer before the scanners
+    /// pause, so a slow consumer paces the scan. `None` never pauses, letting
+    /// matches pile up in memory.
+    pub result_buffer: Option<usize>,
+}
+
+impl SearchOptions {
+    /// Options with the same defaults as the command line
+    pub fn new(pattern: impl Into<String>, root: impl Into<PathBuf>) -> Self {
+        SearchOptions {
+            pattern: pattern.into(),
+            pattern_syntax: PatternSyntax::default(),
+            extra_patterns: Vec::new(),
+            root: root.into(),
+            max_depth: 100,
+            threads: num_cpus::get(),
+            symlink_mode: SymlinkMode::default(),
+            filter: Arc::new(AllOf::default()),
+            now: SystemTime::now(),
+            stat_timeout: None,
+            io_retries: DEFAULT_IO_RETRIES,
+            on_error: None,
+            max_errors: None,
+            checkpoint: None,
+            checkpoint_interval: Duration::from_secs(30),
+            resume: None,
+            search_archives: false,
+            resolve_aliases: false,
+            fanout_depth: None,
+            prefer: Vec::new(),
+            max_dir_entries: None,
+            include_large: Vec::new(),
+            prune: Vec::new(),
+            exclude: Vec::new(),
+            memory_budget: None,
+            result_buffer: Some(DEFAULT_RESULT_BUFFER),
+        }
+    }
+}
+
+/// Handle that stops a running search, possibly from another thread
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+fn canonicalize_all(dirs: &[PathBuf]) -> Vec<PathBuf> {
+    dirs.iter()
+        .map(|dir| dir.canonicalize().unwrap_or_else(|_| dir.clone()))
+        .collect()
+}
+
+/// A running search. Iterating yields matching paths as they are found.
+///
+/// Dropping a search before all results have been received cancels it.
+pub struct Search {
+    results: Receiver<PathBuf>,
+    handles: Vec<thread::JoinHandle<()>>,
+    cancel: CancelToken,
+    /// Set once every result has been received
+    finished: bool,
+    timed_out: Arc<Mutex<Vec<PathBuf>>>,
+    progress: Option<Progress>,
+    stats: Arc<StatsCounters>,
+    /// Errors tolerated before the search cancels itself
+    max_errors: Option<u64>,
+}
+
+/// Bookkeeping for a checkpointed search
+struct Progress {
+    path: PathBuf,
+    interval: Duration,
+    last_saved: Instant,
+    frontier: Arc<Frontier>,
+    checkpoint: Checkpoint,
+    emitted: HashSet<PathBuf>,
+    /// Results received from the scanners but not yet handed out
+    queued: VecDeque<PathBuf>,
+}
+
+impl Progress {
+    fn save(&mut self, results: &Receiver<PathBuf>) {
+        // Any directory missing from the snapshot has already sent all of its
+        // results, so draining the channel afterwards captures every one of them
+        self.checkpoint.pending = self.frontier.snapshot();
+        self.queued.extend(results.try_iter());
+        self.checkpoint.emitted = self.emitted.iter().cloned().collect();
+        self.checkpoint.queued = self.queued.iter().cloned().collect();
+        if let Err(e) = self.checkpoint.save(&self.path) {
+            debug!("Failed to save checkpoint {:?}: {}", self.path, e);
+        }
+        self.last_saved = Instant::now();
+    }
+}
+
+impl Search {
+    /// Spawn the scanner threads and start walking `options.root`. Fails
+    /// before any thread is spawned if a pattern is invalid.
+    pub fn start(options: SearchOptions) -> Result<Search, PatternError> {
+        let syntax = options.pattern_syntax;
+        let pattern = if options.extra_patterns.is_empty() {
+            syntax.matcher(&options.pattern)?
+        } else {
+            PatternMatcher::AnyOf(
+                std::iter::once(&options.pattern)
+                    .chain(&options.extra_patterns)
+                    .map(|pattern| syntax.matcher(pattern))
+                    .collect::<Result<_, _>>()?,
+            )
+        };
+
+        let thread_count = options.threads.max(1);
+        let cancel = CancelToken::default();
+        let fs_guard = FsGuard {
+            timeout: options.stat_timeout,
+            timed_out: Arc::default(),
+            retries: options.io_retries,
+            helper: Arc::default(),
+        };
+        let channels = create_channels(thread_count, options.result_buffer);
+        let frontier = options
+            .checkpoint
+            .as_ref()
+            .map(|_| Arc::new(Frontier::default()));
+
+        // Use canonicalized path for actual filesystem operations, but keep
+        // the original one for normalizing reported paths
+        let work_path = fs_guard
+            .run(&options.root, {
+                let root = options.root.clone();
+                move || std::fs::canonicalize(root)
+            })
+            .unwrap_or_else(|_| options.root.clone());
+        let exclude = (!options.exclude.is_empty()).then(|| {
+            Arc::new(Exclusions {
+                root: work_path.clone(),
+                patterns: options.exclude.clone(),
+            })
+        });
+
+        match &options.resume {
+            // Pick up the saved directories through the unbounded directory queue
+            Some(resume) => {
+                for pending in &resume.pending {
+                    let work = WorkUnit {
+                        path: pending.path.clone(),
+                        depth: pending.depth,
+                    };
+                    if let Some(frontier) = &frontier {
+                        frontier.add(&work);
+                    }
+                    channels
+                        .dir_tx
+                        .send(work)
+                        .expect("Failed to send resumed work");
+                }
+            }
+            // Submit initial work unit with the canonicalized path
+            None => {
+                let work = WorkUnit {
+                    path: work_path,
+                    depth: 0,
+                };
+                if let Some(frontier) = &frontier {
+                    frontier.add(&work);
+                }
+                channels
+                    .work_tx
+                    .send(work)
+                    .expect("Failed to send initial work");
+            }
+        }
+
+        let progress = match (&options.checkpoint, &frontier) {
+            (Some(path), Some(frontier)) => {
+                let resume = options.resume.unwrap_or_default();
+                Some(Progress {
+                    path: path.clone(),
+                    interval: options.checkpoint_interval,
+                    last_saved: Instant::now(),
+                    frontier: Arc::clone(frontier),
+                    checkpoint: Checkpoint::new(&options.root, &options.pattern),
+                    emitted: resume.emitted.into_iter().collect(),
+                    queued: resume.queued.into_iter().collect(),
+                })
+            }
+            _ => None,
+        };
+
+        let stats = Arc::new(StatsCounters::default());
+        let pool = setup_thread_pool(ThreadPoolOptions {
+            thread_count,
+            pattern: Arc::new(pattern),
+            channels,
+            max_depth: options.max_depth,
+            symlink_mode: options.symlink_mode,
+            root_path: options.root,
+            entry_filter: options.filter,
+            now: options.now,
+            cancel: cancel.clone(),
+            fs_guard: fs_guard.clone(),
+            errors: ErrorReporter {
+                handler: options.on_error,
+                stats: Arc::clone(&stats),
+                max_errors: options.max_errors,
+                cancel: cancel.clone(),
+            },
+            stats: Arc::clone(&stats),
+            frontier,
+            search_archives: options.search_archives,
+            resolve_aliases: options.resolve_aliases,
+            fanout_depth: options.fanout_depth,
+            // Scanned paths are canonical, so compare against canonical paths
+            preferred: canonicalize_all(&options.prefer),
+            max_dir_entries: options.max_dir_entries,
+            include_large: Arc::new(canonicalize_all(&options.include_large)),
+            prune: Arc::new(options.prune.iter().map(OsString::from).collect()),
+            exclude,
+            memory_budget: options.memory_budget,
+        });
+
+        let max_errors = options.max_errors;
+        let mut handles = pool.scanner_handles;
+        handles.extend(pool.distributor_handle);
+        Ok(Search {
+            results: pool.result_receiver,
+            handles,
+            cancel,
+            finished: false,
+            timed_out: fs_guard.timed_out,
+            progress,
+            stats,
+            max_errors,
+        })
+    }
+
+    /// A token that cancels this search when triggered
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    /// Paths skipped so far because a filesystem call exceeded `stat_timeout`
+    pub fn timed_out_paths(&self) -> Vec<PathBuf> {
+        self.timed_out.lock().clone()
+    }
+
+    /// Totals so far; final once every result has been received
+    pub fn stats(&self) -> ScanStats {
+        self.stats.snapshot()
+    }
+
+    /// Wait for all scanner threads to exit
+    pub fn join(mut self) {
+        // Scanners blocked on a full result channel give up once it is closed
+        self.results = crossbeam_channel::never();
+        for handle in self.handles.drain(..) {
+            handle.join().unwrap();
+        }
+    }
+}
+
+impl Iterator for Search {
+    type Item = PathBuf;
+
+    fn next(&mut self) -> Option<PathBuf> {
+        if self.progress.is_some() {
+            return self.next_checkpointed();
+        }
+        if self.finished {
+            return None;
+        }
+        if self.cancel.is_cancelled() {
+            // Matches found before the error budget ran out are still handed
+            // out; any other cancellation stops at once
+            if self.error_budget_spent() {
+                return self.results.try_recv().ok();
+            }
+            return None;
+        }
+        let result = self.results.recv().ok();
+        self.finished = result.is_none();
+        result
+    }
+}
+
+impl Search {
+    /// Whether more filesystem operations failed than `max_errors` allows
+    fn error_budget_spent(&self) -> bool {
+        self.max_errors
+            .is_some_and(|max| self.stats.errors.load(Ordering::Relaxed) > max)
+    }
+
+    fn next_checkpointed(&mut self) -> Option<PathBuf> {
+        let progress = self.progress.as_mut()?;
+        loop {
+            if self.finished {
+                return None;
+            }
+            if progress.last_saved.elapsed() >= progress.interval {
+                progress.save(&self.results);
+            }
+            if let Some(path) = progress.queued.pop_front() {
+                if progress.emitted.insert(path.clone()) {
+                    return Some(path);
+                }
+                continue;
+            }
+            if self.cancel.is_cancelled() {
+                progress.save(&self.results);
+                self.finished = true;
+                return None;
+            }
+
+            let wait = progress
+                .interval
+                .saturating_sub(progress.last_saved.elapsed());
+            match self.results.recv_timeout(wait) {
+                Ok(path) => progress.queued.push_back(path),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.finished = true;
+                    // Nothing left to resume
+                    let _ = std::fs::remove_file(&progress.path);
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+impl Search {
+    /// Like [`Search::start`], but also yields every path that could not be
+    /// scanned as an `Err` item, so that nothing is dropped silently.
+    /// `options.on_error` is still called as well.
+    pub fn start_with_errors(mut options: SearchOptions) -> Result<SearchResults, PatternError> {
+        let (error_tx, errors) = unbounded();
+        let on_error = options.on_error.take();
+        options.on_error = Some(Arc::new(move |error: &ScanError| {
+            if let Some(on_error) = &on_error {
+                on_error(error);
+            }
+            let _ = error_tx.send(error.clone());
+        }));
+        Ok(SearchResults {
+            search: Search::start(options)?,
+            errors,
+        })
+    }
+}
+
+/// A running search yielding matches and scan errors as they occur, see
+/// [`Search::start_with_errors`].
+///
+/// An error is yielded no later than the first match found after it, and
+/// every error is yielded before the iterator ends, so counting the `Err`
+/// items gives an accurate total once the search is done.
+pub struct SearchResults {
+    search: Search,
+    errors: Receiver<ScanError>,
+}
+
+impl SearchResults {
+    /// A token that cancels this search when triggered
+    pub fn cancel_token(&self) -> CancelToken {
+        self.search.cancel_token()
+    }
+
+    /// Totals so far; final once every result has been received
+    pub fn stats(&self) -> ScanStats {
+        self.search.stats()
+    }
+
+    /// Wait for all scanner threads to exit
+    pub fn join(self) {
+        self.search.join()
+    }
+}
+
+impl Iterator for SearchResults {
+    type Item = Result<PathBuf, ScanError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Ok(error) = self.errors.try_recv() {
+            return Some(Err(error));
+        }
+        match self.search.next() {
+            Some(path) => Some(Ok(path)),
+            // Errors are reported before the scanner threads exit, and so
+            // before the result channel closes
+            None => self.errors.try_recv().ok().map(Err),
+        }
+    }
+}
+
+impl Drop for Search {
+    fn drop(&mut self) {
+        if !self.finished && !self.handles.is_empty() {
+            self.cancel.cancel();
+            if let Some(progress) = &mut self.progress {
+                progress.save(&self.results);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fs_guard_replaces_hung_helper() {
+        let guard = FsGuard {
+            timeout: Some(Duration::from_millis(50)),
+            ..FsGuard::default()
+        };
+        let path = Path::new("/mnt/stale");
+        let helper_of = |guard: &FsGuard| guard.run(path, || Ok(thread::current().id())).unwrap();
+
+        // Calls are made on one helper thread, not a thread each
+        let helper = helper_of(&guard);
+        assert_ne!(helper, thread::current().id());
+        assert_eq!(helper_of(&guard), helper);
+        assert_ne!(helper_of(&guard.for_worker()), helper);
+
+        let hung = guard.run(path, || {
+            thread::sleep(Duration::from_millis(500));
+            Ok(())
+        });
+        assert_eq!(hung.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert_eq!(*guard.timed_out.lock(), vec![path.to_path_buf()]);
+
+        // Later calls are not stuck behind the hung one
+        let replacement = helper_of(&guard);
+        assert_ne!(replacement, helper);
+        assert_eq!(helper_of(&guard), replacement);
+    }
+
+    /// An op failing with `error` the first `failures` times it is called,
+    /// along with a count of its calls
+    fn flaky(
+        failures: u32,
+        error: io::ErrorKind,
+    ) -> (
+        impl Fn() -> io::Result<()> + Send + Sync + 'static,
+        Arc<AtomicUsize>,
+    ) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = Arc::clone(&calls);
+        let op = move || {
+            if counted.fetch_add(1, Ordering::SeqCst) < failures as usize {
+                Err(io::Error::from(error))
+            } else {
+                Ok(())
+            }
+        };
+        (op, calls)
+    }
+
+    #[test]
+    fn test_transient_errors_are_retried() {
+        let guard = FsGuard {
+            retries: 3,
+            ..FsGuard::default()
+        };
+        let path = Path::new("/mnt/nfs");
+
+        // Succeeds on the third attempt, after pauses of 10ms and 20ms
+        let (op, calls) = flaky(2, io::ErrorKind::Interrupted);
+        let started = Instant::now();
+        let (result, retries) = guard.run_retrying(path, op);
+        assert!(result.is_ok());
+        assert_eq!(retries, 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert!(started.elapsed() >= RETRY_BACKOFF * 3);
+
+        // Gives up once the retries are used up
+        let (op, calls) = flaky(10, io::ErrorKind::StaleNetworkFileHandle);
+        let (result, retries) = guard.run_retrying(path, op);
+        assert_eq!(
+            result.unwrap_err().kind(),
+            io::ErrorKind::StaleNetworkFileHandle
+        );
+        assert_eq!(retries, 3);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        // Other errors are not retried at all
+        let (op, calls) = flaky(10, io::ErrorKind::NotFound);
+        let (result, retries) = guard.run_retrying(path, op);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(retries, 0);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Nor is anything with retries turned off
+        let (op, calls) = flaky(1, io::ErrorKind::WouldBlock);
+        let (result, retries) = FsGuard::default().run_retrying(path, op);
+        assert!(result.is_err());
+        assert_eq!(retries, 0);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,205 @@
+//! A minimal HTTP JSON API for running searches without spawning processes.
+//!
+//! `GET /search?q=<pattern>[&dir=..][&type=f][&size=..][&mtime=..][&max_depth=..][&limit=..]`
+//! streams matches as newline-delimited JSON objects (`{"path": "..."}`).
+
+use crate::filters::{AllOf, SizeFilter, TimeField, TimeFilter, TimestampFilter, TypeFilter};
+use crate::scanner::{Search, SearchOptions};
+use log::debug;
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+
+/// Server-wide defaults applied to every request
+#[derive(Debug, Clone)]
+pub struct ServerOptions {
+    /// Directory searched when a request has no `dir` parameter
+    pub root: PathBuf,
+    /// Scanner threads per request
+    pub threads: usize,
+}
+
+/// Accept connections forever, handling each on its own thread
+pub fn serve(listener: TcpListener, options: ServerOptions) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                debug!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        let options = options.clone();
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, &options) {
+                debug!("Error handling connection: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream, options: &ServerOptions) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // Headers are not used, but must be consumed before responding
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method, target),
+        _ => return respond_error(&mut stream, "400 Bad Request", "Malformed request"),
+    };
+    if method != "GET" {
+        return respond_error(
+            &mut stream,
+            "405 Method Not Allowed",
+            "Only GET is supported",
+        );
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    match path {
+        "/search" => handle_search(&mut stream, &parse_query_string(query), options),
+        _ => respond_error(&mut stream, "404 Not Found", "Unknown endpoint"),
+    }
+}
+
+fn handle_search(
+    stream: &mut TcpStream,
+    params: &HashMap<String, String>,
+    options: &ServerOptions,
+) -> io::Result<()> {
+    let (search_options, limit) = match search_options(params, options) {
+        Ok(parsed) => parsed,
+        Err(e) => return respond_error(stream, "400 Bad Request", &e),
+    };
+    let search = match Search::start(search_options) {
+        Ok(search) => search,
+        Err(e) => return respond_error(stream, "400 Bad Request", &e.to_string()),
+    };
+
+    // No Content-Length: the body is streamed until the connection closes
+    stream.write_all(
+        b"HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nConnection: close\r\n\r\n",
+    )?;
+
+    for path in search.take(limit) {
+        let line = serde_json::json!({ "path": path.to_string_lossy() });
+        writeln!(stream, "{}", line)?;
+    }
+    stream.flush()
+}
+
+fn search_options(
+    params: &HashMap<String, String>,
+    options: &ServerOptions,
+) -> Result<(SearchOptions, usize), String> {
+    let pattern = params.get("q").ok_or("Missing q parameter")?;
+    let root = params
+        .get("dir")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| options.root.clone());
+
+    let mut search_options = SearchOptions::new(pattern.as_str(), root);
+    search_options.threads = options.threads;
+    if let Some(depth) = params.get("max_depth") {
+        search_options.max_depth = depth
+            .parse()
+            .map_err(|_| "Invalid max_depth parameter".to_string())?;
+    }
+
+    let filter = request_filter(
+        params.get("type").map(String::as_str),
+        params.get("size").map(String::as_str),
+        params.get("mtime").map(String::as_str),
+    )?;
+    search_options.filter = Arc::new(filter);
+
+    let limit = match params.get("limit") {
+        Some(limit) => limit
+            .parse()
+            .map_err(|_| "Invalid limit parameter".to_string())?,
+        None => usize::MAX,
+    };
+
+    Ok((search_options, limit))
+}
+
+/// Build the filter for the optional `type`, `size` and `mtime` request parameters
+pub(crate) fn request_filter(
+    type_filter: Option<&str>,
+    size: Option<&str>,
+    mtime: Option<&str>,
+) -> Result<AllOf, String> {
+    let mut filter = AllOf::default();
+    if let Some(type_filter) = type_filter {
+        filter.push(type_filter.parse::<TypeFilter>()?);
+    }
+    if let Some(size) = size {
+        filter.push(SizeFilter::parse(size)?);
+    }
+    if let Some(mtime) = mtime {
+        filter.push(TimestampFilter::new(
+            TimeField::Modified,
+            TimeFilter::parse(mtime)?,
+        ));
+    }
+    Ok(filter)
+}
+
+fn respond_error(stream: &mut TcpStream, status: &str, message: &str) -> io::Result<()> {
+    let body = serde_json::json!({ "error": message }).to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+fn parse_query_string(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
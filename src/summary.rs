@@ -0,0 +1,52 @@
+//! Aggregation of matches by extension for `--summarize-ext`.
+
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::path::Path;
+
+/// Label for files without an extension
+pub const NO_EXTENSION: &str = "(none)";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExtensionTotals {
+    pub count: u64,
+    pub bytes: u64,
+}
+
+/// Match counts and total sizes by lowercase extension. Directories are left
+/// out, since their sizes say nothing about their contents.
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionSummary {
+    totals: HashMap<String, ExtensionTotals>,
+}
+
+impl ExtensionSummary {
+    pub fn add(&mut self, path: &Path, metadata: &Metadata) {
+        if metadata.is_dir() {
+            return;
+        }
+        let ext = path.extension().map_or_else(
+            || NO_EXTENSION.to_string(),
+            |ext| ext.to_string_lossy().to_lowercase(),
+        );
+        let totals = self.totals.entry(ext).or_default();
+        totals.count += 1;
+        totals.bytes += metadata.len();
+    }
+
+    /// Totals for every extension, largest total size first
+    pub fn rows(&self) -> Vec<(&str, ExtensionTotals)> {
+        let mut rows: Vec<(&str, ExtensionTotals)> = self
+            .totals
+            .iter()
+            .map(|(ext, totals)| (ext.as_str(), *totals))
+            .collect();
+        rows.sort_by(|a, b| {
+            b.1.bytes
+                .cmp(&a.1.bytes)
+                .then(b.1.count.cmp(&a.1.count))
+                .then(a.0.cmp(b.0))
+        });
+        rows
+    }
+}
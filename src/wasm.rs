@@ -0,0 +1,106 @@
+//! Sandboxed filter plugins compiled to WebAssembly.
+//!
+//! A module must export its linear memory as `memory` together with:
+//!
+//! ```text
+//! rfind_alloc(len: i32) -> i32
+//!     Returns a pointer to `len` writable bytes for the entry path.
+//! rfind_filter(path_ptr: i32, path_len: i32, size: i64, kind: i32, mtime: i64) -> i32
+//!     Non-zero keeps the entry. `kind` and `mtime` use the same encoding as
+//!     native plugins (see `plugin`).
+//! ```
+//!
+//! No host functions are provided, so modules that import anything fail to load.
+
+use crate::filters::{EntryFilter, EntryInfo};
+use crate::plugin::{entry_kind, entry_mtime};
+use parking_lot::Mutex;
+use std::convert::TryFrom;
+use std::path::Path;
+use wasmtime::{Engine, Instance, Memory, Module, Store, TypedFunc};
+
+type FilterFn = TypedFunc<(i32, i32, i64, i32, i64), i32>;
+
+struct WasmState {
+    store: Store<()>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    filter: FilterFn,
+}
+
+/// A filter backed by a WebAssembly module
+pub struct WasmFilter {
+    // A wasmtime store may only be used by one thread at a time
+    state: Mutex<WasmState>,
+}
+
+impl WasmFilter {
+    /// Compile and instantiate the module at `path`
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let err = |e: wasmtime::Error| format!("Failed to load {}: {}", path.display(), e);
+
+        let engine = Engine::default();
+        let module = Module::from_file(&engine, path).map_err(err)?;
+        let mut store = Store::new(&engine, ());
+        let instance = Instance::new(&mut store, &module, &[]).map_err(err)?;
+
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| format!("{} does not export `memory`", path.display()))?;
+        let alloc = instance
+            .get_typed_func(&mut store, "rfind_alloc")
+            .map_err(err)?;
+        let filter = instance
+            .get_typed_func(&mut store, "rfind_filter")
+            .map_err(err)?;
+
+        Ok(WasmFilter {
+            state: Mutex::new(WasmState {
+                store,
+                memory,
+                alloc,
+                filter,
+            }),
+        })
+    }
+}
+
+impl EntryFilter for WasmFilter {
+    fn matches(&self, entry: &EntryInfo) -> bool {
+        let path = entry.path.to_string_lossy();
+        let path_len = match i32::try_from(path.len()) {
+            Ok(len) => len,
+            Err(_) => return false,
+        };
+
+        let mut state = self.state.lock();
+        let WasmState {
+            store,
+            memory,
+            alloc,
+            filter,
+        } = &mut *state;
+
+        let result = alloc.call(&mut *store, path_len).and_then(|ptr| {
+            memory.write(&mut *store, ptr as usize, path.as_bytes())?;
+            filter.call(
+                &mut *store,
+                (
+                    ptr,
+                    path_len,
+                    entry.metadata.len() as i64,
+                    entry_kind(entry.metadata) as i32,
+                    entry_mtime(entry.metadata),
+                ),
+            )
+        });
+
+        match result {
+            Ok(keep) => keep != 0,
+            Err(e) => {
+                log::debug!("WASM filter failed on {:?}: {}", entry.path, e);
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,452 @@
+use rfind::actions::{Action, Executor, Operation};
+use rfind::journal::{self, JournalOperation};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+/// rfind with its home (and so its undo journal) inside `home`
+fn rfind(home: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_rfind"));
+    command.env("HOME", home);
+    command
+}
+
+#[test]
+fn test_action_planning() {
+    let executor = Executor::new(
+        vec![
+            Action::Exec(vec!["gzip".into(), "-t".into(), "{}".into()]),
+            Action::parse_rename(".log=.old").unwrap(),
+            Action::MoveTo("/archive".into()),
+        ],
+        true,
+    );
+    let operations = executor.plan(Path::new("/var/log/app.log"));
+    assert_eq!(
+        operations,
+        vec![
+            Operation::Exec(vec!["gzip".into(), "-t".into(), "/var/log/app.log".into()]),
+            Operation::Move {
+                from: "/var/log/app.log".into(),
+                to: "/var/log/app.old".into()
+            },
+            Operation::Move {
+                from: "/var/log/app.old".into(),
+                to: "/archive/app.old".into()
+            },
+        ]
+    );
+    assert!(!operations[0].is_mutating());
+    assert!(operations[1].is_mutating());
+    assert!(Action::Exec(vec!["/bin/rm".into()])
+        .plan(Path::new("x"))
+        .is_mutating());
+    assert!(Action::parse_rename("no-separator").is_err());
+}
+
+#[test]
+fn test_dry_run_changes_nothing() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let home = TempDir::new_in(".")?;
+    let base_path = temp_dir.path();
+    fs::write(base_path.join("a.tmp"), "a")?;
+    fs::write(base_path.join("b.txt"), "b")?;
+
+    let run = |args: &[&str]| {
+        rfind(home.path())
+            .arg(".tmp")
+            .arg("--dir")
+            .arg(base_path)
+            .args(args)
+            .output()
+    };
+
+    let output = run(&["--delete", "--dry-run"])?;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        format!("would delete {}\n", base_path.join("a.tmp").display())
+    );
+    assert!(base_path.join("a.tmp").exists());
+
+    let output = run(&["--rename", ".tmp=.bak"])?;
+    assert!(output.status.success());
+    assert!(!base_path.join("a.tmp").exists());
+    assert!(base_path.join("a.bak").exists());
+    assert!(base_path.join("b.txt").exists());
+
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+#[test]
+fn test_delete_to_trash() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path().canonicalize()?;
+    let data_home = base_path.join("data");
+    let files = base_path.join("files");
+    fs::create_dir_all(&files)?;
+    fs::write(files.join("old.tmp"), "a")?;
+
+    let output = rfind(&base_path)
+        .arg(".tmp")
+        .arg("--dir")
+        .arg(&files)
+        .args(["--delete", "--trash"])
+        .env("XDG_DATA_HOME", &data_home)
+        .output()?;
+    assert!(output.status.success());
+    assert!(!files.join("old.tmp").exists());
+    assert!(data_home.join("Trash/files/old.tmp").exists());
+    assert!(data_home.join("Trash/info/old.tmp.trashinfo").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_bulk_delete_needs_force() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let home = TempDir::new_in(".")?;
+    let base_path = temp_dir.path();
+    for i in 0..5 {
+        fs::write(base_path.join(format!("{}.tmp", i)), "a")?;
+    }
+
+    let run = |args: &[&str]| {
+        rfind(home.path())
+            .arg(".tmp")
+            .arg("--dir")
+            .arg(base_path)
+            .args(["--delete", "--max-affected", "3"])
+            .args(args)
+            .stdin(std::process::Stdio::null())
+            .output()
+    };
+
+    // No terminal to confirm on, so nothing may be touched
+    let output = run(&[])?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)?.contains("--force"));
+    assert_eq!(fs::read_dir(base_path)?.count(), 5);
+
+    let output = run(&["--force"])?;
+    assert!(output.status.success());
+    assert_eq!(fs::read_dir(base_path)?.count(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_undo_moves() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let home = TempDir::new_in(".")?;
+    let base_path = temp_dir.path().canonicalize()?;
+    let archive = base_path.join("archive");
+    fs::create_dir(&archive)?;
+    fs::write(base_path.join("a.tmp"), "a")?;
+
+    let output = rfind(home.path())
+        .arg("a.tmp")
+        .arg("--dir")
+        .arg(&base_path)
+        .args(["--rename", ".tmp=.old", "--move-to"])
+        .arg(&archive)
+        .output()?;
+    assert!(output.status.success());
+    assert!(archive.join("a.old").exists());
+
+    let journal_dir = home.path().join(".rfind/journal");
+    let journal = fs::read_dir(&journal_dir)?.next().ok_or("no journal")??;
+    let run_id = journal.path().file_stem().ok_or("bad name")?.to_owned();
+    let entries = journal::load(&journal_dir, &run_id.to_string_lossy())?;
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].operation, JournalOperation::Move);
+    assert_eq!(entries[0].path, base_path.join("a.tmp"));
+    assert!(entries[0].checksum.is_some());
+
+    let output = rfind(home.path()).arg("undo").arg(&run_id).output()?;
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(base_path.join("a.tmp"))?, "a");
+    assert!(!archive.join("a.old").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_delete_empty_dirs() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let home = TempDir::new_in(".")?;
+    let base_path = temp_dir.path();
+    fs::create_dir_all(base_path.join("a/b/c"))?;
+    fs::create_dir_all(base_path.join("a/d"))?;
+    fs::create_dir_all(base_path.join("kept/e"))?;
+    fs::write(base_path.join("kept/file.txt"), "x")?;
+
+    let run = |args: &[&str]| {
+        rfind(home.path())
+            .arg("--dir")
+            .arg(base_path)
+            .arg("--delete-empty-dirs")
+            .args(args)
+            .output()
+    };
+
+    // Parents emptied by the removals are removed too, deepest first
+    let output = run(&["--dry-run"])?;
+    assert!(output.status.success());
+    let planned: Vec<String> = String::from_utf8(output.stdout)?
+        .lines()
+        .map(|line| line.to_string())
+        .collect();
+    assert_eq!(planned.len(), 5);
+    let position = |dir: &str| {
+        let line = format!("would delete {}", base_path.join(dir).display());
+        planned.iter().position(|planned| *planned == line)
+    };
+    assert!(position("a/b/c") < position("a/b"));
+    assert!(position("a/b") < position("a"));
+    assert!(position("a/d") < position("a"));
+    assert!(position("kept/e").is_some());
+    assert!(base_path.join("a/b/c").exists());
+
+    let output = run(&[])?;
+    assert!(output.status.success());
+    assert!(!base_path.join("a").exists());
+    assert!(!base_path.join("kept/e").exists());
+    assert!(base_path.join("kept/file.txt").exists());
+    assert!(base_path.exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_delete_with_matches_spilled_to_disk() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let home = TempDir::new_in(".")?;
+    let base_path = temp_dir.path();
+    for i in 0..50 {
+        fs::write(base_path.join(format!("old{}.tmp", i)), "x")?;
+    }
+    fs::write(base_path.join("keep.txt"), "x")?;
+
+    // A 1 KiB budget only holds a handful of paths
+    let output = rfind(home.path())
+        .arg("*.tmp")
+        .arg("--dir")
+        .arg(base_path)
+        .args(["--delete", "--max-memory", "1k"])
+        .output()?;
+    assert!(output.status.success());
+    let remaining: Vec<_> = fs::read_dir(base_path)?
+        .map(|entry| entry.map(|entry| entry.file_name()))
+        .collect::<Result<_, _>>()?;
+    assert_eq!(remaining, vec!["keep.txt"]);
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_broken_symlink_cleanup() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let home = TempDir::new_in(".")?;
+    let base_path = temp_dir.path();
+    fs::write(base_path.join("target.txt"), "x")?;
+    std::os::unix::fs::symlink("target.txt", base_path.join("good"))?;
+    std::os::unix::fs::symlink("moved.txt", base_path.join("stale"))?;
+
+    let run = |args: &[&str]| {
+        rfind(home.path())
+            .arg("--dir")
+            .arg(base_path)
+            .arg("--broken-symlinks")
+            .args(args)
+            .output()
+    };
+
+    let output = run(&["--list-targets"])?;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        format!("{} -> moved.txt\n", base_path.join("stale").display())
+    );
+
+    let output = run(&["--delete"])?;
+    assert!(output.status.success());
+    assert!(fs::symlink_metadata(base_path.join("stale")).is_err());
+    assert!(fs::symlink_metadata(base_path.join("good")).is_ok());
+    assert!(base_path.join("target.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_ok_asks_before_each_deletion() -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new_in(".")?;
+    let home = TempDir::new_in(".")?;
+    let base_path = temp_dir.path();
+    fs::write(base_path.join("a.tmp"), "a")?;
+    fs::write(base_path.join("b.tmp"), "b")?;
+
+    let mut child = rfind(home.path())
+        .arg(".tmp")
+        .arg("--dir")
+        .arg(base_path)
+        .args(["--delete", "--ok"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(b"y\nn\n")?;
+    let output = child.wait_with_output()?;
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8(output.stderr)?;
+    assert_eq!(stderr.matches("? [y/N] ").count(), 2);
+    assert!(stderr.contains("delete "));
+    assert_eq!(fs::read_dir(base_path)?.count(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_delete_removes_directories_last() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let home = TempDir::new_in(".")?;
+    let base_path = temp_dir.path();
+    fs::create_dir_all(base_path.join("old.tmp/nested.tmp"))?;
+    fs::write(base_path.join("old.tmp/a.tmp"), "a")?;
+    fs::write(base_path.join("old.tmp/nested.tmp/b.tmp"), "b")?;
+    fs::create_dir(base_path.join("kept.tmp"))?;
+    fs::write(base_path.join("kept.tmp/keep.txt"), "k")?;
+
+    let output = rfind(home.path())
+        .arg("*.tmp")
+        .arg("--dir")
+        .arg(base_path)
+        .args(["--delete", "--force"])
+        .output()?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr)?;
+    // Only the directory with an unmatched file in it is left
+    let failures: Vec<&str> = stderr
+        .lines()
+        .filter(|line| line.starts_with("Failed"))
+        .collect();
+    assert_eq!(failures.len(), 1, "{}", stderr);
+    assert!(failures[0].contains("kept.tmp"));
+    assert!(!base_path.join("old.tmp").exists());
+    assert!(base_path.join("kept.tmp/keep.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_delete_refuses_filesystem_root() -> Result<(), Box<dyn std::error::Error>> {
+    let home = TempDir::new_in(".")?;
+    let output = rfind(home.path())
+        .arg("no-such-file-anywhere")
+        .args(["--dir", "/", "--delete"])
+        .output()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)?.contains("Refusing"));
+
+    Ok(())
+}
+
+#[test]
+fn test_batched_exec() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let home = TempDir::new_in(".")?;
+    let base_path = temp_dir.path();
+    for i in 0..3 {
+        fs::write(base_path.join(format!("{}.tmp", i)), "a")?;
+    }
+
+    let command: Vec<String> = ["echo", "{}", "+"].iter().map(|s| s.to_string()).collect();
+    assert!(
+        matches!(Action::exec(&command), Ok(Action::ExecBatch(program)) if program == ["echo"])
+    );
+    assert!(matches!(Action::exec(&command[..2]), Ok(Action::Exec(_))));
+    let swallowed: Vec<String> = ["echo", "{}", "+", "-d", "."]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    assert!(Action::exec(&swallowed).is_err());
+    assert!(Action::exec(&command[1..]).is_err());
+
+    // One command gets every match
+    let output = rfind(home.path())
+        .arg(".tmp")
+        .arg("--dir")
+        .arg(base_path)
+        .args(["--exec-jobs", "2"])
+        .args(["--exec", "sh", "-c", "echo $#", "sh", "{}", "+"])
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, "3\n");
+
+    let output = rfind(home.path())
+        .arg(".tmp")
+        .arg("--dir")
+        .arg(base_path)
+        .arg("--dry-run")
+        .args(["--exec", "rm", "{}", "+"])
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.starts_with("would exec rm "));
+    assert_eq!(fs::read_dir(base_path)?.count(), 3);
+
+    // Matches are never run as the command
+    let output = rfind(home.path())
+        .arg(".tmp")
+        .arg("--dir")
+        .arg(base_path)
+        .args(["--exec", "{}", "+"])
+        .output()?;
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8(output.stderr)?.contains("needs a command"));
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_exec_keeps_non_utf8_paths() -> Result<(), Box<dyn std::error::Error>> {
+    use std::ffi::{OsStr, OsString};
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+    let path = Path::new(OsStr::from_bytes(b"/data/caf\xe9.txt"));
+    let action = Action::Exec(vec!["cmp".into(), "--file={}".into(), "{}.bak".into()]);
+    assert_eq!(
+        action.plan(path),
+        Operation::Exec(vec![
+            "cmp".into(),
+            OsString::from_vec(b"--file=/data/caf\xe9.txt".to_vec()),
+            OsString::from_vec(b"/data/caf\xe9.txt.bak".to_vec()),
+        ])
+    );
+
+    // The command runs on the file itself, not on a lossy copy of its path
+    let temp_dir = TempDir::new_in(".")?;
+    let home = TempDir::new_in(".")?;
+    let dir = temp_dir.path().join(OsStr::from_bytes(b"caf\xe9"));
+    fs::create_dir(&dir)?;
+    fs::write(dir.join("menu.txt"), "x")?;
+    let output = rfind(home.path())
+        .args(["menu.txt", "--dir"])
+        .arg(temp_dir.path())
+        .args(["--exec", "touch", "{}.seen", ";"])
+        .output()?;
+    assert!(output.status.success());
+    assert!(dir.join("menu.txt.seen").exists());
+
+    Ok(())
+}
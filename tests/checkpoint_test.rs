@@ -0,0 +1,73 @@
+use rfind::checkpoint::{Checkpoint, PendingDir};
+use rfind::{Search, SearchOptions};
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+#[test]
+fn test_resume_from_checkpoint() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path().canonicalize()?;
+    let tree = base_path.join("tree");
+    for dir in ["a", "b", "b/c"] {
+        fs::create_dir_all(tree.join(dir))?;
+        fs::write(tree.join(dir).join("x.log"), "x")?;
+    }
+    fs::write(tree.join("top.log"), "x")?;
+    let checkpoint_path = base_path.join("scan.checkpoint");
+
+    // Pretend the scan was interrupted with `b` still to do and one result queued
+    let mut checkpoint = Checkpoint::new(&tree, ".log");
+    checkpoint.pending = vec![PendingDir {
+        path: tree.join("b"),
+        depth: 1,
+    }];
+    checkpoint.emitted = vec![tree.join("top.log")];
+    checkpoint.queued = vec![tree.join("a/x.log")];
+    checkpoint.save(&checkpoint_path)?;
+
+    let resume = Checkpoint::load(&checkpoint_path)?;
+    assert!(resume.check_compatible(&tree, ".log").is_ok());
+    assert!(resume.check_compatible(&tree, ".txt").is_err());
+
+    let mut options = SearchOptions::new(".log", tree.clone());
+    options.checkpoint = Some(checkpoint_path.clone());
+    options.resume = Some(resume);
+    let results: HashSet<PathBuf> = Search::start(options)?.collect();
+
+    let expected: HashSet<PathBuf> = ["a/x.log", "b/x.log", "b/c/x.log"]
+        .iter()
+        .map(|path| tree.join(path))
+        .collect();
+    assert_eq!(results, expected);
+    // A completed scan leaves nothing to resume
+    assert!(!checkpoint_path.exists());
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_max_errors_is_not_an_interruption() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path().canonicalize()?;
+    // Following a link to itself fails every time
+    for i in 0..5 {
+        let name = format!("loop{}", i);
+        std::os::unix::fs::symlink(&name, base_path.join(&name))?;
+    }
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_rfind"))
+        .args(["*", "-L", "-j", "1", "--max-errors", "2", "--checkpoint"])
+        .arg(base_path.join("scan.checkpoint"))
+        .arg("-d")
+        .arg(&base_path)
+        .output()?;
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("Search aborted after"), "{}", stderr);
+    assert!(!stderr.contains("--resume"), "{}", stderr);
+
+    Ok(())
+}
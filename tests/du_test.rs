@@ -0,0 +1,51 @@
+use std::fs;
+use std::process::Command;
+use tempfile::TempDir;
+
+#[test]
+fn test_du_cumulative_sizes() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("src/deep/er")).unwrap();
+    fs::create_dir(dir.path().join("empty")).unwrap();
+    fs::write(dir.path().join("src/deep/er/big"), vec![0; 3000]).unwrap();
+    fs::write(dir.path().join("src/main.rs"), vec![0; 100]).unwrap();
+    fs::write(dir.path().join("README"), vec![0; 50]).unwrap();
+
+    let root = dir.path().canonicalize().unwrap();
+    let du = |extra: &[&str]| -> Vec<(String, String)> {
+        let output = Command::new(env!("CARGO_BIN_EXE_rfind"))
+            .args(["du", ".", "--size-format", "bytes"])
+            .args(extra)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .skip(1)
+            .map(|line| {
+                let (size, path) = line.trim_start().split_once("  ").unwrap();
+                let path = std::path::Path::new(path).strip_prefix(&root).unwrap();
+                (size.to_string(), path.display().to_string())
+            })
+            .collect()
+    };
+    let row = |size: &str, path: &str| (size.to_string(), path.to_string());
+
+    assert_eq!(
+        du(&[]),
+        [
+            row("3150", ""),
+            row("3100", "src"),
+            row("3000", "src/deep"),
+            row("3000", "src/deep/er"),
+            row("0", "empty"),
+        ]
+    );
+    // Deeper files still count towards the directories shown
+    assert_eq!(
+        du(&["--depth", "1", "-j", "1"]),
+        [row("3150", ""), row("3100", "src"), row("0", "empty")]
+    );
+}
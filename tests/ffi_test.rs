@@ -0,0 +1,141 @@
+#![cfg(feature = "ffi")]
+
+use rfind::ffi::*;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::fs;
+use std::ptr;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+extern "C" fn collect(path: *const c_char, user_data: *mut c_void) -> i32 {
+    let paths = unsafe { &mut *(user_data as *mut Vec<String>) };
+    paths.push(
+        unsafe { CStr::from_ptr(path) }
+            .to_string_lossy()
+            .into_owned(),
+    );
+    0
+}
+
+extern "C" fn stop(_path: *const c_char, _user_data: *mut c_void) -> i32 {
+    1
+}
+
+#[test]
+fn test_c_search() {
+    let temp = TempDir::new().unwrap();
+    fs::create_dir(temp.path().join("sub")).unwrap();
+    fs::write(temp.path().join("a.txt"), "").unwrap();
+    fs::write(temp.path().join("sub/b.txt"), "").unwrap();
+    fs::write(temp.path().join("c.rs"), "").unwrap();
+
+    let pattern = CString::new("*.txt").unwrap();
+    let root = CString::new(temp.path().to_str().unwrap()).unwrap();
+    let options = RfindOptions {
+        pattern: pattern.as_ptr(),
+        root: root.as_ptr(),
+        max_depth: 0,
+        threads: 2,
+        follow_symlinks: 0,
+    };
+
+    let mut paths: Vec<String> = Vec::new();
+    let status = unsafe {
+        rfind_search(
+            &options,
+            Some(collect),
+            &mut paths as *mut Vec<String> as *mut c_void,
+            ptr::null(),
+        )
+    };
+    assert_eq!(status, RFIND_OK);
+    paths.sort();
+    assert_eq!(paths.len(), 2);
+    assert!(paths[0].ends_with("a.txt"));
+    assert!(paths[1].ends_with("b.txt"));
+
+    // Stopped by the callback
+    let status = unsafe { rfind_search(&options, Some(stop), ptr::null_mut(), ptr::null()) };
+    assert_eq!(status, RFIND_CANCELLED);
+
+    // Stopped by a handle triggered before the search started
+    let cancel = rfind_cancel_new();
+    let status = unsafe {
+        rfind_cancel_trigger(cancel);
+        let status = rfind_search(
+            &options,
+            Some(collect),
+            &mut paths as *mut _ as *mut c_void,
+            cancel,
+        );
+        rfind_cancel_free(cancel);
+        status
+    };
+    assert_eq!(status, RFIND_CANCELLED);
+
+    let bad_pattern = CString::new("[*.txt").unwrap();
+    let options = RfindOptions {
+        pattern: bad_pattern.as_ptr(),
+        ..options
+    };
+    let status = unsafe { rfind_search(&options, Some(collect), ptr::null_mut(), ptr::null()) };
+    assert_eq!(status, RFIND_ERR_INVALID_PATTERN);
+
+    let status = unsafe { rfind_search(ptr::null(), Some(collect), ptr::null_mut(), ptr::null()) };
+    assert_eq!(status, RFIND_ERR_INVALID_ARGUMENT);
+    let status = unsafe { rfind_search(&options, None, ptr::null_mut(), ptr::null()) };
+    assert_eq!(status, RFIND_ERR_INVALID_ARGUMENT);
+}
+
+extern "C" fn slow(_path: *const c_char, _user_data: *mut c_void) -> i32 {
+    std::thread::sleep(Duration::from_millis(20));
+    0
+}
+
+#[test]
+fn test_c_cancel_handle_stops_every_search() {
+    let temp = TempDir::new().unwrap();
+    for i in 0..200 {
+        fs::write(temp.path().join(format!("{}.txt", i)), "").unwrap();
+    }
+    let root = temp.path().to_str().unwrap().to_string();
+
+    // Pointers are not Send, so the handle crosses threads as an address
+    let cancel = rfind_cancel_new() as usize;
+    let searches: Vec<_> = (0..2)
+        .map(|_| {
+            let root = root.clone();
+            std::thread::spawn(move || {
+                let pattern = CString::new("*.txt").unwrap();
+                let root = CString::new(root).unwrap();
+                let options = RfindOptions {
+                    pattern: pattern.as_ptr(),
+                    root: root.as_ptr(),
+                    max_depth: 0,
+                    threads: 1,
+                    follow_symlinks: 0,
+                };
+                let started = Instant::now();
+                let status = unsafe {
+                    rfind_search(
+                        &options,
+                        Some(slow),
+                        ptr::null_mut(),
+                        cancel as *const RfindCancel,
+                    )
+                };
+                (status, started.elapsed())
+            })
+        })
+        .collect();
+
+    std::thread::sleep(Duration::from_millis(200));
+    unsafe { rfind_cancel_trigger(cancel as *const RfindCancel) };
+    for search in searches {
+        let (status, elapsed) = search.join().unwrap();
+        assert_eq!(status, RFIND_CANCELLED);
+        // 200 matches at 20ms each would take 4s
+        assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+    }
+    unsafe { rfind_cancel_free(cancel as *mut RfindCancel) };
+}
@@ -0,0 +1,328 @@
+use rfind::filters::{self, EntryFilter, EntryInfo, SizeFilter, TypeFilter};
+use std::fs;
+use std::time::SystemTime;
+use tempfile::TempDir;
+
+#[test]
+fn test_entry_filter_combinators() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let file_path = temp_dir.path().join("data.bin");
+    fs::write(&file_path, "a".repeat(2048))?;
+    let dir_path = temp_dir.path().join("subdir");
+    fs::create_dir(&dir_path)?;
+
+    let file_meta = fs::symlink_metadata(&file_path)?;
+    let dir_meta = fs::symlink_metadata(&dir_path)?;
+    let now = SystemTime::now();
+    let file = EntryInfo {
+        path: &file_path,
+        disk_path: &file_path,
+        metadata: &file_meta,
+        now,
+    };
+    let dir = EntryInfo {
+        path: &dir_path,
+        disk_path: &dir_path,
+        metadata: &dir_meta,
+        now,
+    };
+
+    let big_file = TypeFilter::File.and(SizeFilter::parse("+1k")?);
+    assert!(big_file.matches(&file));
+    assert!(!big_file.matches(&dir));
+
+    let not_file = TypeFilter::File.not();
+    assert!(!not_file.matches(&file));
+    assert!(not_file.matches(&dir));
+
+    let named_bin =
+        filters::from_fn(|e: &EntryInfo| e.path.extension().is_some_and(|ext| ext == "bin"));
+    let either = TypeFilter::Dir.or(named_bin);
+    assert!(either.matches(&file));
+    assert!(either.matches(&dir));
+
+    let mut all = filters::AllOf::default();
+    assert!(all.matches(&dir));
+    all.push(TypeFilter::File);
+    all.push(SizeFilter::parse("-1k")?);
+    assert!(!all.matches(&file));
+
+    Ok(())
+}
+
+#[test]
+fn test_where_filter() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let log_path = temp_dir.path().join("server.log");
+    fs::write(&log_path, "a".repeat(4096))?;
+    let metadata = fs::symlink_metadata(&log_path)?;
+    let entry = EntryInfo {
+        path: &log_path,
+        disk_path: &log_path,
+        metadata: &metadata,
+        now: SystemTime::now(),
+    };
+
+    let keep = |expr: &str| filters::WhereFilter::parse(expr).map(|f| f.matches(&entry));
+    assert!(keep(r#"size > 2*KB && name.ends_with(".log")"#)?);
+    assert!(keep(r#"is_file && ext == "log" && age_days < 1"#)?);
+    assert!(!keep("size > MB")?);
+    // Size constants are the units of --size
+    assert!(keep("size > 4*kB && size == 4*KiB")?);
+    // Evaluation errors (here: comparing a string to a number) reject the entry
+    assert!(!keep("name > 3")?);
+    assert!(filters::WhereFilter::parse("size >").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_long_time_units() -> Result<(), Box<dyn std::error::Error>> {
+    use rfind::filters::TimeFilter;
+    use std::time::Duration;
+
+    let now = SystemTime::now();
+    let days_ago = |days: u64| now - Duration::from_secs(days * 24 * 60 * 60);
+
+    assert!(TimeFilter::parse("+6M")?.matches(days_ago(200), now));
+    assert!(!TimeFilter::parse("+6M")?.matches(days_ago(170), now));
+    assert!(TimeFilter::parse("-2w")?.matches(days_ago(13), now));
+    assert!(TimeFilter::parse("1y")?.matches(days_ago(300), now));
+    assert!(!TimeFilter::parse("1y")?.matches(days_ago(100), now));
+    assert!(TimeFilter::parse("3x").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_daystart_counts_calendar_days() -> Result<(), Box<dyn std::error::Error>> {
+    use chrono::{Local, TimeZone};
+    use rfind::filters::TimeFilter;
+
+    let at = |day: u32, hour: u32| -> SystemTime {
+        Local
+            .with_ymd_and_hms(2024, 6, day, hour, 0, 0)
+            .unwrap()
+            .into()
+    };
+    let now = at(12, 9);
+    let late_yesterday = at(11, 23);
+    let early_today = at(12, 1);
+    let daystart = |s: &str| TimeFilter::parse(s).map(TimeFilter::measured_from_daystart);
+
+    // Only ten hours ago, so not a day old by the clock
+    assert!(!TimeFilter::parse("1d")?.matches(late_yesterday, now));
+    assert!(daystart("1d")?.matches(late_yesterday, now));
+    assert!(!daystart("1d")?.matches(early_today, now));
+    assert!(daystart("-1d")?.matches(early_today, now));
+    assert!(!daystart("-1d")?.matches(late_yesterday, now));
+    assert!(daystart("+0d")?.matches(late_yesterday, now));
+    assert!(daystart("+1d")?.matches(at(10, 23), now));
+    assert!(!daystart("+1d")?.matches(late_yesterday, now));
+
+    // Other units still count back from now
+    assert!(daystart("-12h")?.matches(late_yesterday, now));
+    Ok(())
+}
+
+#[test]
+fn test_decimal_and_si_sizes() -> Result<(), Box<dyn std::error::Error>> {
+    assert_eq!(SizeFilter::parse("1.5G")?.to_bytes(), 1_610_612_736);
+    assert_eq!(SizeFilter::parse("1.5GiB")?.to_bytes(), 1_610_612_736);
+    assert_eq!(SizeFilter::parse("+1.5GB")?.to_bytes(), 1_500_000_000);
+    assert_eq!(SizeFilter::parse("2kB")?.to_bytes(), 2000);
+    assert_eq!(SizeFilter::parse("2k")?.to_bytes(), 2048);
+
+    assert!(SizeFilter::parse("+1MB")?.matches(1_000_001));
+    assert!(!SizeFilter::parse("+1M")?.matches(1_000_001));
+
+    assert!(SizeFilter::parse("1.5").is_err());
+    assert!(SizeFilter::parse("+.5M").is_err());
+    assert!(SizeFilter::parse("+nanM").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_find_compatible_sizes() -> Result<(), Box<dyn std::error::Error>> {
+    assert_eq!(SizeFilter::parse("4b")?.to_bytes(), 2048);
+    assert!(SizeFilter::parse("4").is_err());
+
+    // find -size rounds up to whole units: 1 byte is one block, -1M is only empty files
+    let blocks = SizeFilter::parse_find_compat("1")?;
+    assert!(blocks.matches(1));
+    assert!(blocks.matches(512));
+    assert!(!blocks.matches(513));
+    let under_a_mebibyte = SizeFilter::parse_find_compat("-1M")?;
+    assert!(under_a_mebibyte.matches(0));
+    assert!(!under_a_mebibyte.matches(1));
+    assert!(SizeFilter::parse_find_compat("+2k")?.matches(2049));
+    assert!(!SizeFilter::parse_find_compat("+2k")?.matches(2048));
+
+    Ok(())
+}
+
+#[test]
+fn test_local_files_are_not_placeholders() -> Result<(), Box<dyn std::error::Error>> {
+    use rfind::filters::CloudFilter;
+
+    let temp_dir = TempDir::new_in(".")?;
+    let path = temp_dir.path().join("notes.txt");
+    fs::write(&path, "local")?;
+    let metadata = fs::symlink_metadata(&path)?;
+    let entry = EntryInfo {
+        path: &path,
+        disk_path: &path,
+        metadata: &metadata,
+        now: SystemTime::now(),
+    };
+
+    assert!(!rfind::placeholder::is_placeholder(&metadata));
+    assert!(CloudFilter::Local.matches(&entry));
+    assert!(!CloudFilter::Cloud.matches(&entry));
+
+    Ok(())
+}
+
+#[test]
+fn test_icloud_stub_logical_names() {
+    use rfind::placeholder::icloud_logical_name;
+
+    assert_eq!(
+        icloud_logical_name(".Report.pdf.icloud"),
+        Some("Report.pdf")
+    );
+    assert_eq!(icloud_logical_name(".notes.icloud"), Some("notes"));
+    assert_eq!(icloud_logical_name("Report.pdf.icloud"), None);
+    assert_eq!(icloud_logical_name(".Report.pdf"), None);
+    assert_eq!(icloud_logical_name("..icloud"), None);
+}
+
+#[test]
+fn test_newer_reference_comparisons() -> Result<(), Box<dyn std::error::Error>> {
+    use filetime::{set_file_times, FileTime};
+    use rfind::filters::{parse_timestamp, NewerFilter};
+
+    let temp_dir = TempDir::new_in(".")?;
+    let old = temp_dir.path().join("old.txt");
+    let stamp = temp_dir.path().join("build.stamp");
+    let new = temp_dir.path().join("new.txt");
+    for (path, mtime) in [(&old, 1_000_000), (&stamp, 2_000_000), (&new, 3_000_000)] {
+        fs::write(path, "")?;
+        set_file_times(
+            path,
+            FileTime::from_unix_time(5_000_000, 0),
+            FileTime::from_unix_time(mtime, 0),
+        )?;
+    }
+    let matches = |filter: &NewerFilter, path: &std::path::Path| {
+        let metadata = fs::symlink_metadata(path).unwrap();
+        filter.matches(&EntryInfo {
+            path,
+            disk_path: path,
+            metadata: &metadata,
+            now: SystemTime::now(),
+        })
+    };
+
+    let mm = NewerFilter::parse("mm", stamp.to_str().unwrap())?;
+    assert!(matches(&mm, &new));
+    assert!(!matches(&mm, &stamp));
+    assert!(!matches(&mm, &old));
+
+    // Every file was accessed after the stamp was modified
+    let am = NewerFilter::parse("am", stamp.to_str().unwrap())?;
+    assert!(matches(&am, &old));
+
+    let mt = NewerFilter::parse("mt", "@1500000")?;
+    assert!(!matches(&mt, &old));
+    assert!(matches(&mt, &stamp));
+
+    assert!(parse_timestamp("2024-06-01")? < parse_timestamp("2024-06-01 13:30")?);
+    assert_eq!(
+        parse_timestamp("2024-06-01T00:00:00Z")?,
+        SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_717_200_000)
+    );
+    assert!(NewerFilter::parse("mx", "ref").is_err());
+    assert!(NewerFilter::parse("m", "ref").is_err());
+    assert!(NewerFilter::parse("mm", "missing-reference").is_err());
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_filter_command_exit_status() -> Result<(), Box<dyn std::error::Error>> {
+    use rfind::filters::CommandFilter;
+
+    let temp_dir = TempDir::new_in(".")?;
+    let full = temp_dir.path().join("full.txt");
+    let empty = temp_dir.path().join("empty.txt");
+    fs::write(&full, "data")?;
+    fs::write(&empty, "")?;
+    let matches = |filter: &CommandFilter, path: &std::path::Path| {
+        let metadata = fs::symlink_metadata(path).unwrap();
+        filter.matches(&EntryInfo {
+            path,
+            disk_path: path,
+            metadata: &metadata,
+            now: SystemTime::now(),
+        })
+    };
+
+    let non_empty = CommandFilter::parse("test -s {}", 2)?;
+    assert!(matches(&non_empty, &full));
+    assert!(!matches(&non_empty, &empty));
+
+    // The path is appended when the command has no {}
+    let readable = CommandFilter::parse("test -r", 1)?;
+    assert!(matches(&readable, &full));
+
+    // A command that cannot be started rejects everything
+    let missing = CommandFilter::parse("rfind-no-such-command {}", 1)?;
+    assert!(!matches(&missing, &full));
+    assert!(CommandFilter::parse("", 1).is_err());
+
+    // An iCloud stub is checked on disk, not under the name it is reported as
+    let stub = temp_dir.path().join(".Report.pdf.icloud");
+    fs::write(&stub, "bplist")?;
+    let metadata = fs::symlink_metadata(&stub)?;
+    assert!(non_empty.matches(&EntryInfo {
+        path: &temp_dir.path().join("Report.pdf"),
+        disk_path: &stub,
+        metadata: &metadata,
+        now: SystemTime::now(),
+    }));
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlink_target_patterns() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let old_link = temp_dir.path().join("old");
+    let new_link = temp_dir.path().join("new");
+    std::os::unix::fs::symlink("/old/storage/photos/2019", &old_link)?;
+    std::os::unix::fs::symlink("/srv/storage/photos", &new_link)?;
+    let file_path = temp_dir.path().join("plain");
+    fs::write(&file_path, "")?;
+
+    let filter = filters::LinkTargetFilter::new("/old/storage/*")?;
+    let now = SystemTime::now();
+    let matches = |path: &std::path::Path| -> Result<bool, std::io::Error> {
+        let metadata = fs::symlink_metadata(path)?;
+        Ok(filter.matches(&EntryInfo {
+            path,
+            disk_path: path,
+            metadata: &metadata,
+            now,
+        }))
+    };
+    assert!(matches(&old_link)?);
+    assert!(!matches(&new_link)?);
+    assert!(!matches(&file_path)?);
+
+    assert!(filters::LinkTargetFilter::new("[").is_err());
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use rfind::format::{format_size, format_time, SizeFormat, TimeFormat};
+use std::time::{Duration, UNIX_EPOCH};
+
+#[test]
+fn test_size_formats() {
+    assert_eq!(format_size(1_500_000_000, SizeFormat::Bytes), "1500000000");
+
+    assert_eq!(format_size(999, SizeFormat::Human), "999");
+    assert_eq!(format_size(4096, SizeFormat::Human), "4.0K");
+    assert_eq!(format_size(1_500_000_000, SizeFormat::Human), "1.4G");
+    assert_eq!(format_size(15 * 1024 * 1024, SizeFormat::Human), "15M");
+    assert_eq!(format_size(1024 * 1024 - 1, SizeFormat::Human), "1.0M");
+
+    assert_eq!(format_size(1_500_000_000, SizeFormat::Si), "1.5G");
+    assert_eq!(format_size(2000, SizeFormat::Si), "2.0k");
+    assert_eq!("si".parse(), Ok(SizeFormat::Si));
+    assert!("kb".parse::<SizeFormat>().is_err());
+}
+
+#[test]
+fn test_time_formats() {
+    let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let day = Duration::from_secs(24 * 60 * 60);
+
+    assert_eq!(format_time(now, now, &TimeFormat::Epoch), "1700000000");
+    assert_eq!(
+        format_time(now - 3 * day, now, &TimeFormat::Relative),
+        "3 days ago"
+    );
+    assert_eq!(
+        format_time(now - Duration::from_secs(60), now, &TimeFormat::Relative),
+        "1 minute ago"
+    );
+    assert_eq!(
+        format_time(now + 400 * day, now, &TimeFormat::Relative),
+        "in 1 year"
+    );
+
+    let year = TimeFormat::strftime("%Y").unwrap();
+    assert_eq!(format_time(now, now, &year), "2023");
+    assert!(TimeFormat::strftime("%Q").is_err());
+    assert_eq!("relative".parse(), Ok(TimeFormat::Relative));
+}
@@ -0,0 +1,137 @@
+#![cfg(feature = "grpc")]
+
+use rfind::grpc::proto::{SearchRequest, SearchResult, StatusRequest, StatusResponse};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use tempfile::TempDir;
+use tonic::client::Grpc;
+use tonic::codec::ProstCodec;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::transport::Channel;
+use tonic::{Code, Request};
+
+/// Stream every path found by a `Search` call
+async fn search(channel: &Channel, request: SearchRequest) -> Result<Vec<String>, tonic::Status> {
+    let mut client = Grpc::new(channel.clone());
+    client.ready().await.unwrap();
+    let mut stream = client
+        .server_streaming(
+            Request::new(request),
+            PathAndQuery::from_static("/rfind.v1.Finder/Search"),
+            ProstCodec::<SearchRequest, SearchResult>::default(),
+        )
+        .await?
+        .into_inner();
+    let mut paths = Vec::new();
+    while let Some(result) = stream.message().await? {
+        paths.push(result.path);
+    }
+    Ok(paths)
+}
+
+async fn status(channel: &Channel) -> StatusResponse {
+    let mut client = Grpc::new(channel.clone());
+    client.ready().await.unwrap();
+    client
+        .unary(
+            Request::new(StatusRequest {}),
+            PathAndQuery::from_static("/rfind.v1.Finder/Status"),
+            ProstCodec::<StatusRequest, StatusResponse>::default(),
+        )
+        .await
+        .unwrap()
+        .into_inner()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_grpc_search() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let base_path = temp_dir.path();
+    fs::create_dir(base_path.join("logs"))?;
+    fs::write(base_path.join("logs/app.log"), "log")?;
+    fs::write(base_path.join("logs/old.log"), "log")?;
+    fs::write(base_path.join("notes.txt"), "notes")?;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rfind"))
+        .args(["grpc", "--listen", "127.0.0.1:0", "-j", "2", "-d"])
+        .arg(base_path)
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let mut line = String::new();
+    BufReader::new(child.stderr.take().unwrap()).read_line(&mut line)?;
+    let addr = line
+        .trim()
+        .strip_prefix("Listening on ")
+        .and_then(|rest| rest.strip_suffix(" (gRPC)"))
+        .expect("server should announce its address")
+        .to_string();
+
+    let result = async {
+        let channel = Channel::from_shared(format!("http://{}", addr))?
+            .connect()
+            .await?;
+
+        // Results are streamed from the server's default directory
+        let mut paths = search(
+            &channel,
+            SearchRequest {
+                pattern: "*.log".to_string(),
+                r#type: "f".to_string(),
+                ..SearchRequest::default()
+            },
+        )
+        .await?;
+        paths.sort();
+        assert_eq!(paths.len(), 2);
+        assert!(paths[0].ends_with("logs/app.log"));
+        assert!(paths[1].ends_with("logs/old.log"));
+
+        // The limit ends the stream early
+        let paths = search(
+            &channel,
+            SearchRequest {
+                pattern: "*.log".to_string(),
+                limit: 1,
+                ..SearchRequest::default()
+            },
+        )
+        .await?;
+        assert_eq!(paths.len(), 1);
+
+        let paths = search(
+            &channel,
+            SearchRequest {
+                pattern: "notes".to_string(),
+                dir: base_path.to_string_lossy().into_owned(),
+                ..SearchRequest::default()
+            },
+        )
+        .await?;
+        assert_eq!(paths.len(), 1);
+        assert!(paths[0].ends_with("notes.txt"));
+
+        let error = search(
+            &channel,
+            SearchRequest {
+                pattern: "x".to_string(),
+                size: "huge".to_string(),
+                ..SearchRequest::default()
+            },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.code(), Code::InvalidArgument);
+
+        let status = status(&channel).await;
+        assert_eq!(status.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(status.total_searches, 3);
+        Ok::<_, Box<dyn std::error::Error>>(())
+    }
+    .await;
+
+    child.kill()?;
+    child.wait()?;
+    result
+}
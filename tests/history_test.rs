@@ -0,0 +1,48 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+/// rfind with its home (and so its history) inside `home`
+fn rfind(home: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_rfind"));
+    command.env("HOME", home).env_remove("RFIND_HISTORY");
+    command
+}
+
+#[test]
+fn test_history_and_rerun() {
+    let home = TempDir::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.log"), "").unwrap();
+    fs::write(dir.path().join("b.log"), "").unwrap();
+
+    // Nothing is recorded unless asked for
+    rfind(home.path())
+        .args(["*.log", "-d", "."])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(!home.path().join(".rfind/history").exists());
+
+    let output = rfind(home.path())
+        .env("RFIND_HISTORY", "1")
+        .args(["*.log", "-d", "."])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let output = rfind(home.path()).arg("history").output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("   1  "));
+    assert!(stdout.contains("2 result(s)"));
+    assert!(stdout.contains("rfind '*.log' -d ."));
+
+    // Relative paths are resolved from the original directory
+    let output = rfind(home.path()).args(["rerun", "1"]).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("a.log"));
+    assert!(stdout.contains("b.log"));
+}
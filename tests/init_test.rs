@@ -0,0 +1,80 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn rfind_init(shell: &str) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rfind"))
+        .args(["init", shell])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_init_scripts() {
+    // The functions, the jump database hook and the key binding of each shell
+    let expected: &[(&str, &[&str])] = &[
+        (
+            "bash",
+            &[
+                "rcd() {",
+                "rj() {",
+                "command rfind jump --add",
+                r#"bind -x '"\C-xf": __rfind_insert_path'"#,
+            ],
+        ),
+        (
+            "zsh",
+            &[
+                "rcd() {",
+                "rj() {",
+                "command rfind jump --add",
+                "zle -N __rfind_insert_path",
+                "bindkey '^Xf' __rfind_insert_path",
+            ],
+        ),
+        (
+            "fish",
+            &[
+                "function rcd",
+                "function rj",
+                "command rfind jump --add",
+                r"bind \cxf __rfind_insert_path",
+            ],
+        ),
+    ];
+    for (shell, lines) in expected {
+        let output = rfind_init(shell);
+        assert!(output.status.success());
+        let script = String::from_utf8(output.stdout).unwrap();
+        assert!(
+            script.starts_with(&format!("# rfind shell integration for {}", shell)),
+            "{}",
+            script
+        );
+        for line in *lines {
+            assert!(script.contains(line), "{} script lacks {:?}", shell, line);
+        }
+    }
+
+    let output = rfind_init("tcsh");
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+/// Scripts are at least valid syntax for each shell that is installed
+#[cfg(unix)]
+#[test]
+fn test_init_scripts_parse() {
+    for (shell, check) in [("bash", "-n"), ("zsh", "-n"), ("fish", "--no-execute")] {
+        let mut child = match Command::new(shell).arg(check).stdin(Stdio::piped()).spawn() {
+            Ok(child) => child,
+            Err(_) => continue,
+        };
+        let script = rfind_init(shell).stdout;
+        child.stdin.take().unwrap().write_all(&script).unwrap();
+        assert!(
+            child.wait().unwrap().success(),
+            "{} script does not parse",
+            shell
+        );
+    }
+}
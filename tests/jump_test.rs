@@ -0,0 +1,33 @@
+use rfind::jump::JumpDatabase;
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_jump_ranking() {
+    let root = TempDir::new().unwrap();
+    let api = root.path().join("projects").join("api");
+    let docs = root.path().join("projects").join("api-docs");
+    fs::create_dir_all(&api).unwrap();
+    fs::create_dir_all(&docs).unwrap();
+
+    let mut database = JumpDatabase::default();
+    database.add(&docs);
+    database.add(&api);
+    database.add(&api);
+    database.add(&root.path().join("gone"));
+
+    let keywords = vec!["proj".to_string(), "api".to_string()];
+    assert_eq!(
+        database.query(&keywords),
+        vec![api.as_path(), docs.as_path()]
+    );
+    // The last keyword has to match the directory name
+    assert!(database
+        .query(&["api".to_string(), "proj".to_string()])
+        .is_empty());
+
+    // Round trip through the file
+    let file = root.path().join("jump.json");
+    database.save(&file).unwrap();
+    assert_eq!(JumpDatabase::load(&file).unwrap(), database);
+}
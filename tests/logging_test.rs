@@ -0,0 +1,59 @@
+use serde_json::Value;
+use std::fs;
+use std::process::Command;
+use tempfile::TempDir;
+
+#[test]
+fn test_json_error_log() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let missing = temp_dir.path().join("missing");
+    let log_path = temp_dir.path().join("errors.log");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rfind"))
+        .arg("*")
+        .arg("--dir")
+        .arg(&missing)
+        .args(["--log-format", "json", "--error-log"])
+        .arg(&log_path)
+        .output()?;
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let log = fs::read_to_string(&log_path)?;
+    let events: Vec<Value> = log
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["level"], "warn");
+    assert_eq!(events[0]["operation"], "read_dir");
+    assert_eq!(events[0]["path"], missing.to_string_lossy().as_ref());
+    assert!(events[0]["error"].is_string());
+
+    Ok(())
+}
+
+#[test]
+fn test_stats_json_summary() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    fs::create_dir(temp_dir.path().join("sub"))?;
+    fs::write(temp_dir.path().join("a.txt"), "")?;
+    fs::write(temp_dir.path().join("sub").join("b.txt"), "")?;
+    fs::write(temp_dir.path().join("sub").join("c.md"), "")?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rfind"))
+        .args(["*.txt", "--stats-json", "--print0", "--dir"])
+        .arg(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8(output.stderr)?;
+    let summary: Value = serde_json::from_str(stderr.lines().last().unwrap())?;
+    assert_eq!(summary["matches"], 2);
+    assert_eq!(summary["dirs_scanned"], 2);
+    assert_eq!(summary["entries_scanned"], 4);
+    assert_eq!(summary["errors"], 0);
+    assert!(summary["duration_ms"].is_u64());
+
+    Ok(())
+}
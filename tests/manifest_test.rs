@@ -0,0 +1,78 @@
+use parking_lot::Mutex;
+use rfind::manifest::{self, Drift, ManifestWriter};
+use rfind::{ErrorHandler, ScanError};
+use std::fs;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+#[test]
+fn test_manifest_drift() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path();
+    let (a, b, c) = (
+        base_path.join("a.txt"),
+        base_path.join("b.txt"),
+        base_path.join("c.txt"),
+    );
+    fs::write(&a, "a")?;
+    fs::write(&b, "b")?;
+    fs::write(&c, "c")?;
+
+    let manifest_path = base_path.join("manifest.jsonl");
+    let mut writer = ManifestWriter::create(&manifest_path, true)?;
+    for path in [&a, &b, &c] {
+        writer.add(path)?;
+    }
+    drop(writer);
+
+    let entries = manifest::load(&manifest_path)?;
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].size, 1);
+    assert!(entries[0].checksum.is_some());
+    assert!(
+        manifest::verify(entries.clone(), vec![a.clone(), b.clone(), c.clone()], None).is_empty()
+    );
+
+    // Same size, different contents: only the checksum gives it away
+    fs::write(&b, "B")?;
+    fs::remove_file(&c)?;
+    let d = base_path.join("d.txt");
+    fs::write(&d, "d")?;
+
+    let drift = manifest::verify(entries, vec![a, b.clone(), d.clone()], None);
+    assert!(drift.contains(&Drift::Changed(b, vec!["checksum"])));
+    assert!(drift.contains(&Drift::Added(d)));
+    assert!(drift.contains(&Drift::Removed(c)));
+    assert_eq!(drift.len(), 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_manifest_max_filesize() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path();
+    let (small, large) = (base_path.join("small.bin"), base_path.join("large.bin"));
+    fs::write(&small, "a")?;
+    fs::write(&large, "a".repeat(100))?;
+
+    let skipped = Arc::new(Mutex::new(Vec::new()));
+    let on_skip: ErrorHandler = {
+        let skipped = Arc::clone(&skipped);
+        Arc::new(move |error: &ScanError| skipped.lock().push(error.path.clone()))
+    };
+    let manifest_path = base_path.join("manifest.jsonl");
+    let mut writer =
+        ManifestWriter::create(&manifest_path, true)?.with_max_filesize(10, Some(on_skip));
+    writer.add(&small)?;
+    writer.add(&large)?;
+    drop(writer);
+
+    let entries = manifest::load(&manifest_path)?;
+    assert!(entries[0].checksum.is_some());
+    assert_eq!(entries[1].size, 100);
+    assert!(entries[1].checksum.is_none());
+    assert_eq!(*skipped.lock(), vec![large]);
+
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use rfind::memory::{path_cost, MemoryBudget, PathBuffer, VisitedSet};
+use std::path::{Path, PathBuf};
+
+#[test]
+fn test_path_buffer_spills_past_budget() -> Result<(), Box<dyn std::error::Error>> {
+    let paths: Vec<PathBuf> = (0..100)
+        .map(|i| PathBuf::from(format!("/data/dir{}/file{}.log", i / 10, i)))
+        .collect();
+    let budget = MemoryBudget::new(path_cost(&paths[0]) * 10);
+
+    let mut buffer = PathBuffer::new(Some(budget.clone()));
+    for path in &paths {
+        buffer.push(path.clone())?;
+    }
+    assert!(buffer.is_spilled());
+    assert_eq!(buffer.len(), paths.len());
+    // Spilled paths no longer count against the budget
+    assert_eq!(budget.used(), 0);
+
+    // Read back in order, as many times as needed
+    for _ in 0..2 {
+        let read: Vec<PathBuf> = buffer.iter()?.collect::<Result<_, _>>()?;
+        assert_eq!(read, paths);
+    }
+    assert_eq!(buffer.into_vec()?, paths);
+
+    // Within the budget nothing touches the disk
+    let mut buffer = PathBuffer::new(Some(MemoryBudget::new(1 << 20)));
+    buffer.push(paths[0].clone())?;
+    assert!(!buffer.is_spilled());
+    assert_eq!(buffer.into_vec()?, vec![paths[0].clone()]);
+
+    Ok(())
+}
+
+#[test]
+fn test_visited_set_falls_back_to_fingerprints() {
+    let budget = MemoryBudget::new(path_cost(Path::new("/a")));
+    let mut visited = VisitedSet::new(Some(budget.clone()));
+
+    assert!(visited.insert("/a".into()));
+    assert!(!visited.insert("/a".into()));
+    // Over budget, new paths are still told apart
+    assert!(visited.insert("/b".into()));
+    assert!(visited.insert("/c".into()));
+    assert!(!visited.insert("/b".into()));
+    assert_eq!(budget.used(), path_cost(Path::new("/a")));
+
+    drop(visited);
+    assert_eq!(budget.used(), 0);
+}
@@ -0,0 +1,650 @@
+use std::fs;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn rfind_in(dir: &TempDir, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_rfind"))
+        .args(args)
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_strip_cwd_prefix() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("sub")).unwrap();
+    fs::write(dir.path().join("sub").join("a.txt"), "").unwrap();
+
+    let absolute = rfind_in(&dir, &["a.txt", "-d", "."]);
+    assert!(absolute.trim_end().ends_with("sub/a.txt"));
+    assert!(absolute.starts_with('/'));
+
+    assert_eq!(
+        rfind_in(&dir, &["a.txt", "-d", ".", "--strip-cwd-prefix"]),
+        "sub/a.txt\n"
+    );
+    assert_eq!(
+        rfind_in(&dir, &["a.txt", "-d", "sub", "--strip-cwd-prefix=never"]),
+        "./sub/a.txt\n"
+    );
+    assert_eq!(
+        rfind_in(
+            &dir,
+            &["a.txt", "-d", ".", "--strip-cwd-prefix", "--print0"]
+        ),
+        "./sub/a.txt\0"
+    );
+}
+
+#[test]
+fn test_rfind_opts_defaults() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.txt"), "").unwrap();
+
+    // Options from RFIND_OPTS come first, so the command line can override them
+    let output = Command::new(env!("CARGO_BIN_EXE_rfind"))
+        .env("RFIND_OPTS", "--strip-cwd-prefix=never -j 2")
+        .args(["a.txt", "-d", ".", "-j", "1"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "./a.txt\n");
+}
+
+#[test]
+fn test_profiles() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.rs"), "").unwrap();
+    fs::write(dir.path().join("a.txt"), "").unwrap();
+    let config = dir.path().join("config.toml");
+    fs::write(
+        &config,
+        "[profiles]\nrust = [\"--where\", 'ext == \"rs\"', \"--strip-cwd-prefix\"]\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rfind"))
+        .env("RFIND_CONFIG", &config)
+        .args(["a.*", "-d", ".", "--profile", "rust"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "a.rs\n");
+
+    let status = Command::new(env!("CARGO_BIN_EXE_rfind"))
+        .env("RFIND_CONFIG", &config)
+        .args(["a.*", "-d", ".", "--profile", "missing"])
+        .status()
+        .unwrap();
+    assert!(!status.success());
+}
+
+#[test]
+fn test_has_file_finds_project_roots() {
+    let dir = TempDir::new().unwrap();
+    for project in ["app", "libs/core", "notes"] {
+        fs::create_dir_all(dir.path().join(project)).unwrap();
+    }
+    fs::create_dir(dir.path().join("app").join(".git")).unwrap();
+    fs::write(dir.path().join("app").join("Cargo.toml"), "").unwrap();
+    fs::write(dir.path().join("libs/core").join("Cargo.toml"), "").unwrap();
+
+    let roots = rfind_in(
+        &dir,
+        &["-d", ".", "--strip-cwd-prefix", "--has-file", "Cargo.toml"],
+    );
+    let mut roots: Vec<&str> = roots.lines().collect();
+    roots.sort();
+    assert_eq!(roots, ["app", "libs/core"]);
+
+    assert_eq!(
+        rfind_in(
+            &dir,
+            &[
+                "-d",
+                ".",
+                "--strip-cwd-prefix",
+                "--has-file",
+                "Cargo.toml",
+                "--has-file",
+                ".git"
+            ]
+        ),
+        "app\n"
+    );
+}
+
+#[test]
+fn test_no_dev_clutter() {
+    let dir = TempDir::new().unwrap();
+    for sub in ["src", "node_modules/left-pad", "target/debug", "vendor"] {
+        fs::create_dir_all(dir.path().join(sub)).unwrap();
+        fs::write(dir.path().join(sub).join("index.js"), "").unwrap();
+    }
+    let config = dir.path().join("config.toml");
+    let search = |config: &std::path::Path| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rfind"))
+            .env("RFIND_CONFIG", config)
+            .args([
+                "index.js",
+                "-d",
+                ".",
+                "--strip-cwd-prefix",
+                "--no-dev-clutter",
+            ])
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let mut found: Vec<String> = String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect();
+        found.sort();
+        found
+    };
+
+    assert_eq!(search(&config), ["src/index.js", "vendor/index.js"]);
+
+    fs::write(&config, "dev_clutter = [\"vendor\"]\n").unwrap();
+    assert_eq!(
+        search(&config),
+        [
+            "node_modules/left-pad/index.js",
+            "src/index.js",
+            "target/debug/index.js"
+        ]
+    );
+}
+
+#[test]
+fn test_extension_presets() {
+    let dir = TempDir::new().unwrap();
+    for file in ["beach.JPG", "beach.cr3", "beach.mp4", "notes.org"] {
+        fs::write(dir.path().join(file), "").unwrap();
+    }
+    let config = dir.path().join("config.toml");
+    let search = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rfind"))
+            .env("RFIND_CONFIG", &config)
+            .args(["-d", ".", "--strip-cwd-prefix"])
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let mut found: Vec<String> = String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect();
+        found.sort();
+        found
+    };
+
+    assert_eq!(
+        search(&["beach", "--preset", "images"]),
+        ["beach.JPG", "beach.cr3"]
+    );
+
+    fs::write(
+        &config,
+        "[presets]\nimages = [\"jpg\"]\nnotes = [\"org\"]\n",
+    )
+    .unwrap();
+    assert_eq!(
+        search(&["--preset", "images", "--preset", "notes"]),
+        ["beach.JPG", "notes.org"]
+    );
+}
+
+#[test]
+fn test_largest_matches() {
+    let dir = TempDir::new().unwrap();
+    for (name, size) in [("small.bin", 10), ("big.bin", 3000), ("medium.bin", 500)] {
+        fs::write(dir.path().join(name), vec![0u8; size]).unwrap();
+    }
+
+    assert_eq!(
+        rfind_in(
+            &dir,
+            &["*.bin", "-d", ".", "--strip-cwd-prefix", "--largest", "2"]
+        ),
+        "2.9K\tbig.bin\n500\tmedium.bin\n"
+    );
+
+    // With -L a link is ranked by its target, like the filters see it
+    #[cfg(unix)]
+    {
+        fs::create_dir(dir.path().join("links")).unwrap();
+        std::os::unix::fs::symlink("../big.bin", dir.path().join("links/link.bin")).unwrap();
+        let args = [
+            "*.bin",
+            "-d",
+            "links",
+            "--strip-cwd-prefix",
+            "--largest",
+            "1",
+            "--size-format",
+            "bytes",
+        ];
+        assert!(!rfind_in(&dir, &args).starts_with("3000\t"));
+        assert_eq!(
+            rfind_in(&dir, &[&args[..], &["-L"]].concat()),
+            "3000\tlinks/link.bin\n"
+        );
+    }
+}
+
+#[test]
+fn test_newest_and_oldest_matches() {
+    let dir = TempDir::new().unwrap();
+    let day = std::time::Duration::from_secs(24 * 60 * 60);
+    let now = std::time::SystemTime::now();
+    for (name, age) in [("a.log", 3), ("b.log", 1), ("c.log", 10), ("d.log", 5)] {
+        let file = fs::File::create(dir.path().join(name)).unwrap();
+        file.set_modified(now - day * age).unwrap();
+    }
+    let ranked = |args: &[&str]| -> Vec<String> {
+        rfind_in(&dir, args)
+            .lines()
+            .map(|line| line.split('\t').nth(1).unwrap().to_string())
+            .collect()
+    };
+
+    assert_eq!(
+        ranked(&["*.log", "-d", ".", "--strip-cwd-prefix", "--newest", "2"]),
+        ["b.log", "a.log"]
+    );
+    assert_eq!(
+        ranked(&["*.log", "-d", ".", "--strip-cwd-prefix", "--oldest", "3"]),
+        ["c.log", "d.log", "a.log"]
+    );
+    assert_eq!(
+        rfind_in(
+            &dir,
+            &[
+                "c.log",
+                "-d",
+                ".",
+                "--oldest",
+                "1",
+                "--time-style",
+                "relative"
+            ]
+        )
+        .split('\t')
+        .next(),
+        Some("1 week ago")
+    );
+}
+
+#[test]
+fn test_summarize_extensions() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("sub.d")).unwrap();
+    for (name, size) in [("a.RS", 100), ("b.rs", 50), ("c.md", 2000), ("Makefile", 1)] {
+        fs::write(dir.path().join(name), vec![b'x'; size]).unwrap();
+    }
+
+    let table = rfind_in(
+        &dir,
+        &["*", "-d", ".", "--summarize-ext", "--size-format", "bytes"],
+    );
+    let rows: Vec<Vec<&str>> = table
+        .lines()
+        .map(|line| line.split_whitespace().collect())
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            vec!["EXTENSION", "COUNT", "SIZE"],
+            vec!["md", "1", "2000"],
+            vec!["rs", "2", "150"],
+            vec!["(none)", "1", "1"],
+        ]
+    );
+}
+
+#[test]
+fn test_first_match_only() {
+    let dir = TempDir::new().unwrap();
+    for sub in ["a", "b", "c"] {
+        fs::create_dir(dir.path().join(sub)).unwrap();
+        for i in 0..20 {
+            fs::write(dir.path().join(sub).join(format!("{}.log", i)), "").unwrap();
+        }
+    }
+
+    for flag in ["-1", "--first"] {
+        let output = rfind_in(&dir, &["*.log", "-d", ".", flag]);
+        assert_eq!(output.lines().count(), 1);
+        assert!(output.trim_end().ends_with(".log"));
+    }
+    assert_eq!(rfind_in(&dir, &["*.txt", "-d", ".", "-1"]), "");
+}
+
+#[test]
+fn test_structured_output_fields() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a,b.txt"), "hello").unwrap();
+
+    let csv = rfind_in(
+        &dir,
+        &[
+            "*.txt",
+            "-d",
+            ".",
+            "--strip-cwd-prefix",
+            "--csv",
+            "--fields",
+            "name,size,type",
+        ],
+    );
+    assert_eq!(csv, "name,size,type\n\"a,b.txt\",5,file\n");
+
+    let json = rfind_in(
+        &dir,
+        &[
+            "*.txt",
+            "-d",
+            ".",
+            "--strip-cwd-prefix",
+            "--json",
+            "--fields",
+            "path,ext",
+        ],
+    );
+    let object: serde_json::Value = serde_json::from_str(json.trim_end()).unwrap();
+    assert_eq!(
+        object,
+        serde_json::json!({ "path": "a,b.txt", "ext": "txt" })
+    );
+
+    // Sizes and times are plain numbers unless a format is asked for
+    fs::write(dir.path().join("big.txt"), vec![b'x'; 2048]).unwrap();
+    let csv = rfind_in(
+        &dir,
+        &["big.txt", "-d", ".", "--csv", "--fields", "name,size"],
+    );
+    assert_eq!(csv, "name,size\nbig.txt,2048\n");
+    let csv = rfind_in(
+        &dir,
+        &[
+            "big.txt",
+            "-d",
+            ".",
+            "--csv",
+            "--fields",
+            "name,size",
+            "--size-format",
+            "human",
+        ],
+    );
+    assert_eq!(csv, "name,size\nbig.txt,2.0K\n");
+    let json = rfind_in(
+        &dir,
+        &["big.txt", "-d", ".", "--json", "--fields", "size,mtime"],
+    );
+    let object: serde_json::Value = serde_json::from_str(json.trim_end()).unwrap();
+    assert_eq!(object["size"], 2048);
+    assert!(object["mtime"].is_u64());
+    let json = rfind_in(
+        &dir,
+        &[
+            "big.txt",
+            "-d",
+            ".",
+            "--json",
+            "--fields",
+            "size,mtime",
+            "--size-format",
+            "si",
+            "--time-style",
+            "iso",
+        ],
+    );
+    let object: serde_json::Value = serde_json::from_str(json.trim_end()).unwrap();
+    assert_eq!(object["size"], "2.0k");
+    assert!(object["mtime"].is_string());
+    fs::remove_file(dir.path().join("big.txt")).unwrap();
+
+    // Without --fields, CSV has just the path
+    let csv = rfind_in(&dir, &["*.txt", "-d", ".", "--strip-cwd-prefix", "--csv"]);
+    assert_eq!(csv, "path\n\"a,b.txt\"\n");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(
+            dir.path().join("a,b.txt"),
+            fs::Permissions::from_mode(0o640),
+        )
+        .unwrap();
+        let csv = rfind_in(&dir, &["*.txt", "-d", ".", "--csv", "--fields", "perm"]);
+        assert_eq!(csv, "perm\n0640\n");
+    }
+}
+
+#[test]
+fn test_base_directory() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("sub").join("inner")).unwrap();
+    fs::write(dir.path().join("sub").join("inner").join("a.txt"), "").unwrap();
+    fs::write(dir.path().join("a.txt"), "").unwrap();
+
+    assert_eq!(
+        rfind_in(
+            &dir,
+            &[
+                "a.txt",
+                "-d",
+                ".",
+                "--base-directory",
+                "sub",
+                "--strip-cwd-prefix"
+            ]
+        ),
+        "inner/a.txt\n"
+    );
+    assert_eq!(
+        rfind_in(
+            &dir,
+            &[
+                "a.txt",
+                "-d",
+                "inner",
+                "--base-directory",
+                "sub",
+                "--exec",
+                "sh",
+                "-c",
+                "pwd"
+            ]
+        ),
+        // Commands run from the base directory too
+        format!(
+            "{}\n",
+            dir.path().canonicalize().unwrap().join("sub").display()
+        )
+    );
+}
+
+#[test]
+fn test_patterns_from_file() {
+    let dir = TempDir::new().unwrap();
+    for name in ["id_rsa", "server.pem", ".env", "notes.txt", "main.rs"] {
+        fs::write(dir.path().join(name), "").unwrap();
+    }
+    fs::write(
+        dir.path().join("secrets.list"),
+        "# private keys\nid_rsa\n*.pem\n\n  ^.env$  \n",
+    )
+    .unwrap();
+
+    let sorted = |output: String| {
+        let mut lines: Vec<String> = output.lines().map(String::from).collect();
+        lines.sort();
+        lines
+    };
+    let args = [
+        "-d",
+        ".",
+        "--strip-cwd-prefix",
+        "--patterns-from",
+        "secrets.list",
+    ];
+    assert_eq!(
+        sorted(rfind_in(&dir, &args)),
+        [".env", "id_rsa", "server.pem"]
+    );
+    // The pattern on the command line is ORed with the ones in the file
+    assert_eq!(
+        sorted(rfind_in(&dir, &[&["*.rs"], &args[..]].concat())),
+        [".env", "id_rsa", "main.rs", "server.pem"]
+    );
+
+    // Lines are checked in the syntax the search uses: this is no glob
+    fs::write(dir.path().join("regex.list"), "^id_\\w**$\n\\.pem$\n").unwrap();
+    let regex_args = [
+        "-d",
+        ".",
+        "--strip-cwd-prefix",
+        "--regex",
+        "--patterns-from",
+        "regex.list",
+    ];
+    assert_eq!(
+        sorted(rfind_in(&dir, &regex_args)),
+        ["id_rsa", "server.pem"]
+    );
+    fs::write(dir.path().join("regex.list"), "\\.pem$\n(id_rsa\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rfind"))
+        .args(regex_args)
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("line 2: invalid regular expression '(id_rsa'"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_invalid_pattern() {
+    let dir = TempDir::new().unwrap();
+    for (args, message) in [
+        (&["[*"][..], "invalid glob '[*'"),
+        (&["--regex", "(x"][..], "invalid regular expression '(x'"),
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_rfind"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(message), "{}", stderr);
+    }
+}
+
+#[test]
+fn test_exclude_regex() {
+    let dir = TempDir::new().unwrap();
+    for sub in ["lib/v1.2/src", "lib/v1.10", "lib/current"] {
+        fs::create_dir_all(dir.path().join(sub)).unwrap();
+    }
+    for file in [
+        "lib/v1.2/src/a.rs",
+        "lib/current/a.rs",
+        "lib/current/a.rs.bak",
+        "lib/v1.10/a.rs",
+    ] {
+        fs::write(dir.path().join(file), "").unwrap();
+    }
+
+    let mut found: Vec<String> = rfind_in(
+        &dir,
+        &[
+            "a.rs",
+            "-d",
+            ".",
+            "--strip-cwd-prefix",
+            "--exclude-regex",
+            r"(^|/)v[0-9]+\.[0-9]+(/|$)",
+            "--exclude-regex",
+            r"\.bak$",
+        ],
+    )
+    .lines()
+    .map(String::from)
+    .collect();
+    found.sort();
+    assert_eq!(found, ["lib/current/a.rs"]);
+}
+
+#[test]
+fn test_show_matching_pattern() {
+    let dir = TempDir::new().unwrap();
+    for name in ["server.key", "server.pem", "notes.txt"] {
+        fs::write(dir.path().join(name), "").unwrap();
+    }
+    fs::write(dir.path().join("sweep.list"), "*.key\n*.pem\n").unwrap();
+
+    let args = [
+        "-d",
+        ".",
+        "--strip-cwd-prefix",
+        "--patterns-from",
+        "sweep.list",
+        "--show-pattern",
+    ];
+    let mut lines: Vec<String> = rfind_in(&dir, &args).lines().map(String::from).collect();
+    lines.sort();
+    assert_eq!(lines, ["*.key\tserver.key", "*.pem\tserver.pem"]);
+
+    let mut records: Vec<serde_json::Value> = rfind_in(&dir, &[&args[..], &["--json"]].concat())
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    records.sort_by_key(|record| record["path"].to_string());
+    assert_eq!(
+        records,
+        [
+            serde_json::json!({ "path": "server.key", "pattern": "*.key" }),
+            serde_json::json!({ "path": "server.pem", "pattern": "*.pem" }),
+        ]
+    );
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+fn test_icloud_stub_filters_read_the_stub() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join(".Report.pdf.icloud"), "bplist").unwrap();
+
+    // Reported as the file it stands for, but checked where it is on disk
+    assert_eq!(
+        rfind_in(
+            &dir,
+            &[
+                "*.pdf",
+                "-d",
+                ".",
+                "--strip-cwd-prefix",
+                "--filter-cmd",
+                "test -s {}"
+            ]
+        ),
+        "Report.pdf\n"
+    );
+}
@@ -0,0 +1,120 @@
+#![cfg(unix)]
+
+use rfind::permissions::{self, PermissionIssue};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn set_mode(path: &std::path::Path, mode: u32) {
+    fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+}
+
+#[test]
+fn test_permission_issues() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("tool");
+    fs::write(&file, "").unwrap();
+    let shared = dir.path().join("shared");
+    fs::create_dir(&shared).unwrap();
+
+    set_mode(&file, 0o644);
+    let metadata = fs::symlink_metadata(&file).unwrap();
+    assert!(!permissions::has_special_mode(&metadata));
+    assert_eq!(permissions::issues(&metadata), []);
+
+    set_mode(&file, 0o4757);
+    let metadata = fs::symlink_metadata(&file).unwrap();
+    assert!(permissions::has_special_mode(&metadata));
+    assert_eq!(
+        permissions::issues(&metadata),
+        [PermissionIssue::WorldWritable, PermissionIssue::Setuid]
+    );
+
+    // World-writable directories are fine with the sticky bit, like /tmp
+    set_mode(&shared, 0o1777);
+    assert_eq!(
+        permissions::issues(&fs::symlink_metadata(&shared).unwrap()),
+        []
+    );
+    set_mode(&shared, 0o777);
+    assert_eq!(
+        permissions::issues(&fs::symlink_metadata(&shared).unwrap()),
+        [PermissionIssue::WorldWritable]
+    );
+}
+
+#[test]
+fn test_audit_perms_report() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    fs::write(root.join("ok.txt"), "").unwrap();
+    fs::write(root.join("open.txt"), "").unwrap();
+    set_mode(&root.join("open.txt"), 0o666);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rfind"))
+        .args(["audit", "perms"])
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!("world-writable (1)\n  {}\n", root.join("open.txt").display())
+    );
+}
+
+#[test]
+fn test_security_preset_labels() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    fs::write(root.join("ok"), "").unwrap();
+    fs::write(root.join("tool"), "").unwrap();
+    set_mode(&root.join("tool"), 0o4757);
+
+    let search = |extra: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rfind"))
+            .args(["--preset", "security", "-d"])
+            .arg(&root)
+            .args(extra)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let tool = root.join("tool");
+    assert_eq!(
+        search(&[]),
+        format!("high\tworld-writable,setuid\t{}\n", tool.display())
+    );
+    let object: serde_json::Value = serde_json::from_str(search(&["--json"]).trim()).unwrap();
+    assert_eq!(
+        object,
+        serde_json::json!({
+            "path": tool.to_string_lossy(),
+            "severity": "high",
+            "rules": ["world-writable", "setuid"],
+        })
+    );
+
+    // Structured output keeps the labels
+    assert_eq!(
+        search(&["--csv"]),
+        format!(
+            "path,severity,rules\n{},high,\"world-writable,setuid\"\n",
+            tool.display()
+        )
+    );
+    let object: serde_json::Value =
+        serde_json::from_str(search(&["--json", "--fields", "name,perm"]).trim()).unwrap();
+    assert_eq!(
+        object,
+        serde_json::json!({
+            "name": "tool",
+            "perm": "4757",
+            "severity": "high",
+            "rules": ["world-writable", "setuid"],
+        })
+    );
+}
@@ -0,0 +1,210 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+/// Source of a plugin keeping non-empty `.txt` files, written against the C
+/// ABI documented in src/plugin.rs
+const KEEP_NONEMPTY_TXT: &str = r#"
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+#[repr(C)]
+pub struct RfindEntry {
+    path: *const c_char,
+    size: u64,
+    kind: u32,
+    mtime: i64,
+}
+
+#[no_mangle]
+pub extern "C" fn rfind_plugin_abi_version() -> u32 {
+    VERSION
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rfind_filter(entry: *const RfindEntry) -> i32 {
+    let entry = &*entry;
+    let path = CStr::from_ptr(entry.path).to_bytes();
+    (entry.kind == 1 && entry.size > 0 && entry.mtime > 0 && path.ends_with(b".txt")) as i32
+}
+"#;
+
+/// Build `source` into a shared library in `dir`
+fn build_plugin(dir: &Path, name: &str, source: &str) -> PathBuf {
+    let source_path = dir.join(format!("{}.rs", name));
+    fs::write(&source_path, source).unwrap();
+    let library = dir.join(format!(
+        "{}{}{}",
+        std::env::consts::DLL_PREFIX,
+        name,
+        std::env::consts::DLL_SUFFIX
+    ));
+    let status = Command::new(std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into()))
+        .args(["--crate-type", "cdylib", "--edition", "2018", "-o"])
+        .arg(&library)
+        .arg(&source_path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    library
+}
+
+fn rfind_with_plugin(dir: &TempDir, plugin: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rfind"))
+        .args(["*", "-d", "tree", "--plugin"])
+        .arg(plugin)
+        .current_dir(dir.path())
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_native_plugin() {
+    let dir = TempDir::new().unwrap();
+    let tree = dir.path().join("tree");
+    fs::create_dir_all(tree.join("dir.txt")).unwrap();
+    fs::write(tree.join("a.txt"), "keep").unwrap();
+    fs::write(tree.join("empty.txt"), "").unwrap();
+    fs::write(tree.join("b.md"), "skip").unwrap();
+
+    // Entries are accepted or rejected by the plugin
+    let plugin = build_plugin(
+        dir.path(),
+        "keep_txt",
+        &format!("const VERSION: u32 = 1;\n{}", KEEP_NONEMPTY_TXT),
+    );
+    let output = rfind_with_plugin(&dir, &plugin);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let matches: Vec<&str> = stdout.lines().collect();
+    assert_eq!(matches.len(), 1);
+    assert!(matches[0].ends_with("tree/a.txt"));
+
+    // A plugin built for another ABI version is refused
+    let plugin = build_plugin(
+        dir.path(),
+        "future_abi",
+        &format!("const VERSION: u32 = 2;\n{}", KEEP_NONEMPTY_TXT),
+    );
+    let output = rfind_with_plugin(&dir, &plugin);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("uses ABI version 2, expected 1"),
+        "{}",
+        stderr
+    );
+
+    // So is a library that is not a plugin
+    let plugin = build_plugin(
+        dir.path(),
+        "no_filter",
+        "#[no_mangle]\npub extern \"C\" fn rfind_plugin_abi_version() -> u32 { 1 }\n",
+    );
+    let output = rfind_with_plugin(&dir, &plugin);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("has no rfind_filter"), "{}", stderr);
+
+    let output = rfind_with_plugin(&dir, &dir.path().join("missing.so"));
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Failed to load plugin"), "{}", stderr);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_plugin_gets_raw_paths() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = TempDir::new().unwrap();
+    let tree = dir.path().join("tree");
+    let latin1 = tree.join(OsStr::from_bytes(b"caf\xe9"));
+    fs::create_dir_all(&latin1).unwrap();
+    fs::write(latin1.join("a.txt"), "keep").unwrap();
+    fs::write(tree.join("b.txt"), "skip").unwrap();
+
+    // Keep paths with a byte that is not valid UTF-8 on its own
+    let plugin = build_plugin(
+        dir.path(),
+        "keep_latin1",
+        &format!(
+            "const VERSION: u32 = 1;\n{}",
+            KEEP_NONEMPTY_TXT.replace(
+                "path.ends_with(b\".txt\")",
+                "path.ends_with(b\".txt\") && path.contains(&0xe9)"
+            )
+        ),
+    );
+    let output = rfind_with_plugin(&dir, &plugin);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let matches: Vec<&str> = stdout.lines().collect();
+    assert_eq!(matches.len(), 1);
+    assert!(matches[0].ends_with("/a.txt"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_plugin_manifest() {
+    let dir = TempDir::new().unwrap();
+    let tree = dir.path().join("tree");
+    fs::create_dir(&tree).unwrap();
+    fs::write(tree.join("a.txt"), "keep").unwrap();
+    fs::write(tree.join("b.txt"), "keep").unwrap();
+    fs::write(tree.join("c.md"), "skip").unwrap();
+    fs::write(tree.join("empty.txt"), "").unwrap();
+
+    // A library relative to the manifest, a command filter rejecting b.txt,
+    // and an action marking what is left
+    build_plugin(
+        dir.path(),
+        "keep_txt",
+        &format!("const VERSION: u32 = 1;\n{}", KEEP_NONEMPTY_TXT),
+    );
+    let manifest = dir.path().join("checks.toml");
+    fs::write(
+        &manifest,
+        format!(
+            r#"
+[[filter]]
+library = "{}keep_txt{}"
+
+[[filter]]
+command = ["sh", "-c", "case $1 in *b.txt) exit 1;; esac", "sh"]
+
+[[action]]
+command = ["touch", "{{}}.seen"]
+"#,
+            std::env::consts::DLL_PREFIX,
+            std::env::consts::DLL_SUFFIX
+        ),
+    )
+    .unwrap();
+    let output = rfind_with_plugin(&dir, &manifest);
+    assert!(output.status.success());
+    assert!(tree.join("a.txt.seen").exists());
+    assert!(!tree.join("b.txt.seen").exists());
+    assert!(!tree.join("c.md.seen").exists());
+    assert!(!tree.join("empty.txt.seen").exists());
+
+    // Mistakes in the manifest are reported before searching
+    fs::write(&manifest, "[[filter]]\n").unwrap();
+    let output = rfind_with_plugin(&dir, &manifest);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("each filter needs either a command or a library"),
+        "{}",
+        stderr
+    );
+
+    fs::write(&manifest, "[[filters]]\ncommand = [\"true\"]\n").unwrap();
+    let output = rfind_with_plugin(&dir, &manifest);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Invalid plugin manifest"), "{}", stderr);
+}
@@ -0,0 +1,66 @@
+use rfind::format::FieldFormat;
+use rfind::query::{Column, Query};
+use rfind::Search;
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_query_parsing() {
+    let query = Query::parse(
+        "SELECT path, size FROM /tmp WHERE ext = 'rs' AND (size > 10k OR name LIKE 'a%') \
+         ORDER BY size DESC, name LIMIT 20",
+    )
+    .unwrap();
+    assert_eq!(query.columns, vec![Column::Path, Column::Size]);
+    assert_eq!(query.from.to_str(), Some("/tmp"));
+    assert_eq!(
+        query.filter.as_deref(),
+        Some(r#"((ext == "rs") && ((size > 10240) || like(name, "a%")))"#)
+    );
+    assert_eq!(
+        query.order_by,
+        vec![(Column::Size, true), (Column::Name, false)]
+    );
+    assert_eq!(query.limit, Some(20));
+
+    let query = Query::parse("select * from '/my dir' where mtime > now() - 7d").unwrap();
+    assert_eq!(query.columns.len(), 4);
+    assert_eq!(query.from.to_str(), Some("/my dir"));
+    assert_eq!(query.filter.as_deref(), Some("(mtime > (now - 604800))"));
+
+    // Size units are those of --size
+    let query = Query::parse("SELECT path FROM / WHERE size > 1MB AND size < 2MiB").unwrap();
+    assert_eq!(
+        query.filter.as_deref(),
+        Some("((size > 1000000) && (size < 2097152))")
+    );
+
+    assert!(Query::parse("SELECT owner FROM /").is_err());
+    assert!(Query::parse("SELECT path FROM / WHERE colour = 'red'").is_err());
+    assert!(Query::parse("SELECT path FROM / LIMIT ten").is_err());
+    assert!(Query::parse("SELECT path FROM / WHERE size > 1Q").is_err());
+}
+
+#[test]
+fn test_query_execution() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path();
+    fs::write(base_path.join("small.rs"), "a")?;
+    fs::write(base_path.join("large.rs"), "a".repeat(100))?;
+    fs::write(base_path.join("medium.rs"), "a".repeat(10))?;
+    fs::write(base_path.join("notes.txt"), "a".repeat(1000))?;
+
+    let sql = format!(
+        "SELECT name, size FROM '{}' WHERE ext = 'rs' ORDER BY size DESC LIMIT 2",
+        base_path.display()
+    );
+    let query = Query::parse(&sql)?;
+    let rows = query.rows(Search::start(query.search_options()?)?);
+    let lines: Vec<String> = rows
+        .iter()
+        .map(|row| query.format_row(row, &FieldFormat::default()))
+        .collect();
+    assert_eq!(lines, vec!["large.rs\t100", "medium.rs\t10"]);
+
+    Ok(())
+}
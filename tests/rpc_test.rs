@@ -0,0 +1,113 @@
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{ChildStdout, Command, Stdio};
+use tempfile::TempDir;
+
+fn write_message(stdin: &mut impl Write, message: &Value) -> std::io::Result<()> {
+    let body = message.to_string();
+    write!(stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    stdin.flush()
+}
+
+fn read_message(stdout: &mut BufReader<ChildStdout>) -> Result<Value, Box<dyn std::error::Error>> {
+    let mut length = 0;
+    loop {
+        let mut line = String::new();
+        stdout.read_line(&mut line)?;
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse()?;
+        }
+    }
+    let mut body = vec![0; length];
+    stdout.read_exact(&mut body)?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+#[test]
+fn test_lsp_like_search() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path();
+    fs::write(base_path.join("a.rs"), "a")?;
+    fs::write(base_path.join("b.rs"), "b")?;
+    fs::write(base_path.join("c.txt"), "c")?;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rfind"))
+        .arg("--lsp-like")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+
+    write_message(
+        &mut stdin,
+        &json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "search",
+            "params": { "pattern": "*.rs", "dir": base_path, "type": "f" },
+        }),
+    )?;
+
+    let mut paths = Vec::new();
+    let response = loop {
+        let message = read_message(&mut stdout)?;
+        if message["method"] == "search/result" {
+            assert_eq!(message["params"]["id"], 1);
+            paths.push(message["params"]["path"].as_str().unwrap().to_string());
+        } else {
+            break message;
+        }
+    };
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["result"]["count"], 2);
+    assert_eq!(response["result"]["cancelled"], false);
+    paths.sort();
+    assert!(paths[0].ends_with("a.rs") && paths[1].ends_with("b.rs"));
+
+    // Cancelling a search that is no longer running is reported as such
+    write_message(
+        &mut stdin,
+        &json!({ "jsonrpc": "2.0", "id": 2, "method": "cancel", "params": { "id": 1 } }),
+    )?;
+    let response = read_message(&mut stdout)?;
+    assert_eq!(response["id"], 2);
+    assert_eq!(response["result"]["cancelled"], false);
+
+    write_message(
+        &mut stdin,
+        &json!({ "jsonrpc": "2.0", "id": 3, "method": "bogus" }),
+    )?;
+    assert_eq!(read_message(&mut stdout)?["error"]["code"], -32601);
+
+    // A bad pattern is refused, and the session carries on
+    write_message(
+        &mut stdin,
+        &json!({
+            "jsonrpc": "2.0",
+            "id": 4,
+            "method": "search",
+            "params": { "pattern": "[*", "dir": base_path },
+        }),
+    )?;
+    let response = read_message(&mut stdout)?;
+    assert_eq!(response["id"], 4);
+    assert_eq!(response["error"]["code"], -32602);
+    let message = response["error"]["message"].as_str().unwrap();
+    assert!(message.starts_with("invalid glob '[*'"), "{}", message);
+
+    write_message(
+        &mut stdin,
+        &json!({ "jsonrpc": "2.0", "id": 5, "method": "cancel", "params": { "id": 4 } }),
+    )?;
+    assert_eq!(read_message(&mut stdout)?["id"], 5);
+
+    write_message(&mut stdin, &json!({ "jsonrpc": "2.0", "method": "exit" }))?;
+    assert!(child.wait()?.success());
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+/// rfind with its home (and so its saved searches) inside `home`
+fn rfind(home: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_rfind"));
+    command.env("HOME", home);
+    command
+}
+
+#[test]
+fn test_save_and_run_search() {
+    let home = TempDir::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.mp4"), "").unwrap();
+    fs::write(dir.path().join("b.txt"), "").unwrap();
+
+    let status = rfind(home.path())
+        .args(["save-search", "videos", "--", "*.mp4", "-d"])
+        .arg(dir.path())
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let output = rfind(home.path()).args(["run", "videos"]).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("a.mp4"));
+    assert!(!stdout.contains("b.txt"));
+
+    // Invalid arguments are rejected up front, unknown names at run time
+    let status = rfind(home.path())
+        .args(["save-search", "broken", "--", "*.mp4", "--no-such-flag"])
+        .status()
+        .unwrap();
+    assert!(!status.success());
+    let status = rfind(home.path()).args(["run", "broken"]).status().unwrap();
+    assert!(!status.success());
+}
@@ -0,0 +1,488 @@
+use parking_lot::Mutex;
+use rfind::{ScanError, ScanOperation, Search, SearchOptions};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+#[test]
+fn test_scan_errors_are_reported() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let missing = temp_dir.path().join("missing");
+
+    let errors: Arc<Mutex<Vec<ScanError>>> = Arc::default();
+    let mut options = SearchOptions::new("*", missing.clone());
+    options.on_error = Some({
+        let errors = Arc::clone(&errors);
+        Arc::new(move |error: &ScanError| errors.lock().push(error.clone()))
+    });
+
+    assert_eq!(Search::start(options)?.count(), 0);
+
+    let errors = errors.lock();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].path, missing);
+    assert_eq!(errors[0].operation, ScanOperation::ReadDir);
+    assert_eq!(errors[0].kind, Some(std::io::ErrorKind::NotFound));
+    // Only transient errors are tried again
+    assert_eq!(errors[0].retries, 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_search_archives() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path().canonicalize()?;
+
+    let archive_path = base_path.join("bundle.tar.gz");
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+        fs::File::create(&archive_path)?,
+        flate2::Compression::default(),
+    ));
+    for (name, contents) in [("docs/readme.md", "a"), ("src/main.rs", "b")] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, name, contents.as_bytes())?;
+    }
+    builder.into_inner()?.finish()?;
+
+    let mut options = SearchOptions::new("*.md", base_path.clone());
+    assert_eq!(Search::start(options)?.count(), 0);
+
+    options = SearchOptions::new("*.md", base_path.clone());
+    options.search_archives = true;
+    let results: Vec<PathBuf> = Search::start(options)?.collect();
+    assert_eq!(
+        results,
+        vec![PathBuf::from(format!(
+            "{}!/docs/readme.md",
+            archive_path.display()
+        ))]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_anchored_substring_patterns() {
+    use rfind::scanner::create_pattern_matcher;
+
+    let exact = create_pattern_matcher("^Makefile$");
+    assert!(exact.matches("Makefile"));
+    assert!(exact.matches("makefile"));
+    assert!(!exact.matches("Makefile.am"));
+    assert!(!exact.matches("GNUmakefile"));
+
+    assert!(create_pattern_matcher("^make").matches("Makefile.am"));
+    assert!(!create_pattern_matcher("^make").matches("GNUmakefile"));
+    assert!(create_pattern_matcher("file$").matches("GNUmakefile"));
+    assert!(!create_pattern_matcher("file$").matches("Makefile.am"));
+    assert!(create_pattern_matcher("akefi").matches("Makefile.am"));
+}
+
+#[test]
+fn test_negated_patterns() {
+    use rfind::scanner::create_pattern_matcher;
+
+    let not_minified = create_pattern_matcher("!*.min.js");
+    assert!(not_minified.matches("app.js"));
+    assert!(!not_minified.matches("app.min.js"));
+
+    let not_tests = create_pattern_matcher("!^test_");
+    assert!(not_tests.matches("main.rs"));
+    assert!(!not_tests.matches("test_main.rs"));
+}
+
+#[test]
+fn test_search_with_stat_timeout() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path().canonicalize()?;
+    for dir in 0..10 {
+        let dir = base_path.join(format!("d{}", dir));
+        fs::create_dir(&dir)?;
+        for file in 0..20 {
+            fs::write(dir.join(format!("{}.txt", file)), "")?;
+        }
+    }
+
+    // Every call goes through the guard, and none of them comes close to
+    // timing out
+    let mut options = SearchOptions::new("*.txt", &base_path);
+    options.threads = 4;
+    options.stat_timeout = Some(std::time::Duration::from_secs(5));
+    let mut search = Search::start(options)?;
+    assert_eq!(search.by_ref().count(), 200);
+    assert!(search.timed_out_paths().is_empty());
+    search.join();
+
+    Ok(())
+}
+
+#[test]
+fn test_regex_patterns() -> Result<(), Box<dyn std::error::Error>> {
+    use rfind::PatternSyntax;
+
+    let dated = PatternSyntax::Regex.matcher(r"^\d{4}-\d{2}\.log$")?;
+    assert!(dated.matches("2024-03.log"));
+    assert!(!dated.matches("2024-03.log.gz"));
+    assert!(!PatternSyntax::Regex.matcher("readme")?.matches("README.md"));
+    assert!(PatternSyntax::RegexIgnoreCase
+        .matcher("readme")?
+        .matches("README.md"));
+    assert!(PatternSyntax::Regex.matcher("(unclosed").is_err());
+
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path().canonicalize()?;
+    for file in ["a1.txt", "b22.txt", "c.txt"] {
+        fs::write(base_path.join(file), "")?;
+    }
+    let mut options = SearchOptions::new(r"\d+\.txt$", &base_path);
+    options.pattern_syntax = PatternSyntax::Regex;
+    let mut found: Vec<PathBuf> = Search::start(options)?.collect();
+    found.sort();
+    assert_eq!(
+        found,
+        vec![base_path.join("a1.txt"), base_path.join("b22.txt")]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_invalid_patterns_are_reported() {
+    use rfind::PatternSyntax;
+
+    let error = Search::start(SearchOptions::new("[*", ".")).err().unwrap();
+    assert_eq!(error.pattern, "[*");
+    assert!(error.to_string().starts_with("invalid glob '[*': "));
+    assert!(PatternSyntax::Glob.validate("!*[").is_err());
+    // Without * or ? the pattern is a substring, whatever it contains
+    assert!(PatternSyntax::Glob.validate("[").is_ok());
+
+    let mut options = SearchOptions::new("*.txt", ".");
+    options.extra_patterns = vec!["*.md".to_string(), "a[*".to_string()];
+    let error = Search::start(options).err().unwrap();
+    assert_eq!(error.pattern, "a[*");
+
+    let mut options = SearchOptions::new("(unclosed", ".");
+    options.pattern_syntax = PatternSyntax::Regex;
+    let error = Search::start_with_errors(options).err().unwrap();
+    assert!(error
+        .to_string()
+        .starts_with("invalid regular expression '(unclosed': "));
+}
+
+#[test]
+fn test_single_thread_walks_in_order() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path().canonicalize()?;
+    fs::create_dir_all(base_path.join("b/d"))?;
+    fs::create_dir_all(base_path.join("a/c"))?;
+    for file in ["z.txt", "a/x.txt", "a/c/w.txt", "b/d/y.txt"] {
+        fs::write(base_path.join(file), "")?;
+    }
+
+    let mut options = SearchOptions::new("*", base_path.clone());
+    options.threads = 1;
+    let results: Vec<PathBuf> = Search::start(options)?.collect();
+
+    let expected: Vec<PathBuf> = [
+        "a",
+        "a/c",
+        "a/c/w.txt",
+        "a/x.txt",
+        "b",
+        "b/d",
+        "b/d/y.txt",
+        "z.txt",
+    ]
+    .iter()
+    .map(|path| base_path.join(path))
+    .collect();
+    assert_eq!(results, expected);
+
+    Ok(())
+}
+
+#[test]
+fn test_fanout_depth_finds_everything() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path().canonicalize()?;
+    let deep = base_path.join("a/b/c/d/e");
+    fs::create_dir_all(&deep)?;
+    fs::create_dir_all(base_path.join("x/y"))?;
+    fs::write(deep.join("deep.txt"), "")?;
+    fs::write(base_path.join("x/y/shallow.txt"), "")?;
+
+    let search = |fanout_depth| {
+        let mut options = SearchOptions::new("*", base_path.clone());
+        options.threads = 4;
+        options.fanout_depth = fanout_depth;
+        let mut results: Vec<PathBuf> = Search::start(options).unwrap().collect();
+        results.sort();
+        results
+    };
+
+    let all = search(None);
+    assert_eq!(all.len(), 9);
+    assert_eq!(search(Some(0)), all);
+    assert_eq!(search(Some(2)), all);
+
+    Ok(())
+}
+
+#[test]
+fn test_bounded_results_pace_the_scan() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path().canonicalize()?;
+    for dir in 0..4 {
+        fs::create_dir(base_path.join(format!("dir{}", dir)))?;
+        for file in 0..25 {
+            fs::write(base_path.join(format!("dir{}/file{}.txt", dir, file)), "")?;
+        }
+    }
+
+    let start = |result_buffer| {
+        let mut options = SearchOptions::new("*.txt", base_path.clone());
+        options.threads = 4;
+        options.result_buffer = result_buffer;
+        Search::start(options).unwrap()
+    };
+
+    // Nothing is lost while the scanners wait on a slow consumer
+    let mut search = start(Some(1));
+    let mut results = Vec::new();
+    for path in search.by_ref() {
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        results.push(path);
+    }
+    search.join();
+    assert_eq!(results.len(), 100);
+    assert_eq!(start(None).count(), 100);
+
+    // Scanners blocked on a full channel still exit when the search stops early
+    let mut search = start(Some(1));
+    assert!(search.next().is_some());
+    search.cancel_token().cancel();
+    search.join();
+
+    Ok(())
+}
+
+#[test]
+fn test_errors_are_yielded_with_matches() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path().canonicalize()?;
+    fs::create_dir(base_path.join("small"))?;
+    fs::create_dir(base_path.join("huge"))?;
+    fs::write(base_path.join("small/kept.tmp"), "")?;
+    for i in 0..5 {
+        fs::write(base_path.join(format!("huge/{}.tmp", i)), "")?;
+    }
+
+    let reported: Arc<Mutex<Vec<ScanError>>> = Arc::default();
+    let mut options = SearchOptions::new("*.tmp", base_path.clone());
+    options.max_dir_entries = Some(3);
+    options.on_error = Some({
+        let reported = Arc::clone(&reported);
+        Arc::new(move |error: &ScanError| reported.lock().push(error.clone()))
+    });
+    let (matches, errors): (Vec<_>, Vec<_>) =
+        Search::start_with_errors(options)?.partition(|item| item.is_ok());
+
+    assert_eq!(matches.len(), 1);
+    assert_eq!(errors.len(), 1);
+    let error = errors.into_iter().next().unwrap().unwrap_err();
+    assert_eq!(error.path, base_path.join("huge"));
+    assert_eq!(error.operation, ScanOperation::LargeDirectory);
+    // The handler from the options still sees every error
+    assert_eq!(reported.lock().len(), 1);
+
+    // A missing root is an error item rather than an empty result
+    let missing = SearchOptions::new("*", base_path.join("missing"));
+    let items: Vec<_> = Search::start_with_errors(missing)?.collect();
+    assert_eq!(items.len(), 1);
+    let error = items[0].as_ref().unwrap_err();
+    assert_eq!(error.kind, Some(std::io::ErrorKind::NotFound));
+
+    Ok(())
+}
+
+#[test]
+fn test_preferred_directories_come_first() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path().canonicalize()?;
+    for dir in ["a", "m/wanted", "z"] {
+        fs::create_dir_all(base_path.join(dir))?;
+        fs::write(base_path.join(dir).join("file.txt"), "")?;
+    }
+
+    let mut options = SearchOptions::new("file.txt", base_path.clone());
+    options.threads = 1;
+    options.prefer = vec![base_path.join("m/wanted")];
+    let results: Vec<PathBuf> = Search::start(options)?.collect();
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0], base_path.join("m/wanted/file.txt"));
+
+    Ok(())
+}
+
+#[test]
+fn test_large_directories_are_skipped() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path().canonicalize()?;
+    let cache = base_path.join("cache");
+    fs::create_dir(&cache)?;
+    for i in 0..5 {
+        fs::write(cache.join(format!("{}.tmp", i)), "")?;
+    }
+    fs::write(base_path.join("keep.tmp"), "")?;
+
+    let errors: Arc<Mutex<Vec<ScanError>>> = Arc::default();
+    let mut options = SearchOptions::new("*.tmp", base_path.clone());
+    options.max_dir_entries = Some(3);
+    options.on_error = Some({
+        let errors = Arc::clone(&errors);
+        Arc::new(move |error: &ScanError| errors.lock().push(error.clone()))
+    });
+    let results: Vec<PathBuf> = Search::start(options)?.collect();
+
+    assert_eq!(results, vec![base_path.join("keep.tmp")]);
+    let errors = errors.lock();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].path, cache);
+    assert_eq!(errors[0].operation, ScanOperation::LargeDirectory);
+
+    let mut options = SearchOptions::new("*.tmp", base_path.clone());
+    options.max_dir_entries = Some(3);
+    options.include_large = vec![cache];
+    assert_eq!(Search::start(options)?.count(), 6);
+
+    Ok(())
+}
+
+#[cfg(windows)]
+#[test]
+fn test_junctions_are_followed_like_symlinks() -> Result<(), Box<dyn std::error::Error>> {
+    use rfind::SymlinkMode;
+
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path().canonicalize()?;
+    fs::create_dir(base_path.join("real"))?;
+    fs::write(base_path.join("real").join("file.txt"), "")?;
+    // A junction back to the top must not loop forever under -L
+    for (link, target) in [("junction", "real"), ("loop", ".")] {
+        let status = std::process::Command::new("cmd")
+            .args(["/C", "mklink", "/J"])
+            .arg(base_path.join(link))
+            .arg(base_path.join(target))
+            .status()?;
+        assert!(status.success());
+    }
+
+    let search = |symlink_mode| {
+        let mut options = SearchOptions::new("file.txt", base_path.clone());
+        options.symlink_mode = symlink_mode;
+        Search::start(options)?.count()
+    };
+    assert_eq!(search(SymlinkMode::Never), 1);
+    assert!(search(SymlinkMode::Always) >= 2);
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_filters_test_link_targets_when_following() -> Result<(), Box<dyn std::error::Error>> {
+    use rfind::filters::{EntryFilter, SizeFilter, TypeFilter};
+    use rfind::SymlinkMode;
+
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path().canonicalize()?;
+    fs::write(base_path.join("big.bin"), vec![0; 4096])?;
+    std::os::unix::fs::symlink("big.bin", base_path.join("link.bin"))?;
+    std::os::unix::fs::symlink("missing.bin", base_path.join("broken.bin"))?;
+
+    let search = |symlink_mode, filter: &Arc<dyn EntryFilter>| {
+        let mut options = SearchOptions::new("*.bin", base_path.clone());
+        options.symlink_mode = symlink_mode;
+        options.filter = Arc::clone(filter);
+        let mut names: Vec<String> = Search::start(options)
+            .unwrap()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    };
+
+    let big: Arc<dyn EntryFilter> = Arc::new(SizeFilter::parse("+1k")?);
+    assert_eq!(search(SymlinkMode::Never, &big), ["big.bin"]);
+    assert_eq!(search(SymlinkMode::Always, &big), ["big.bin", "link.bin"]);
+
+    // Like find -L -type l, only broken links are still links
+    let links: Arc<dyn EntryFilter> = Arc::new(TypeFilter::Symlink);
+    assert_eq!(
+        search(SymlinkMode::Never, &links),
+        ["broken.bin", "link.bin"]
+    );
+    assert_eq!(search(SymlinkMode::Always, &links), ["broken.bin"]);
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_search_is_cancelled_past_max_errors() -> Result<(), Box<dyn std::error::Error>> {
+    use rfind::SymlinkMode;
+
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path().canonicalize()?;
+    // Following a link to itself fails every time
+    for i in 0..5 {
+        let name = format!("loop{}", i);
+        std::os::unix::fs::symlink(&name, base_path.join(&name))?;
+    }
+
+    let search = |max_errors| {
+        let mut options = SearchOptions::new("*", base_path.clone());
+        options.symlink_mode = SymlinkMode::Always;
+        options.threads = 1;
+        options.max_errors = max_errors;
+        let mut search = Search::start(options).unwrap();
+        search.by_ref().count();
+        (search.cancel_token().is_cancelled(), search.stats().errors)
+    };
+
+    assert_eq!(search(None), (false, 5));
+    assert_eq!(search(Some(5)), (false, 5));
+    let (cancelled, errors) = search(Some(2));
+    assert!(cancelled);
+    assert!(errors > 2);
+
+    // Matches found before the abort are still handed out, even when nothing
+    // was taken from the search until after it. The files are listed before
+    // the directory with the failing links is.
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path().canonicalize()?;
+    fs::create_dir(base_path.join("links"))?;
+    for i in 0..5 {
+        fs::write(base_path.join(format!("{}.txt", i)), "")?;
+        let name = format!("loop{}", i);
+        std::os::unix::fs::symlink(&name, base_path.join("links").join(&name))?;
+    }
+    let mut options = SearchOptions::new("*.txt", base_path.clone());
+    options.symlink_mode = SymlinkMode::Always;
+    options.threads = 1;
+    options.max_errors = Some(2);
+    let search = Search::start(options)?;
+    while !search.cancel_token().is_cancelled() {
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    assert_eq!(search.count(), 5);
+
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Command, Stdio};
+use tempfile::TempDir;
+
+fn get(addr: &str, target: &str) -> std::io::Result<String> {
+    let mut stream = TcpStream::connect(addr)?;
+    write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", target, addr)?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    Ok(response)
+}
+
+#[test]
+fn test_serve_search() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new_in(".")?;
+    let base_path = temp_dir.path();
+    fs::create_dir(base_path.join("logs"))?;
+    fs::write(base_path.join("logs/app.log"), "log")?;
+    fs::write(base_path.join("notes.txt"), "notes")?;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rfind"))
+        .args(["serve", "--listen", "127.0.0.1:0", "-d"])
+        .arg(base_path)
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let mut line = String::new();
+    BufReader::new(child.stderr.take().unwrap()).read_line(&mut line)?;
+    let addr = line
+        .trim()
+        .strip_prefix("Listening on http://")
+        .expect("server should announce its address")
+        .to_string();
+
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        let response = get(&addr, "/search?q=%2A.log&type=f")?;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        let body = response.split("\r\n\r\n").nth(1).unwrap_or("");
+        let paths: Vec<serde_json::Value> = body
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(paths.len(), 1);
+        assert!(paths[0]["path"].as_str().unwrap().ends_with("app.log"));
+
+        let response = get(&addr, "/search?q=x&type=bogus")?;
+        assert!(response.starts_with("HTTP/1.1 400"));
+
+        // A bad pattern is refused before any result is sent
+        let response = get(&addr, "/search?q=%5B%2A")?;
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+        assert!(response.contains("Content-Type: application/json"));
+        let body = response.split("\r\n\r\n").nth(1).unwrap_or("");
+        let error: serde_json::Value = serde_json::from_str(body)?;
+        let message = error["error"].as_str().unwrap();
+        assert!(message.starts_with("invalid glob '[*'"), "{}", message);
+
+        // The server is still up
+        let response = get(&addr, "/search?q=notes")?;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+
+        let response = get(&addr, "/nope")?;
+        assert!(response.starts_with("HTTP/1.1 404"));
+        Ok(())
+    })();
+
+    child.kill()?;
+    child.wait()?;
+    result
+}
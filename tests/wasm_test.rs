@@ -0,0 +1,132 @@
+#![cfg(feature = "wasm")]
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+/// Encode a section with its id and length
+fn section(id: u8, contents: &[u8]) -> Vec<u8> {
+    let mut section = vec![id, contents.len() as u8];
+    section.extend_from_slice(contents);
+    section
+}
+
+/// The binary form of the following module, leaving out the `rfind_filter`
+/// export unless `export_filter` is set:
+///
+/// ```wat
+/// (module
+///   (memory (export "memory") 1)
+///   (func (export "rfind_alloc") (param i32) (result i32)
+///     i32.const 1024)
+///   ;; Keep files of more than 3 bytes whose path ends in "t"
+///   (func (export "rfind_filter")
+///     (param $ptr i32) (param $len i32) (param $size i64) (param $kind i32) (param i64)
+///     (result i32)
+///     local.get $kind
+///     i32.const 1
+///     i32.eq
+///     local.get $size
+///     i64.const 3
+///     i64.gt_u
+///     i32.and
+///     local.get $ptr
+///     local.get $len
+///     i32.add
+///     i32.const 1
+///     i32.sub
+///     i32.load8_u
+///     i32.const 0x74
+///     i32.eq
+///     i32.and))
+/// ```
+fn filter_module(export_filter: bool) -> Vec<u8> {
+    let types = [
+        0x02, // two types
+        0x60, 0x01, 0x7f, 0x01, 0x7f, // (i32) -> i32
+        0x60, 0x05, 0x7f, 0x7f, 0x7e, 0x7f, 0x7e, 0x01, 0x7f, // (i32 i32 i64 i32 i64) -> i32
+    ];
+    let functions = [0x02, 0x00, 0x01];
+    let memories = [0x01, 0x00, 0x01];
+
+    let mut exports = vec![if export_filter { 0x03 } else { 0x02 }];
+    exports.extend_from_slice(b"\x06memory\x02\x00");
+    exports.extend_from_slice(b"\x0brfind_alloc\x00\x00");
+    if export_filter {
+        exports.extend_from_slice(b"\x0crfind_filter\x00\x01");
+    }
+
+    let alloc = [
+        0x00, // no locals
+        0x41, 0x80, 0x08, // i32.const 1024
+        0x0b, // end
+    ];
+    let filter = [
+        0x00, // no locals
+        0x20, 0x03, 0x41, 0x01, 0x46, // kind == 1
+        0x20, 0x02, 0x42, 0x03, 0x56, // size > 3
+        0x71, // and
+        0x20, 0x00, 0x20, 0x01, 0x6a, 0x41, 0x01, 0x6b, // ptr + len - 1
+        0x2d, 0x00, 0x00, // i32.load8_u
+        0x41, 0xf4, 0x00, 0x46, // == 't'
+        0x71, // and
+        0x0b, // end
+    ];
+    let mut code = vec![0x02, alloc.len() as u8];
+    code.extend_from_slice(&alloc);
+    code.push(filter.len() as u8);
+    code.extend_from_slice(&filter);
+
+    let mut module = b"\0asm\x01\0\0\0".to_vec();
+    module.extend(section(1, &types));
+    module.extend(section(3, &functions));
+    module.extend(section(5, &memories));
+    module.extend(section(7, &exports));
+    module.extend(section(10, &code));
+    module
+}
+
+fn rfind_with_module(dir: &TempDir, module: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rfind"))
+        .args(["*", "-d", "tree", "--wasm-filter"])
+        .arg(module)
+        .current_dir(dir.path())
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_wasm_filter() {
+    let dir = TempDir::new().unwrap();
+    let tree = dir.path().join("tree");
+    fs::create_dir_all(tree.join("sub.txt")).unwrap();
+    fs::write(tree.join("a.txt"), "keep").unwrap();
+    fs::write(tree.join("short.txt"), "no").unwrap();
+    fs::write(tree.join("b.md"), "skip").unwrap();
+
+    // Entries are kept or skipped by the module
+    let module = dir.path().join("filter.wasm");
+    fs::write(&module, filter_module(true)).unwrap();
+    let output = rfind_with_module(&dir, &module);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let matches: Vec<&str> = stdout.lines().collect();
+    assert_eq!(matches.len(), 1);
+    assert!(matches[0].ends_with("tree/a.txt"));
+
+    // A module without the filter function is refused
+    fs::write(&module, filter_module(false)).unwrap();
+    let output = rfind_with_module(&dir, &module);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("rfind_filter"), "{}", stderr);
+
+    // As is anything that is not a module
+    fs::write(&module, "not wasm").unwrap();
+    let output = rfind_with_module(&dir, &module);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Failed to load"), "{}", stderr);
+}